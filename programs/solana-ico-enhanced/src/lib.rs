@@ -1,7 +1,8 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
-use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::{self, Burn, Mint, Token, TokenAccount, Transfer};
 use pyth_solana_receiver_sdk::price_update::{PriceUpdateV2, get_feed_id_from_hex};
+use solana_keccak_hasher as keccak;
+use solana_secp256k1_recover::secp256k1_recover;
 
 declare_id!("6nTTJwtDuxjv8C1JMsajYQapmPAGrC3QF1w5nu9LXJvt");
 
@@ -27,6 +28,8 @@ pub const SECONDS_PER_YEAR: i64 = 31536000;
 pub const COOLDOWN_PERIOD: i64 = 172800; // 48 hours in seconds
 pub const MAX_REFERRAL_PERCENTAGE: u64 = 20;
 pub const REFERRAL_BONUS_PERCENTAGE: u64 = 10; // 10% for presale referrals
+pub const REFEREE_BONUS_PERCENTAGE: u64 = 5; // 5% for referred buyers (first purchase only)
+pub const MAX_REFERRAL_TIERS: usize = 5;
 
 // Presale Constants (10 stages, prices in USD cents with 4 decimals)
 pub const PRESALE_TOTAL_ALLOCATION: u64 = 102_400_000; // 40% of 256M supply
@@ -57,6 +60,13 @@ pub const APY_TIER_A: u64 = 128; // 365 days
 pub const APY_TIER_B: u64 = 68;  // 182 days
 pub const APY_TIER_C: u64 = 34;  // 90 days
 
+// Reward pool mode (fixed emission shared by tier-weighted stake)
+pub const STAKE_TIER_COUNT: usize = 3;
+pub const REWARD_POOL_PRECISION: u128 = 1_000_000_000_000; // acc_reward_per_share scale
+
+// Early unstake penalty (declines linearly to 0 at unlock)
+pub const MAX_EARLY_UNSTAKE_PENALTY_BPS: u64 = 5000; // 50% max configurable penalty
+
 // Tier lock periods
 pub const LOCK_PERIOD_TIER_A: u64 = 365;
 pub const LOCK_PERIOD_TIER_B: u64 = 182;
@@ -77,6 +87,36 @@ pub const CROSS_CHAIN_MIN_USD_CENTS: u64 = 2500; // $25 minimum per transaction
 pub const CROSS_CHAIN_MAX_USD_CENTS: u64 = 5_000_000; // $50,000 maximum per transaction
 pub const CROSS_CHAIN_MAX_TOTAL_USD_CENTS: u64 = 20_000_000; // $200,000 maximum per user total
 pub const CROSS_CHAIN_COOLDOWN_SECONDS: i64 = 30; // 30 seconds between purchases
+pub const MAX_COMMITTEE_MEMBERS: usize = 7; // Max coordinator committee size (M-of-N attestations)
+pub const MAX_CHAIN_NAME_LEN: usize = 32; // SupportedChain name length
+pub const CROSS_CHAIN_PRICE_TOLERANCE_BPS: u64 = 100; // 1% tolerance on reported NOC amounts
+pub const MAX_CROSS_CHAIN_PRICE_TOLERANCE_BPS: u64 = 500; // 5% max configurable tolerance
+pub const STAGE_QUOTE_WINDOW_SECONDS: i64 = 600; // Previous-stage quotes accepted for 10 minutes
+pub const MAX_COORDINATOR_OVERLAP_SECONDS: i64 = 7 * 24 * 60 * 60; // Old and new coordinator both accepted for at most 7 days
+pub const CROSS_CHAIN_HOURLY_LIMIT_USD_CENTS: u64 = 25_000_000; // $250,000 per rolling hour (all chains)
+pub const CROSS_CHAIN_DAILY_LIMIT_USD_CENTS: u64 = 200_000_000; // $2,000,000 per rolling day (all chains)
+pub const CROSS_CHAIN_CHAIN_HOURLY_LIMIT_USD_CENTS: u64 = 10_000_000; // $100,000 per rolling hour per chain
+pub const CROSS_CHAIN_CHAIN_DAILY_LIMIT_USD_CENTS: u64 = 100_000_000; // $1,000,000 per rolling day per chain
+pub const RATE_LIMIT_HOUR_BUCKETS: usize = 12; // 5-minute buckets
+pub const RATE_LIMIT_DAY_BUCKETS: usize = 24; // 1-hour buckets
+pub const MAX_INDEXED_STAKES: usize = 20; // Coordinator/legacy stake accounts tracked per user
+pub const MAX_FOREIGN_ADDRESS_LEN: usize = 64; // Raw address bytes for non-EVM source chains
+pub const FOREIGN_ALLOCATION_VERSION: u8 = 2; // CrossChainAllocation v1 is EVM-only
+pub const FOREIGN_LINK_TIMELOCK_SECONDS: i64 = 259200; // 72 hours between linking a foreign allocation and claiming
+
+// Wormhole VAA ingestion
+// Core Bridge - PostedVAA accounts must be owned by this program (build with `devnet` for the devnet bridge)
+#[cfg(not(feature = "devnet"))]
+pub const WORMHOLE_CORE_BRIDGE_PROGRAM_ID: Pubkey = pubkey!("worm2ZoG2kUd4vFXhvjh93UUH596ayRfgQ2MgjNMTth");
+#[cfg(feature = "devnet")]
+pub const WORMHOLE_CORE_BRIDGE_PROGRAM_ID: Pubkey = pubkey!("3u8hJUVTA4jH1wYAyUur7FFZVQ8H635K3tSHHF4ssjQ5");
+pub const POSTED_VAA_DISCRIMINATOR: &[u8; 3] = b"vaa";
+pub const POSTED_VAA_HEADER_LEN: usize = 95; // Fixed fields + payload length prefix
+pub const VAA_PURCHASE_PAYLOAD_ID: u8 = 2; // v2 carries the source tx hash and log index
+pub const VAA_PURCHASE_PAYLOAD_LEN: usize = 1 + 20 + 8 + 8 + 1 + 20 + 32 + 4;
+
+// EVM wallet link signatures (EIP-191 personal_sign)
+pub const EVM_SIGNED_MESSAGE_PREFIX: &[u8] = b"\x19Ethereum Signed Message:\n";
 
 #[error_code]
 pub enum ErrorCode {
@@ -179,6 +219,77 @@ pub enum ErrorCode {
     CrossChainUserLimitExceeded,
     #[msg("Cross-chain purchase cooldown active (30s)")]
     CrossChainCooldown,
+    // Referral ledger errors
+    #[msg("Referrer stats and referral record accounts are required for referred purchases")]
+    ReferralAccountsRequired,
+    #[msg("No cross-chain referral rewards to claim")]
+    NoCrossChainReferralRewards,
+    #[msg("Signer is not the Solana wallet linked to this cross-chain account")]
+    WalletNotLinked,
+    #[msg("A Solana wallet is already linked to this allocation")]
+    WalletAlreadyLinked,
+    #[msg("Wallet link is still time-locked")]
+    WalletLinkTimelocked,
+    #[msg("EVM signature does not match the ETH address")]
+    InvalidEvmSignature,
+    #[msg("Coordinator committee is enabled - purchases must be attested")]
+    CoordinatorCommitteeRequired,
+    #[msg("Invalid coordinator committee (members or threshold)")]
+    InvalidCommittee,
+    #[msg("Signer is not a coordinator committee member")]
+    NotCommitteeMember,
+    #[msg("Committee member already attested this purchase")]
+    AlreadyAttested,
+    #[msg("Attestation data does not match its hash")]
+    AttestationMismatch,
+    #[msg("Invalid posted VAA account")]
+    InvalidVaa,
+    #[msg("VAA emitter is not registered for this chain")]
+    UnregisteredEmitter,
+    #[msg("Invalid VAA purchase payload")]
+    InvalidVaaPayload,
+    #[msg("Chain name too long")]
+    InvalidChainName,
+    #[msg("Reported NOC amount does not match the stage price")]
+    CrossChainPriceMismatch,
+    #[msg("Quoted stage is not current or its quote window expired")]
+    InvalidQuotedStage,
+    #[msg("Vault sub-ledger cannot cover this amount - reconcile the vault ledger")]
+    VaultBucketInsufficient,
+    #[msg("Reward vault cannot cover any of these rewards - fund the reward vault")]
+    RewardVaultInsufficient,
+    #[msg("Reward pool mode is already enabled (tier weights are locked)")]
+    RewardPoolEnabled,
+    #[msg("Not a stake account awaiting resize")]
+    InvalidStakeAccountResize,
+    #[msg("Early unstake is disabled")]
+    EarlyUnstakeDisabled,
+    #[msg("Lock period has ended - use initiate_unstake")]
+    LockPeriodEnded,
+    #[msg("Invalid foreign address (length, chain family or hash)")]
+    InvalidForeignAddress,
+    #[msg("Cross-chain purchases paused by the circuit breaker - admin reset required")]
+    CrossChainPaused,
+    #[msg("Cross-chain purchase cannot be reversed (claimed, merged, staked or bonus claimed)")]
+    CrossChainPurchaseNotReversible,
+    #[msg("Cross-chain purchase already reversed")]
+    CrossChainPurchaseAlreadyReversed,
+    #[msg("User stake index is full")]
+    StakeIndexFull,
+    #[msg("Stake account already registered in the user stake index")]
+    StakeAlreadyIndexed,
+    #[msg("Only legacy keypair stake accounts can be registered; PDA stakes are derivable")]
+    NotLegacyStake,
+    #[msg("Pending referral rewards account required when the referrer has no allocation")]
+    PendingReferralAccountRequired,
+    #[msg("No pending referral rewards")]
+    NoPendingReferralRewards,
+    #[msg("Invalid referral tier table")]
+    InvalidReferralTiers,
+    #[msg("Not a presale allocation awaiting resize")]
+    InvalidAllocationResize,
+    #[msg("Vault ledger not reconciled since the upgrade - run reconcile_vault_ledger")]
+    VaultLedgerNotReconciled,
 }
 
 #[program]
@@ -236,11 +347,24 @@ pub mod ico {
         config.total_stakers = 0;
         config.next_stake_id = 1;
         config.referral_reward_percentage = REFERRAL_BONUS_PERCENTAGE;
+        config.referee_bonus_percentage = REFEREE_BONUS_PERCENTAGE;
         config.total_referral_bonuses = 0;
+
+        // Referral tiers by cumulative referred volume: 5% < $10k, 8% < $100k, 12% above
+        config.referral_tiers = [ReferralTier::default(); MAX_REFERRAL_TIERS];
+        config.referral_tiers[0] = ReferralTier { min_referred_usd_cents: 0, percentage: 5 };
+        config.referral_tiers[1] = ReferralTier { min_referred_usd_cents: 1_000_000, percentage: 8 };
+        config.referral_tiers[2] = ReferralTier { min_referred_usd_cents: 10_000_000, percentage: 12 };
+        config.referral_tier_count = 3;
         
         // Cross-chain state
         config.coordinator = Pubkey::default(); // Set via set_coordinator()
+        config.next_coordinator = Pubkey::default(); // Set via schedule_coordinator_rotation()
         config.cross_chain_tokens_sold = 0;
+        config.coordinator_committee_enabled = false; // Set via set_coordinator_committee()
+        config.cross_chain_price_tolerance_bps = CROSS_CHAIN_PRICE_TOLERANCE_BPS;
+        config.stage_quote_window_seconds = STAGE_QUOTE_WINDOW_SECONDS;
+        config.stage_advanced_at = clock.unix_timestamp;
         
         // Purchase limits (0 = use default constants)
         config.max_per_user_usd = 0; // Use PRESALE_MAX_PURCHASE_USD constant
@@ -249,6 +373,9 @@ pub mod ico {
         // SOL treasury - defaults to admin, can be changed to Squads vault
         config.sol_treasury = ctx.accounts.admin.key();
 
+        // Nothing committed yet - the vault sub-ledgers are exact from the start
+        config.vault_ledger_reconciled = true;
+
         msg!("ICO initialized with {} tokens, TGE at {}", ico_amount, tge_timestamp);
         Ok(())
     }
@@ -341,71 +468,45 @@ pub mod ico {
 
         // === CEI PATTERN: EFFECTS FIRST, then INTERACTIONS ===
         
-        // Process referral bonus (10%) - FROM COMMUNITY REWARDS POOL (not presale)
-        // ONE-TIME ONLY: Referral bonus is only given on the FIRST purchase
-        let referral_bonus = if user_allocation.referrer != Pubkey::default() && user_allocation.purchase_count == 0 {
-            let bonus = tokens_to_allocate
-                .checked_mul(REFERRAL_BONUS_PERCENTAGE)
-                .ok_or(ErrorCode::Overflow)?
-                .checked_div(100)
-                .ok_or(ErrorCode::Overflow)?;
-
-            // Check if Community Rewards pool has enough tokens
-            let new_total_referral = config.total_referral_bonuses
-                .checked_add(bonus)
-                .ok_or(ErrorCode::Overflow)?;
-            
-            if new_total_referral > COMMUNITY_REWARDS_ALLOCATION * TOKEN_DECIMALS {
-                // Pool exhausted - no bonus but purchase continues
-                msg!("Referral pool exhausted - no bonus awarded");
-                0
-            } else {
-                // Update referrer's allocation (if provided - check if account is initialized)
-                if ctx.accounts.referrer_allocation.data_is_empty() {
-                    // No referrer - skip bonus
-                    msg!("No referrer provided");
-                    0
-                } else {
-                    // SECURITY: Validate referrer_allocation PDA matches expected seeds
-                    let referrer_pubkey = user_allocation.referrer;
-                    let (expected_pda, _bump) = Pubkey::find_program_address(
-                        &[b"allocation", referrer_pubkey.as_ref()],
-                        ctx.program_id
-                    );
-                    require!(
-                        ctx.accounts.referrer_allocation.key() == expected_pda,
-                        ErrorCode::InvalidPDA
-                    );
-                    
-                    // Try to deserialize referrer allocation
-                    let mut referrer_data = ctx.accounts.referrer_allocation.try_borrow_mut_data()?;
-                    let mut referrer_alloc = PresaleAllocation::try_deserialize(&mut &referrer_data[..])?;
-                    
-                    referrer_alloc.referral_bonus_tokens = referrer_alloc
-                        .referral_bonus_tokens
-                        .checked_add(bonus)
-                        .ok_or(ErrorCode::Overflow)?;
-                    referrer_alloc.total_tokens = referrer_alloc
-                        .total_tokens
-                        .checked_add(bonus)
-                        .ok_or(ErrorCode::Overflow)?;
-                    
-                    // Serialize back
-                    referrer_alloc.try_serialize(&mut &mut referrer_data[..])?;
-                    
-                    // Track total referral bonuses issued (from Community Rewards pool)
-                    config.total_referral_bonuses = new_total_referral;
-                    
-                    msg!("One-time referral bonus awarded: {} tokens", bonus);
-                    bonus
-                }
-            }
-        } else {
-            if user_allocation.purchase_count > 0 {
-                msg!("Referral bonus skipped - not first purchase");
-            }
-            0
+        // Process referral bonus (volume-tiered) - FROM COMMUNITY REWARDS POOL (not presale)
+        // Paid on every purchase by a referred buyer
+        let referrer_volume = ctx
+            .accounts
+            .referrer_stats
+            .as_ref()
+            .map_or(0, |stats| stats.referred_usd_cents);
+        let referred_purchase = ReferredPurchase {
+            referrer: user_allocation.referrer,
+            referee: ctx.accounts.user.key(),
+            tokens: tokens_to_allocate,
+            usd_cents,
+            referrer_volume_usd_cents: referrer_volume,
+            timestamp: clock.unix_timestamp,
         };
+        let referral_bonus = pay_referrer_bonus(
+            config,
+            &referred_purchase,
+            &ctx.accounts.referrer_allocation,
+            false,
+            &mut ctx.accounts.pending_referral_rewards,
+            &mut None,
+            None,
+        )?;
+
+        // Referee bonus for the buyer (first purchase only) - FROM COMMUNITY REWARDS POOL
+        let referee_bonus = apply_referee_bonus(config, user_allocation, tokens_to_allocate)?;
+
+        // Update referrer ledger (counts, referred volume, bonus) on every referred purchase
+        if user_allocation.referrer != Pubkey::default() {
+            record_referral_event(
+                &mut ctx.accounts.referrer_stats,
+                &mut ctx.accounts.referral_record,
+                &mut ctx.accounts.referrer_user_account,
+                &referred_purchase,
+                referral_bonus,
+                referee_bonus,
+            )?;
+        }
 
         // Update user allocation (NO MINTING - just record keeping)
         user_allocation.user = ctx.accounts.user.key();
@@ -413,6 +514,7 @@ pub mod ico {
             .total_tokens
             .checked_add(tokens_to_allocate)
             .ok_or(ErrorCode::Overflow)?;
+        credit_vault_bucket(config, VaultBucket::PresaleUnclaimed, tokens_to_allocate)?;
         user_allocation.total_spent_cents = new_total_spent;
         user_allocation.purchase_count = user_allocation.purchase_count.checked_add(1).ok_or(ErrorCode::Overflow)?;
         
@@ -436,14 +538,7 @@ pub mod ico {
             .ok_or(ErrorCode::Overflow)?;
 
         // Advance stage if current stage is full
-        while config.stage_tokens_sold >= TOKENS_PER_STAGE * TOKEN_DECIMALS && config.current_stage < 9 {
-            config.current_stage += 1;
-            config.stage_tokens_sold = config.stage_tokens_sold
-                .checked_sub(TOKENS_PER_STAGE * TOKEN_DECIMALS)
-                .ok_or(ErrorCode::Overflow)?;
-            
-            msg!("Advanced to stage {}", config.current_stage + 1);
-        }
+        advance_stage_if_full(config, clock.unix_timestamp)?;
 
         // Close presale if hard cap reached
         if config.tokens_sold >= PRESALE_TOTAL_ALLOCATION * TOKEN_DECIMALS {
@@ -467,9 +562,10 @@ pub mod ico {
         )?;
 
         msg!(
-            "Allocation recorded: {} tokens to user, {} referral bonus",
+            "Allocation recorded: {} tokens to user, {} referral bonus, {} referee bonus",
             tokens_to_allocate,
-            referral_bonus
+            referral_bonus,
+            referee_bonus
         );
 
         Ok(())
@@ -486,6 +582,10 @@ pub mod ico {
             &mut ctx.accounts.user_account,
             &mut ctx.accounts.user_allocation,
             &ctx.accounts.referrer_allocation,
+            &mut ctx.accounts.referrer_stats,
+            &mut ctx.accounts.referral_record,
+            &mut ctx.accounts.referrer_user_account,
+            &mut ctx.accounts.pending_referral_rewards,
             &ctx.accounts.stablecoin_ata_for_user,
             &ctx.accounts.stablecoin_ata_for_admin,
             &ctx.accounts.stablecoin_mint,
@@ -507,6 +607,10 @@ pub mod ico {
             &mut ctx.accounts.user_account,
             &mut ctx.accounts.user_allocation,
             &ctx.accounts.referrer_allocation,
+            &mut ctx.accounts.referrer_stats,
+            &mut ctx.accounts.referral_record,
+            &mut ctx.accounts.referrer_user_account,
+            &mut ctx.accounts.pending_referral_rewards,
             &ctx.accounts.stablecoin_ata_for_user,
             &ctx.accounts.stablecoin_ata_for_admin,
             &ctx.accounts.stablecoin_mint,
@@ -531,6 +635,12 @@ pub mod ico {
             &mut ctx.accounts.user_account,
             &mut ctx.accounts.user_allocation,
             &mut ctx.accounts.stake_account,
+            &ctx.accounts.referrer_allocation,
+            &mut ctx.accounts.referrer_stats,
+            &mut ctx.accounts.referral_record,
+            &mut ctx.accounts.referrer_user_account,
+            &mut ctx.accounts.pending_referral_rewards,
+            &mut ctx.accounts.referrer_vesting_stake,
             &ctx.accounts.stablecoin_ata_for_user,
             &ctx.accounts.stablecoin_ata_for_admin,
             &ctx.accounts.user,
@@ -556,6 +666,12 @@ pub mod ico {
             &mut ctx.accounts.user_account,
             &mut ctx.accounts.user_allocation,
             &mut ctx.accounts.stake_account,
+            &ctx.accounts.referrer_allocation,
+            &mut ctx.accounts.referrer_stats,
+            &mut ctx.accounts.referral_record,
+            &mut ctx.accounts.referrer_user_account,
+            &mut ctx.accounts.pending_referral_rewards,
+            &mut ctx.accounts.referrer_vesting_stake,
             &ctx.accounts.stablecoin_ata_for_user,
             &ctx.accounts.stablecoin_ata_for_admin,
             &ctx.accounts.user,
@@ -569,7 +685,7 @@ pub mod ico {
 
     /// Claim presale allocation at/after TGE
     pub fn claim_presale_allocation(ctx: Context<ClaimPresaleAllocation>) -> Result<()> {
-        let config = &ctx.accounts.config;
+        let config = &mut ctx.accounts.config;
         let clock = Clock::get()?;
         let user_allocation = &mut ctx.accounts.user_allocation;
 
@@ -581,14 +697,13 @@ pub mod ico {
         );
 
         // Validate allocation exists and not claimed
-        require!(user_allocation.total_tokens > 0, ErrorCode::InvalidAmount);
+        let tokens_to_claim = user_allocation.claimable_tokens()?;
+        require!(tokens_to_claim > 0, ErrorCode::InvalidAmount);
         require!(!user_allocation.claimed, ErrorCode::AllocationAlreadyClaimed);
 
-        // Store tokens to transfer (need before state change)
-        let tokens_to_claim = user_allocation.total_tokens;
-
         // === EFFECTS (state changes first) ===
         user_allocation.claimed = true;
+        debit_vault_bucket(config, VaultBucket::PresaleUnclaimed, tokens_to_claim)?;
 
         // === INTERACTIONS (external calls last) ===
         // NOW mint the tokens (first time tokens actually exist)
@@ -630,7 +745,7 @@ pub mod ico {
     /// Only admin can call this - transfers from vault to user's wallet
     /// Sets claimed = true on user's allocation for proper tracking
     pub fn admin_claim_for_user(ctx: Context<AdminClaimForUser>) -> Result<()> {
-        let config = &ctx.accounts.config;
+        let config = &mut ctx.accounts.config;
         let clock = Clock::get()?;
         let user_allocation = &mut ctx.accounts.user_allocation;
 
@@ -642,14 +757,13 @@ pub mod ico {
         );
 
         // Validate allocation exists and not already claimed
-        require!(user_allocation.total_tokens > 0, ErrorCode::InvalidAmount);
+        let tokens_to_claim = user_allocation.claimable_tokens()?;
+        require!(tokens_to_claim > 0, ErrorCode::InvalidAmount);
         require!(!user_allocation.claimed, ErrorCode::AllocationAlreadyClaimed);
 
-        // Store tokens to transfer (need before state change)
-        let tokens_to_claim = user_allocation.total_tokens;
-
         // === EFFECTS (state changes first) ===
         user_allocation.claimed = true;
+        debit_vault_bucket(config, VaultBucket::PresaleUnclaimed, tokens_to_claim)?;
 
         // === INTERACTIONS (external calls last) ===
         // Transfer tokens from vault to user
@@ -729,6 +843,7 @@ pub mod ico {
             user_allocation.claimed = false;
             user_allocation.referrer = Pubkey::default();
             user_allocation.referral_bonus_tokens = 0;
+            user_allocation.vest_staked_tokens = 0;
             user_allocation.purchase_count = 0;
             user_allocation.first_purchase_at = 0;
             user_allocation.last_purchase_at = 0;
@@ -749,6 +864,7 @@ pub mod ico {
         // Update config to track total giveaways issued
         let config = &mut ctx.accounts.config;
         config.total_referral_bonuses = new_total_giveaway;
+        credit_vault_bucket(config, VaultBucket::PresaleUnclaimed, token_amount)?;
 
         msg!(
             "ADMIN_GIVEAWAY: Added {} tokens to user {} (total allocation: {})",
@@ -777,9 +893,8 @@ pub mod ico {
 
         // Validate not already claimed
         require!(!user_allocation.claimed, ErrorCode::AllocationAlreadyClaimed);
-        require!(user_allocation.total_tokens > 0, ErrorCode::InvalidAmount);
-
-        let amount = user_allocation.total_tokens;
+        let amount = user_allocation.claimable_tokens()?;
+        require!(amount > 0, ErrorCode::InvalidAmount);
 
         // Check global staking cap
         require!(
@@ -795,8 +910,9 @@ pub mod ico {
             );
         }
 
-        // Mark as claimed
+        // Mark as claimed - tokens move from the presale bucket to staked principal
         user_allocation.claimed = true;
+        debit_vault_bucket(config, VaultBucket::PresaleUnclaimed, amount)?;
 
         // Transfer tokens to staking vault (NOT to user wallet)
         let ico_mint_key = ctx.accounts.ico_mint.key();
@@ -858,6 +974,7 @@ pub mod ico {
                 .checked_add(amount)
                 .ok_or(ErrorCode::Overflow)?;
         }
+        add_pool_stake(config, stake_account, amount, clock.unix_timestamp)?;
 
         if !ctx.accounts.user_account.has_staked {
             ctx.accounts.user_account.has_staked = true;
@@ -906,6 +1023,144 @@ pub mod ico {
         Ok(())
     }
 
+    /// View function for the referral dashboard (anyone can call)
+    pub fn get_referrer_stats(ctx: Context<GetReferrerStats>) -> Result<()> {
+        let stats = &ctx.accounts.referrer_stats;
+
+        msg!(
+            "Referrer Stats - Referrer: {}, Referrals: {}, Referred USD (cents): {}, Referred tokens: {}, Bonus tokens: {}, Last referral at: {}",
+            stats.referrer,
+            stats.total_referrals,
+            stats.referred_usd_cents,
+            stats.referred_tokens,
+            stats.bonus_tokens,
+            stats.last_referral_at
+        );
+
+        Ok(())
+    }
+
+    /// Referrer opts in (or out) of receiving referral bonuses as vesting stake in the given tier
+    /// Falls back to the presale allocation when the stake is missing, inactive or capped
+    pub fn set_referral_bonus_preference(
+        ctx: Context<SetReferralBonusPreference>,
+        as_stake: bool,
+        tier: StakeTier,
+    ) -> Result<()> {
+        let stats = &mut ctx.accounts.referrer_stats;
+
+        if stats.referrer == Pubkey::default() {
+            stats.referrer = ctx.accounts.referrer.key();
+        }
+        stats.bonus_as_stake = as_stake;
+        stats.bonus_stake_tier = tier;
+
+        msg!("Referral bonus preference updated: as stake = {}, tier = {:?}", as_stake, tier);
+        Ok(())
+    }
+
+    /// Sweep escrowed referral bonuses into the referrer's presale allocation (before claiming)
+    pub fn sweep_pending_referral_rewards(ctx: Context<SweepPendingReferralRewards>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let pending = &mut ctx.accounts.pending_referral_rewards;
+        let referrer_allocation = &mut ctx.accounts.referrer_allocation;
+
+        // === CHECKS ===
+        require!(pending.pending_tokens > 0, ErrorCode::NoPendingReferralRewards);
+        require!(!referrer_allocation.claimed, ErrorCode::AllocationAlreadyClaimed);
+
+        let amount = pending.pending_tokens;
+
+        // === EFFECTS ===
+        pending.pending_tokens = 0;
+        pending.total_swept = pending
+            .total_swept
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        if referrer_allocation.user == Pubkey::default() {
+            referrer_allocation.user = ctx.accounts.referrer.key();
+        }
+        referrer_allocation.referral_bonus_tokens = referrer_allocation
+            .referral_bonus_tokens
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        referrer_allocation.total_tokens = referrer_allocation
+            .total_tokens
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        // Escrowed bonus becomes an unclaimed presale allocation
+        debit_vault_bucket(config, VaultBucket::CommunityReserved, amount)?;
+        credit_vault_bucket(config, VaultBucket::PresaleUnclaimed, amount)?;
+
+        msg!(
+            "Swept {} pending referral tokens into allocation of {}",
+            amount,
+            ctx.accounts.referrer.key()
+        );
+
+        Ok(())
+    }
+
+    /// Claim escrowed referral bonuses as tokens at/after TGE
+    pub fn claim_pending_referral_rewards(ctx: Context<ClaimPendingReferralRewards>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+        let pending = &mut ctx.accounts.pending_referral_rewards;
+
+        // === CHECKS ===
+        require!(
+            clock.unix_timestamp >= config.tge_timestamp,
+            ErrorCode::TokensLocked
+        );
+        require!(pending.pending_tokens > 0, ErrorCode::NoPendingReferralRewards);
+
+        let amount = pending.pending_tokens;
+
+        // === EFFECTS (state changes first) ===
+        pending.pending_tokens = 0;
+        pending.total_claimed = pending
+            .total_claimed
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        debit_vault_bucket(config, VaultBucket::CommunityReserved, amount)?;
+
+        // === INTERACTIONS (external calls last) ===
+        let ico_mint_key = ctx.accounts.ico_mint.key();
+        let (expected_pda, bump) = Pubkey::find_program_address(
+            &[ico_mint_key.as_ref()],
+            ctx.program_id,
+        );
+
+        require!(
+            ctx.accounts.ico_ata_for_ico_program.key() == expected_pda,
+            ErrorCode::InvalidPDA
+        );
+
+        let seeds = &[ico_mint_key.as_ref(), &[bump]];
+        let signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.ico_ata_for_ico_program.to_account_info(),
+                to: ctx.accounts.ico_ata_for_referrer.to_account_info(),
+                authority: ctx.accounts.ico_ata_for_ico_program.to_account_info(),
+            },
+            signer,
+        );
+        token::transfer(cpi_ctx, amount)?;
+
+        msg!(
+            "Claimed {} pending referral tokens for {}",
+            amount,
+            ctx.accounts.referrer.key()
+        );
+
+        Ok(())
+    }
+
     // =====================================================
     // VESTING STAKING - PRESALE WITH AUTOMATIC STAKING
     // Tokens are minted and immediately staked (locked until TGE)
@@ -988,6 +1243,44 @@ pub mod ico {
         // Calculate tokens based on current stage price
         let tokens_to_stake = calculate_tokens_for_usd(usd_cents, config.current_stage)?;
 
+        // Process referral bonuses exactly like the allocation-only paths
+        // Referrers that opted in receive their bonus as additional vesting stake
+        let referrer_volume = ctx
+            .accounts
+            .referrer_stats
+            .as_ref()
+            .map_or(0, |stats| stats.referred_usd_cents);
+        let stake_tier_preference = referral_stake_preference(&ctx.accounts.referrer_stats);
+        let referred_purchase = ReferredPurchase {
+            referrer: user_allocation.referrer,
+            referee: ctx.accounts.user.key(),
+            tokens: tokens_to_stake,
+            usd_cents,
+            referrer_volume_usd_cents: referrer_volume,
+            timestamp: clock.unix_timestamp,
+        };
+        let referral_bonus = pay_referrer_bonus(
+            config,
+            &referred_purchase,
+            &ctx.accounts.referrer_allocation,
+            false,
+            &mut ctx.accounts.pending_referral_rewards,
+            &mut ctx.accounts.referrer_vesting_stake,
+            stake_tier_preference,
+        )?;
+        let referee_bonus = apply_referee_bonus(config, user_allocation, tokens_to_stake)?;
+
+        if user_allocation.referrer != Pubkey::default() {
+            record_referral_event(
+                &mut ctx.accounts.referrer_stats,
+                &mut ctx.accounts.referral_record,
+                &mut ctx.accounts.referrer_user_account,
+                &referred_purchase,
+                referral_bonus,
+                referee_bonus,
+            )?;
+        }
+
         // Check global staking cap
         require!(
             config.total_staked.checked_add(tokens_to_stake).ok_or(ErrorCode::Overflow)? <= MAX_TOTAL_STAKED,
@@ -1017,6 +1310,10 @@ pub mod ico {
             .total_tokens
             .checked_add(tokens_to_stake)
             .ok_or(ErrorCode::Overflow)?;
+        user_allocation.vest_staked_tokens = user_allocation
+            .vest_staked_tokens
+            .checked_add(tokens_to_stake)
+            .ok_or(ErrorCode::Overflow)?;
         user_allocation.total_spent_cents = new_total_spent;
         user_allocation.purchase_count = user_allocation.purchase_count.checked_add(1).ok_or(ErrorCode::Overflow)?;
         
@@ -1051,35 +1348,7 @@ pub mod ico {
             msg!("Created new vesting stake: {} tokens in {:?} tier", tokens_to_stake, tier);
         } else {
             // Existing stake - calculate pending rewards first, then add new tokens
-            let apy = match stake_account.tier {
-                StakeTier::TierA => APY_TIER_A,
-                StakeTier::TierB => APY_TIER_B,
-                StakeTier::TierC => APY_TIER_C,
-            };
-            
-            let time_elapsed = clock.unix_timestamp
-                .checked_sub(stake_account.last_reward_calculation)
-                .ok_or(ErrorCode::Overflow)?;
-            
-            if time_elapsed > 0 {
-                let rewards = (stake_account.amount as u128)
-                    .checked_mul(apy as u128)
-                    .ok_or(ErrorCode::Overflow)?
-                    .checked_mul(time_elapsed as u128)
-                    .ok_or(ErrorCode::Overflow)?
-                    .checked_div(100 * SECONDS_PER_YEAR as u128)
-                    .ok_or(ErrorCode::Overflow)? as u64;
-                
-                if stake_account.auto_compound {
-                    stake_account.amount = stake_account.amount
-                        .checked_add(rewards)
-                        .ok_or(ErrorCode::Overflow)?;
-                } else {
-                    stake_account.pending_rewards = stake_account.pending_rewards
-                        .checked_add(rewards)
-                        .ok_or(ErrorCode::Overflow)?;
-                }
-            }
+            checkpoint_stake_rewards(config, stake_account, clock.unix_timestamp)?;
             
             // Add new tokens to existing stake
             stake_account.amount = stake_account.amount
@@ -1106,6 +1375,7 @@ pub mod ico {
                 .checked_add(tokens_to_stake)
                 .ok_or(ErrorCode::Overflow)?;
         }
+        add_pool_stake(config, stake_account, tokens_to_stake, clock.unix_timestamp)?;
 
         if !ctx.accounts.user_account.has_staked {
             ctx.accounts.user_account.has_staked = true;
@@ -1130,13 +1400,7 @@ pub mod ico {
             .ok_or(ErrorCode::Overflow)?;
 
         // Advance stage if current stage is full
-        while config.stage_tokens_sold >= TOKENS_PER_STAGE * TOKEN_DECIMALS && config.current_stage < 9 {
-            config.current_stage += 1;
-            config.stage_tokens_sold = config.stage_tokens_sold
-                .checked_sub(TOKENS_PER_STAGE * TOKEN_DECIMALS)
-                .ok_or(ErrorCode::Overflow)?;
-            msg!("Advanced to stage {}", config.current_stage + 1);
-        }
+        advance_stage_if_full(config, clock.unix_timestamp)?;
 
         // Close presale if hard cap reached
         if config.tokens_sold >= PRESALE_TOTAL_ALLOCATION * TOKEN_DECIMALS {
@@ -1252,6 +1516,7 @@ pub mod ico {
                 .checked_add(amount)
                 .ok_or(ErrorCode::Overflow)?;
         }
+        add_pool_stake(config, stake_account, amount, clock.unix_timestamp)?;
 
         if !ctx.accounts.user_account.has_staked {
             ctx.accounts.user_account.has_staked = true;
@@ -1303,6 +1568,7 @@ pub mod ico {
         require!(ctx.accounts.stake_account.active, ErrorCode::StakeNotActive);
 
         let clock = Clock::get()?;
+        update_reward_pool(&mut ctx.accounts.config, clock.unix_timestamp)?;
         let rewards = calculate_rewards_internal(
             &ctx.accounts.config,
             &ctx.accounts.stake_account,
@@ -1311,67 +1577,109 @@ pub mod ico {
 
         require!(rewards > 0, ErrorCode::NoRewards);
 
+        // Rewards are paid only from the reward vault; any shortfall stays pending
+        let config = &mut ctx.accounts.config;
+        let paid = payable_rewards(config, &ctx.accounts.reward_vault, rewards);
+        require!(paid > 0, ErrorCode::RewardVaultInsufficient);
+
+        // === CEI: EFFECTS FIRST (state changes before transfer) ===
         let stake_account = &mut ctx.accounts.stake_account;
+        let penalty_share = unbooked_penalty_share(config, stake_account)?;
+        settle_reward_liability(config, stake_account, rewards, penalty_share, paid)?;
+        stake_account.last_reward_calculation = clock.unix_timestamp;
+        reset_reward_debt(config, stake_account)?;
 
         // Handle auto-compound
-        if stake_account.auto_compound {
-            // === CEI: EFFECTS ===
-            // Add rewards to principal
+        let destination = if stake_account.auto_compound {
+            // Compounded rewards move from the reward vault into staked principal (ICO vault)
             stake_account.amount = stake_account
                 .amount
-                .checked_add(rewards)
+                .checked_add(paid)
                 .ok_or(ErrorCode::Overflow)?;
-            stake_account.pending_rewards = 0;
-            stake_account.last_reward_calculation = clock.unix_timestamp;
+            config.total_staked = config
+                .total_staked
+                .checked_add(paid)
+                .ok_or(ErrorCode::Overflow)?;
+            if stake_account.tier == StakeTier::TierA {
+                config.total_staked_tier_a = config
+                    .total_staked_tier_a
+                    .checked_add(paid)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
+            add_pool_stake(config, stake_account, paid, clock.unix_timestamp)?;
 
-            msg!("Auto-compounded {} rewards into stake", rewards);
+            msg!("Auto-compounded {} rewards into stake", paid);
+            ctx.accounts.ico_ata_for_ico_program.to_account_info()
         } else {
-            // === CEI: EFFECTS FIRST (state changes before transfer) ===
-            stake_account.pending_rewards = 0;
-            stake_account.last_reward_calculation = clock.unix_timestamp;
-            ctx.accounts.config.total_rewards_distributed = ctx
-                .accounts
-                .config
+            config.total_rewards_distributed = config
                 .total_rewards_distributed
-                .checked_add(rewards)
+                .checked_add(paid)
                 .ok_or(ErrorCode::Overflow)?;
 
-            // === CEI: INTERACTIONS LAST ===
-            // Transfer rewards to user
-            let ico_mint_key = ctx.accounts.ico_mint.key();
-            let (expected_pda, bump) = Pubkey::find_program_address(
-                &[ico_mint_key.as_ref()],
-                ctx.program_id,
-            );
-            
-            require!(
-                ctx.accounts.ico_ata_for_ico_program.key() == expected_pda,
-                ErrorCode::InvalidPDA
-            );
-            
-            let seeds = &[ico_mint_key.as_ref(), &[bump]];
-            let signer = &[&seeds[..]];
+            msg!("Harvested {} rewards", paid);
+            ctx.accounts.ico_ata_for_user.to_account_info()
+        };
+        if paid < rewards {
+            msg!("Reward vault short - {} rewards left pending", rewards - paid);
+        }
 
-            let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.ico_ata_for_ico_program.to_account_info(),
-                    to: ctx.accounts.ico_ata_for_user.to_account_info(),
-                    authority: ctx.accounts.ico_ata_for_ico_program.to_account_info(),
-                },
-                signer,
-            );
-            token::transfer(cpi_ctx, rewards)?;
+        // === CEI: INTERACTIONS LAST ===
+        transfer_from_reward_vault(
+            &ctx.accounts.reward_vault,
+            destination,
+            &ctx.accounts.token_program,
+            ctx.accounts.ico_mint.key(),
+            ctx.bumps.reward_vault,
+            paid,
+        )?;
+
+        Ok(())
+    }
+
+    /// Claim rewards left unpaid on a closed stake because the reward vault was short at unstake
+    pub fn claim_unpaid_rewards(ctx: Context<ClaimUnpaidRewards>) -> Result<()> {
+        let stake_account = &ctx.accounts.stake_account;
+        require!(
+            stake_account.owner == ctx.accounts.user.key(),
+            ErrorCode::NotStakeOwner
+        );
+        require!(!stake_account.active, ErrorCode::InvalidAmount);
+        let owed = stake_account.pending_rewards;
+        require!(owed > 0, ErrorCode::NoRewards);
+
+        let config = &mut ctx.accounts.config;
+        let paid = payable_rewards(config, &ctx.accounts.reward_vault, owed);
+        require!(paid > 0, ErrorCode::RewardVaultInsufficient);
+
+        // === CEI: EFFECTS FIRST (state changes before transfer) ===
+        settle_reward_liability(config, &mut ctx.accounts.stake_account, owed, 0, paid)?;
+        config.total_rewards_distributed = config
+            .total_rewards_distributed
+            .checked_add(paid)
+            .ok_or(ErrorCode::Overflow)?;
+
+        // === CEI: INTERACTIONS LAST ===
+        transfer_from_reward_vault(
+            &ctx.accounts.reward_vault,
+            ctx.accounts.ico_ata_for_user.to_account_info(),
+            &ctx.accounts.token_program,
+            ctx.accounts.ico_mint.key(),
+            ctx.bumps.reward_vault,
+            paid,
+        )?;
 
-            msg!("Harvested {} rewards", rewards);
+        if let Some(index) = ctx.accounts.stake_index.as_mut() {
+            unindex_closed_stake(index, &ctx.accounts.stake_account);
         }
 
+        msg!("Claimed {} unpaid rewards ({} still pending)", paid, owed - paid);
         Ok(())
     }
 
-    /// Initiate unstake - starts 48-hour cooldown
+    /// Initiate unstake of `amount` - starts 48-hour cooldown for that portion
+    /// The rest stays staked under the same tier and start time
     /// Vesting stakes can only be unstaked after TGE
-    pub fn initiate_unstake(ctx: Context<InitiateUnstake>) -> Result<()> {
+    pub fn initiate_unstake(ctx: Context<InitiateUnstake>, amount: u64) -> Result<()> {
         require!(
             !ctx.accounts.user_account.is_blocked,
             ErrorCode::AddressBlocked
@@ -1383,40 +1691,12 @@ pub mod ico {
         require!(ctx.accounts.stake_account.active, ErrorCode::StakeNotActive);
 
         let clock = Clock::get()?;
-        let stake_account = &ctx.accounts.stake_account;
-        let config = &ctx.accounts.config;
-
-        // VESTING CHECK: If this is a vesting stake, check if TGE has passed
-        if stake_account.is_vesting {
-            require!(
-                clock.unix_timestamp >= config.tge_timestamp,
-                ErrorCode::VestingStakeLocked
-            );
-            msg!("Vesting stake - TGE has passed, unstake allowed");
-        }
-
-        // Check if lock period has passed
-        let unlock_time = stake_account
-            .start_time
-            .checked_add(
-                (stake_account.lock_period_days as i64)
-                    .checked_mul(SECONDS_PER_DAY)
-                    .ok_or(ErrorCode::Overflow)?,
-            )
-            .ok_or(ErrorCode::Overflow)?;
-
-        require!(
-            clock.unix_timestamp >= unlock_time,
-            ErrorCode::StillInLockPeriod
-        );
-
-        let stake_account = &mut ctx.accounts.stake_account;
-        stake_account.cooldown_start = clock.unix_timestamp;
-
-        msg!(
-            "Unstake initiated for stake {}. Can finalize after 48 hours.",
-            stake_account.stake_id
-        );
+        begin_unstake_cooldown(
+            &mut ctx.accounts.config,
+            &mut ctx.accounts.stake_account,
+            amount,
+            clock.unix_timestamp,
+        )?;
 
         Ok(())
     }
@@ -1434,90 +1714,175 @@ pub mod ico {
         require!(ctx.accounts.stake_account.active, ErrorCode::StakeNotActive);
 
         let clock = Clock::get()?;
-        let stake_account = &ctx.accounts.stake_account;
+        settle_unstake(
+            &mut ctx.accounts.config,
+            &mut ctx.accounts.stake_account,
+            &ctx.accounts.ico_ata_for_ico_program,
+            ctx.accounts.reward_vault.as_ref().zip(ctx.bumps.reward_vault),
+            &ctx.accounts.ico_ata_for_user,
+            &ctx.accounts.token_program,
+            clock.unix_timestamp,
+        )?;
+        if let Some(index) = ctx.accounts.stake_index.as_mut() {
+            unindex_closed_stake(index, &ctx.accounts.stake_account);
+        }
 
-        // Validate cooldown period has passed
-        require!(stake_account.cooldown_start > 0, ErrorCode::InvalidAmount);
-        let cooldown_end = stake_account
-            .cooldown_start
-            .checked_add(COOLDOWN_PERIOD)
-            .ok_or(ErrorCode::Overflow)?;
+        Ok(())
+    }
+
+    /// Exit a stake before its lock period ends, skipping the cooldown, for a penalty on principal
+    /// The penalty is split between a burn, the treasury and the remaining stakers of the tier
+    pub fn early_unstake(ctx: Context<EarlyUnstake>) -> Result<()> {
+        require!(
+            !ctx.accounts.user_account.is_blocked,
+            ErrorCode::AddressBlocked
+        );
+        require!(
+            ctx.accounts.stake_account.owner == ctx.accounts.user.key(),
+            ErrorCode::NotStakeOwner
+        );
+        require!(ctx.accounts.stake_account.active, ErrorCode::StakeNotActive);
         require!(
-            clock.unix_timestamp >= cooldown_end,
-            ErrorCode::StillInCooldown
+            ctx.accounts.config.early_unstake_max_penalty_bps > 0,
+            ErrorCode::EarlyUnstakeDisabled
         );
 
-        // Calculate final rewards
-        let rewards = calculate_rewards_internal(
-            &ctx.accounts.config,
+        let clock = Clock::get()?;
+        let config = &mut ctx.accounts.config;
+        let stake_account = &mut ctx.accounts.stake_account;
+        if stake_account.is_vesting {
+            require!(
+                clock.unix_timestamp >= config.tge_timestamp,
+                ErrorCode::VestingStakeLocked
+            );
+        }
+        let penalty_bps = early_unstake_penalty_bps(config, stake_account, clock.unix_timestamp)?;
+        let tier = stake_account.tier;
+
+        // === CEI: EFFECTS FIRST (state changes before transfers) ===
+        let (stake_amount, paid, rewards) = deactivate_stake(
+            config,
             stake_account,
+            Some(&ctx.accounts.reward_vault),
             clock.unix_timestamp,
         )?;
 
-        let stake_amount = stake_account.amount;
-        let tier = stake_account.tier;
-        let total_amount = stake_amount
-            .checked_add(rewards)
+        let penalty = (stake_amount as u128)
+            .checked_mul(penalty_bps as u128)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_div(10_000)
+            .ok_or(ErrorCode::Overflow)? as u64;
+        let burned = (penalty as u128)
+            .checked_mul(config.penalty_burn_bps as u128)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_div(10_000)
+            .ok_or(ErrorCode::Overflow)? as u64;
+        let mut to_treasury = (penalty as u128)
+            .checked_mul(config.penalty_treasury_bps as u128)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_div(10_000)
+            .ok_or(ErrorCode::Overflow)? as u64;
+        let mut to_stakers = penalty
+            .checked_sub(burned)
+            .and_then(|rest| rest.checked_sub(to_treasury))
             .ok_or(ErrorCode::Overflow)?;
 
-        // Deactivate stake
-        let stake_account = &mut ctx.accounts.stake_account;
-        stake_account.active = false;
+        // Stakers' share accrues as rewards to the tier's remaining stake (paid from the reward vault)
+        // and is booked as reward liability right away. Only stakes counted in tier_staked share it:
+        // legacy stakes not yet resized are excluded until resize_stake_account counts them.
+        let remaining_staked = config.tier_staked[tier as usize];
+        if to_stakers > 0 && remaining_staked == 0 {
+            to_treasury = to_treasury.checked_add(to_stakers).ok_or(ErrorCode::Overflow)?;
+            to_stakers = 0;
+        }
+        if to_stakers > 0 {
+            let per_share = (to_stakers as u128)
+                .checked_mul(REWARD_POOL_PRECISION)
+                .ok_or(ErrorCode::Overflow)?
+                .checked_div(remaining_staked as u128)
+                .ok_or(ErrorCode::Overflow)?;
+            config.tier_penalty_acc_per_share[tier as usize] = config.tier_penalty_acc_per_share[tier as usize]
+                .checked_add(per_share)
+                .ok_or(ErrorCode::Overflow)?;
+            config.reward_budget = config
+                .reward_budget
+                .checked_add(to_stakers)
+                .ok_or(ErrorCode::Overflow)?;
+            config.total_reward_liability = config
+                .total_reward_liability
+                .checked_add(to_stakers)
+                .ok_or(ErrorCode::Overflow)?;
+        }
 
-        // Update config
-        let config = &mut ctx.accounts.config;
-        config.total_staked = config
-            .total_staked
-            .checked_sub(stake_amount)
+        config.total_penalty_collected = config
+            .total_penalty_collected
+            .checked_add(penalty)
             .ok_or(ErrorCode::Overflow)?;
 
-        if tier == StakeTier::TierA {
-            config.total_staked_tier_a = config
-                .total_staked_tier_a
-                .checked_sub(stake_amount)
-                .ok_or(ErrorCode::Overflow)?;
-        }
-
-        if rewards > 0 {
-            config.total_rewards_distributed = config
-                .total_rewards_distributed
-                .checked_add(rewards)
-                .ok_or(ErrorCode::Overflow)?;
-        }
-
-        // Transfer principal + rewards to user
+        // === CEI: INTERACTIONS LAST ===
         let ico_mint_key = ctx.accounts.ico_mint.key();
-        let (expected_pda, bump) = Pubkey::find_program_address(
-            &[ico_mint_key.as_ref()],
-            ctx.program_id,
-        );
-        
-        require!(
-            ctx.accounts.ico_ata_for_ico_program.key() == expected_pda,
-            ErrorCode::InvalidPDA
-        );
-        
+        let bump = ctx.bumps.ico_ata_for_ico_program;
         let seeds = &[ico_mint_key.as_ref(), &[bump]];
         let signer = &[&seeds[..]];
+        let returned = stake_amount.checked_sub(penalty).ok_or(ErrorCode::Overflow)?;
+        for (destination, amount) in [
+            (ctx.accounts.ico_ata_for_user.to_account_info(), returned),
+            (ctx.accounts.penalty_treasury.to_account_info(), to_treasury),
+            (ctx.accounts.reward_vault.to_account_info(), to_stakers),
+        ] {
+            if amount == 0 {
+                continue;
+            }
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.ico_ata_for_ico_program.to_account_info(),
+                    to: destination,
+                    authority: ctx.accounts.ico_ata_for_ico_program.to_account_info(),
+                },
+                signer,
+            );
+            token::transfer(cpi_ctx, amount)?;
+        }
 
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.ico_ata_for_ico_program.to_account_info(),
-                to: ctx.accounts.ico_ata_for_user.to_account_info(),
-                authority: ctx.accounts.ico_ata_for_ico_program.to_account_info(),
-            },
-            signer,
-        );
-        token::transfer(cpi_ctx, total_amount)?;
+        if burned > 0 {
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Burn {
+                    mint: ctx.accounts.ico_mint.to_account_info(),
+                    from: ctx.accounts.ico_ata_for_ico_program.to_account_info(),
+                    authority: ctx.accounts.ico_ata_for_ico_program.to_account_info(),
+                },
+                signer,
+            );
+            token::burn(cpi_ctx, burned)?;
+        }
+
+        if paid > 0 {
+            transfer_from_reward_vault(
+                &ctx.accounts.reward_vault,
+                ctx.accounts.ico_ata_for_user.to_account_info(),
+                &ctx.accounts.token_program,
+                ico_mint_key,
+                ctx.bumps.reward_vault,
+                paid,
+            )?;
+        }
+        if let Some(index) = ctx.accounts.stake_index.as_mut() {
+            unindex_closed_stake(index, &ctx.accounts.stake_account);
+        }
 
         msg!(
-            "Unstaked {} tokens with {} rewards (total: {})",
+            "Early unstaked {} tokens: penalty {} ({} bps; burned {}, treasury {}, stakers {}), rewards {} ({} left pending)",
             stake_amount,
-            rewards,
-            total_amount
+            penalty,
+            penalty_bps,
+            burned,
+            to_treasury,
+            to_stakers,
+            paid,
+            rewards - paid
         );
-
         Ok(())
     }
 
@@ -1587,6 +1952,118 @@ pub mod ico {
         Ok(())
     }
 
+    /// Grow a stake account created before the current StakeAccount layout (anyone can pay)
+    /// Legacy stakes cannot be loaded by any other instruction until resized. Active stakes not
+    /// yet counted toward reward pool weighting (pool_counted) are counted once.
+    pub fn resize_stake_account(ctx: Context<ResizeStakeAccount>) -> Result<()> {
+        let new_size = 8 + StakeAccount::SPACE;
+        let grow = {
+            let data = ctx.accounts.stake_account.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && &data[..8] == StakeAccount::DISCRIMINATOR,
+                ErrorCode::InvalidStakeAccountResize
+            );
+            data.len() < new_size
+        };
+
+        if grow {
+            let rent = Rent::get()?;
+            let lamports_diff = rent
+                .minimum_balance(new_size)
+                .saturating_sub(ctx.accounts.stake_account.lamports());
+            if lamports_diff > 0 {
+                let cpi_context = CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: ctx.accounts.stake_account.clone(),
+                    },
+                );
+                anchor_lang::system_program::transfer(cpi_context, lamports_diff)?;
+            }
+            ctx.accounts.stake_account.resize(new_size)?;
+        }
+
+        // The flag lives in the account itself, so repeated or later resizes never re-count
+        let mut stake = {
+            let data = ctx.accounts.stake_account.try_borrow_data()?;
+            StakeAccount::try_deserialize(&mut &data[..])?
+        };
+        let count = stake.active && !stake.pool_counted;
+        require!(grow || count, ErrorCode::InvalidStakeAccountResize);
+        if count {
+            let clock = Clock::get()?;
+            let amount = stake.earning_amount();
+            add_pool_stake(&mut ctx.accounts.config, &mut stake, amount, clock.unix_timestamp)?;
+        }
+        let mut data = ctx.accounts.stake_account.try_borrow_mut_data()?;
+        stake.try_serialize(&mut &mut data[..])?;
+
+        msg!("Stake {} resized to {} bytes", stake.stake_id, new_size);
+        Ok(())
+    }
+
+    /// Admin function to grow a presale allocation created before vest_staked_tokens existed
+    /// `vest_staked_tokens` is the part of total_tokens bought via the vest-stake paths
+    /// (reconciled off-chain) - it was delivered as stake and is excluded from the TGE claim
+    pub fn resize_presale_allocation(
+        ctx: Context<ResizePresaleAllocation>,
+        vest_staked_tokens: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.config.admin == ctx.accounts.admin.key(),
+            ErrorCode::InvalidAdmin
+        );
+
+        let new_size = 8 + PresaleAllocation::SPACE;
+        {
+            let data = ctx.accounts.user_allocation.try_borrow_data()?;
+            require!(
+                data.len() >= 8
+                    && &data[..8] == PresaleAllocation::DISCRIMINATOR
+                    && data.len() < new_size,
+                ErrorCode::InvalidAllocationResize
+            );
+        }
+
+        let rent = Rent::get()?;
+        let lamports_diff = rent
+            .minimum_balance(new_size)
+            .saturating_sub(ctx.accounts.user_allocation.lamports());
+        if lamports_diff > 0 {
+            let cpi_context = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.admin.to_account_info(),
+                    to: ctx.accounts.user_allocation.clone(),
+                },
+            );
+            anchor_lang::system_program::transfer(cpi_context, lamports_diff)?;
+        }
+        ctx.accounts.user_allocation.resize(new_size)?;
+
+        let mut allocation = {
+            let data = ctx.accounts.user_allocation.try_borrow_data()?;
+            PresaleAllocation::try_deserialize(&mut &data[..])?
+        };
+        require!(
+            vest_staked_tokens == 0
+                || (!allocation.claimed && vest_staked_tokens <= allocation.total_tokens),
+            ErrorCode::InvalidAmount
+        );
+        allocation.vest_staked_tokens = vest_staked_tokens;
+        let mut data = ctx.accounts.user_allocation.try_borrow_mut_data()?;
+        allocation.try_serialize(&mut &mut data[..])?;
+
+        msg!(
+            "Allocation of {} resized to {} bytes (vest-staked {})",
+            allocation.user,
+            new_size,
+            vest_staked_tokens
+        );
+        Ok(())
+    }
+
     pub fn update_token_price(ctx: Context<UpdateConfig>, new_price: u64) -> Result<()> {
         require!(
             ctx.accounts.config.admin == ctx.accounts.admin.key(),
@@ -1645,13 +2122,13 @@ pub mod ico {
             ErrorCode::InvalidAdmin
         );
 
-        // Ensure we're not withdrawing staked tokens
+        // Ensure we're not withdrawing tokens committed to any sub-ledger
         let available_balance = ctx
             .accounts
             .ico_ata_for_ico_program
             .amount
-            .checked_sub(ctx.accounts.config.total_staked)
-            .ok_or(ErrorCode::Overflow)?;
+            .checked_sub(ctx.accounts.config.vault_committed()?)
+            .ok_or(ErrorCode::InsufficientBalance)?;
 
         require!(
             amount <= available_balance,
@@ -1687,6 +2164,256 @@ pub mod ico {
         Ok(())
     }
 
+    /// Admin function to set the vault sub-ledgers after an upgrade (reconciled off-chain)
+    /// Staked principal is always config.total_staked; committed total must fit in the vault
+    /// Instructions that move tokens between sub-ledgers fail until this has run
+    pub fn reconcile_vault_ledger(
+        ctx: Context<ReconcileVaultLedger>,
+        presale_unclaimed: u64,
+        reward_reserve: u64,
+        team_reserved: u64,
+        community_reserved: u64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(config.admin == ctx.accounts.admin.key(), ErrorCode::InvalidAdmin);
+
+        config.vault_presale_unclaimed = presale_unclaimed;
+        config.vault_reward_reserve = reward_reserve;
+        config.vault_team_reserved = team_reserved;
+        config.vault_community_reserved = community_reserved;
+        config.vault_ledger_reconciled = true;
+
+        let committed = config.vault_committed()?;
+        let vault_balance = ctx.accounts.ico_ata_for_ico_program.amount;
+        require!(committed <= vault_balance, ErrorCode::InsufficientBalance);
+
+        msg!(
+            "Vault ledger reconciled: presale={}, staked={}, rewards={}, team={}, community={}, free={}",
+            presale_unclaimed,
+            config.total_staked,
+            reward_reserve,
+            team_reserved,
+            community_reserved,
+            vault_balance - committed
+        );
+        Ok(())
+    }
+
+    /// Admin function to move tokens from the legacy stake pool ATA into the ICO vault
+    /// Coordinator vesting stakes are already counted in total_staked, so no ledger change is needed
+    pub fn merge_stake_pool_vault(ctx: Context<MergeStakePoolVault>) -> Result<()> {
+        require!(
+            ctx.accounts.config.admin == ctx.accounts.admin.key(),
+            ErrorCode::InvalidAdmin
+        );
+
+        let amount = ctx.accounts.stake_pool_ata.amount;
+        require!(amount > 0, ErrorCode::InsufficientBalance);
+
+        let bump = ctx.bumps.stake_pool_authority;
+        let seeds = &[b"stake_pool".as_ref(), &[bump]];
+        let signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.stake_pool_ata.to_account_info(),
+                to: ctx.accounts.ico_ata_for_ico_program.to_account_info(),
+                authority: ctx.accounts.stake_pool_authority.to_account_info(),
+            },
+            signer,
+        );
+        token::transfer(cpi_ctx, amount)?;
+
+        msg!("Merged {} tokens from stake pool into ICO vault", amount);
+        Ok(())
+    }
+
+    /// Admin function to create the dedicated staking reward vault
+    pub fn initialize_reward_vault(ctx: Context<InitializeRewardVault>) -> Result<()> {
+        require!(
+            ctx.accounts.config.admin == ctx.accounts.admin.key(),
+            ErrorCode::InvalidAdmin
+        );
+        msg!("Reward vault initialized: {}", ctx.accounts.reward_vault.key());
+        Ok(())
+    }
+
+    /// Admin function to fund the reward vault from the admin's token account
+    pub fn fund_reward_vault(ctx: Context<FundRewardVault>, amount: u64) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(config.admin == ctx.accounts.admin.key(), ErrorCode::InvalidAdmin);
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        config.reward_budget = config
+            .reward_budget
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.ico_ata_for_admin.to_account_info(),
+                to: ctx.accounts.reward_vault.to_account_info(),
+                authority: ctx.accounts.admin.to_account_info(),
+            },
+        );
+        token::transfer(cpi_ctx, amount)?;
+
+        msg!(
+            "Funded reward vault with {} tokens (budget {}, liability {}, shortfall {})",
+            amount,
+            config.reward_budget,
+            config.total_reward_liability,
+            config.reward_shortfall()
+        );
+        Ok(())
+    }
+
+    /// Admin function to move the ICO vault's reward reserve bucket into the reward vault
+    pub fn move_reward_reserve_to_vault(ctx: Context<MoveRewardReserveToVault>, amount: u64) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(config.admin == ctx.accounts.admin.key(), ErrorCode::InvalidAdmin);
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        debit_vault_bucket(config, VaultBucket::RewardReserve, amount)?;
+        config.reward_budget = config
+            .reward_budget
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        let ico_mint_key = ctx.accounts.ico_mint.key();
+        let bump = ctx.bumps.ico_ata_for_ico_program;
+        let seeds = &[ico_mint_key.as_ref(), &[bump]];
+        let signer = &[&seeds[..]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.ico_ata_for_ico_program.to_account_info(),
+                to: ctx.accounts.reward_vault.to_account_info(),
+                authority: ctx.accounts.ico_ata_for_ico_program.to_account_info(),
+            },
+            signer,
+        );
+        token::transfer(cpi_ctx, amount)?;
+
+        msg!(
+            "Moved {} reserved reward tokens into the reward vault (budget {}, liability {}, shortfall {})",
+            amount,
+            ctx.accounts.config.reward_budget,
+            ctx.accounts.config.total_reward_liability,
+            ctx.accounts.config.reward_shortfall()
+        );
+        Ok(())
+    }
+
+    /// Admin function to set the reward pool emission rate, remaining emission budget and tier weights
+    /// Tier weights are locked once pool mode is enabled
+    pub fn configure_reward_pool(
+        ctx: Context<UpdateConfig>,
+        emission_per_second: u64,
+        emission_budget: u64,
+        tier_weights: [u16; STAKE_TIER_COUNT],
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.config.admin == ctx.accounts.admin.key(),
+            ErrorCode::InvalidAdmin
+        );
+        require!(tier_weights.iter().all(|w| *w > 0), ErrorCode::InvalidAmount);
+
+        let clock = Clock::get()?;
+        let config = &mut ctx.accounts.config;
+        if config.reward_pool_enabled {
+            require!(
+                tier_weights == config.reward_tier_weights,
+                ErrorCode::RewardPoolEnabled
+            );
+            // Emit up to now at the old rate before changing it
+            update_reward_pool(config, clock.unix_timestamp)?;
+        }
+
+        config.reward_emission_per_second = emission_per_second;
+        config.reward_emission_budget = emission_budget;
+        config.reward_tier_weights = tier_weights;
+
+        msg!(
+            "Reward pool configured: {} tokens/s, budget {}, weights A={} B={} C={}",
+            emission_per_second,
+            emission_budget,
+            tier_weights[0],
+            tier_weights[1],
+            tier_weights[2]
+        );
+        Ok(())
+    }
+
+    /// Admin function to switch staking rewards from fixed tier APY to pool mode (one-way)
+    /// Stakes created before pool mode must be resized first to share in emissions
+    pub fn enable_reward_pool(ctx: Context<UpdateConfig>) -> Result<()> {
+        require!(
+            ctx.accounts.config.admin == ctx.accounts.admin.key(),
+            ErrorCode::InvalidAdmin
+        );
+
+        let config = &mut ctx.accounts.config;
+        require!(!config.reward_pool_enabled, ErrorCode::RewardPoolEnabled);
+        require!(config.reward_emission_per_second > 0, ErrorCode::InvalidAmount);
+        require!(
+            config.reward_tier_weights.iter().all(|w| *w > 0),
+            ErrorCode::InvalidAmount
+        );
+
+        let clock = Clock::get()?;
+        config.reward_pool_enabled = true;
+        config.reward_pool_started_at = clock.unix_timestamp;
+        config.last_reward_pool_update = clock.unix_timestamp;
+
+        msg!(
+            "Reward pool enabled: {} tokens/s, budget {}, weighted stake {}",
+            config.reward_emission_per_second,
+            config.reward_emission_budget,
+            total_weighted_stake(config)?
+        );
+        Ok(())
+    }
+
+    /// Admin function to set the early unstake penalty and how it is split
+    /// The remainder after burn and treasury shares goes to the exiting stake's tier (0 max = disabled)
+    pub fn set_early_unstake_penalty(
+        ctx: Context<UpdateConfig>,
+        max_penalty_bps: u64,
+        burn_bps: u64,
+        treasury_bps: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.config.admin == ctx.accounts.admin.key(),
+            ErrorCode::InvalidAdmin
+        );
+        require!(
+            max_penalty_bps <= MAX_EARLY_UNSTAKE_PENALTY_BPS,
+            ErrorCode::InvalidAmount
+        );
+        require!(
+            burn_bps.checked_add(treasury_bps).ok_or(ErrorCode::Overflow)? <= 10_000,
+            ErrorCode::InvalidAmount
+        );
+
+        let config = &mut ctx.accounts.config;
+        config.early_unstake_max_penalty_bps = max_penalty_bps;
+        config.penalty_burn_bps = burn_bps;
+        config.penalty_treasury_bps = treasury_bps;
+
+        msg!(
+            "Early unstake penalty set: max {} bps, burn {} bps, treasury {} bps, stakers {} bps",
+            max_penalty_bps,
+            burn_bps,
+            treasury_bps,
+            10_000 - burn_bps - treasury_bps
+        );
+        Ok(())
+    }
+
     // =====================================================
     // TEAM VESTING FUNCTIONS (Admin Only)
     // =====================================================
@@ -1727,6 +2454,7 @@ pub mod ico {
         team_vesting.member = ctx.accounts.team_member.key();
         team_vesting.total_allocation = raw_amount;
         team_vesting.claimed_amount = 0;
+        credit_vault_bucket(&mut ctx.accounts.config, VaultBucket::TeamReserved, raw_amount)?;
         team_vesting.created_at = clock.unix_timestamp;
         team_vesting.cliff_end = cliff_end;
         team_vesting.is_active = true;
@@ -1743,7 +2471,7 @@ pub mod ico {
 
     /// Claim team tokens after 18-month lockup period (team member only)
     pub fn claim_team_tokens(ctx: Context<ClaimTeamTokens>) -> Result<()> {
-        let _config = &ctx.accounts.config;
+        let config = &mut ctx.accounts.config;
         let clock = Clock::get()?;
         let team_vesting = &mut ctx.accounts.team_vesting;
 
@@ -1773,6 +2501,7 @@ pub mod ico {
         // === CEI: EFFECTS FIRST (state changes before transfer) ===
         team_vesting.claimed_amount = team_vesting.total_allocation;
         team_vesting.is_active = false;
+        debit_vault_bucket(config, VaultBucket::TeamReserved, claimable)?;
 
         // === CEI: INTERACTIONS LAST ===
         // Transfer tokens from program ATA to team member
@@ -1845,21 +2574,329 @@ pub mod ico {
             ErrorCode::InvalidAdmin
         );
 
-        ctx.accounts.config.coordinator = coordinator;
+        // Immediate replacement (emergency) - cancels any scheduled rotation
+        let config = &mut ctx.accounts.config;
+        config.coordinator = coordinator;
+        config.next_coordinator = Pubkey::default();
+        config.next_coordinator_activates_at = 0;
+        config.coordinator_overlap_seconds = 0;
         msg!("Coordinator set to {}", coordinator);
         Ok(())
     }
 
-    /// Admin function to update maximum purchase limit per user
-    /// Set to 0 to use the default constant PRESALE_MAX_PURCHASE_USD
-    pub fn update_max_per_user(ctx: Context<UpdateConfig>, max_usd_cents: u64) -> Result<()> {
+    /// Admin function to schedule a coordinator rotation
+    /// From `activates_at` the next coordinator is accepted; the current one stays valid for
+    /// `overlap_seconds` more, after which the next coordinator is promoted.
+    /// Pass Pubkey::default() to cancel a pending rotation.
+    pub fn schedule_coordinator_rotation(
+        ctx: Context<UpdateConfig>,
+        next_coordinator: Pubkey,
+        activates_at: i64,
+        overlap_seconds: i64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(config.admin == ctx.accounts.admin.key(), ErrorCode::InvalidAdmin);
+        let clock = Clock::get()?;
+
+        // Finish any rotation that is already due before replacing it
+        promote_coordinator_if_due(config, clock.unix_timestamp);
+
+        if next_coordinator == Pubkey::default() {
+            config.next_coordinator = Pubkey::default();
+            config.next_coordinator_activates_at = 0;
+            config.coordinator_overlap_seconds = 0;
+            msg!("Coordinator rotation cancelled");
+            return Ok(());
+        }
+
+        require!(next_coordinator != config.coordinator, ErrorCode::InvalidCoordinator);
+        require!(activates_at >= clock.unix_timestamp, ErrorCode::InvalidAmount);
         require!(
-            ctx.accounts.config.admin == ctx.accounts.admin.key(),
-            ErrorCode::InvalidAdmin
+            (0..=MAX_COORDINATOR_OVERLAP_SECONDS).contains(&overlap_seconds),
+            ErrorCode::InvalidAmount
         );
 
-        ctx.accounts.config.max_per_user_usd = max_usd_cents;
-        msg!("Updated max per user to {} cents", max_usd_cents);
+        config.next_coordinator = next_coordinator;
+        config.next_coordinator_activates_at = activates_at;
+        config.coordinator_overlap_seconds = overlap_seconds;
+
+        msg!(
+            "Coordinator rotation scheduled: {} -> {} at {}, overlap {}s",
+            config.coordinator,
+            next_coordinator,
+            activates_at,
+            overlap_seconds
+        );
+        Ok(())
+    }
+
+    /// Admin function to set the coordinator committee (M-of-N attestations)
+    /// Pass an empty member list to disable committee mode and fall back to the single coordinator
+    /// Committee mode has no attested vest-stake path: coordinator_mint_and_vest_stake is disabled
+    /// while it is on, so cross-chain purchases are only credited as allocations
+    pub fn set_coordinator_committee(
+        ctx: Context<SetCoordinatorCommittee>,
+        members: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(config.admin == ctx.accounts.admin.key(), ErrorCode::InvalidAdmin);
+
+        require!(members.len() <= MAX_COMMITTEE_MEMBERS, ErrorCode::InvalidCommittee);
+        if members.is_empty() {
+            require!(threshold == 0, ErrorCode::InvalidCommittee);
+        } else {
+            require!(
+                threshold >= 1 && threshold as usize <= members.len(),
+                ErrorCode::InvalidCommittee
+            );
+        }
+        for (i, member) in members.iter().enumerate() {
+            require!(*member != Pubkey::default(), ErrorCode::InvalidCommittee);
+            require!(!members[..i].contains(member), ErrorCode::InvalidCommittee);
+        }
+
+        let committee = &mut ctx.accounts.coordinator_committee;
+        committee.members = [Pubkey::default(); MAX_COMMITTEE_MEMBERS];
+        committee.members[..members.len()].copy_from_slice(&members);
+        committee.member_count = members.len() as u8;
+        committee.threshold = threshold;
+        // Bumping the epoch invalidates attestations from the previous member set
+        committee.epoch = committee.epoch.checked_add(1).ok_or(ErrorCode::Overflow)?;
+
+        config.coordinator_committee_enabled = !members.is_empty();
+
+        msg!(
+            "Coordinator committee set: {} members, threshold {}, epoch {}",
+            members.len(),
+            threshold,
+            committee.epoch
+        );
+        Ok(())
+    }
+
+    /// Admin function to add or update a supported EVM chain and its purchase limits
+    /// Limits set to 0 use the default CROSS_CHAIN_* constants
+    pub fn set_supported_chain(
+        ctx: Context<SetSupportedChain>,
+        chain_id: u64,
+        settings: SupportedChainSettings,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.config.admin == ctx.accounts.admin.key(),
+            ErrorCode::InvalidAdmin
+        );
+        require!(chain_id != 0, ErrorCode::InvalidChainId);
+        require!(settings.name.len() <= MAX_CHAIN_NAME_LEN, ErrorCode::InvalidChainName);
+        require!(settings.cooldown_seconds >= 0, ErrorCode::InvalidAmount);
+
+        let chain = &mut ctx.accounts.supported_chain;
+        chain.chain_id = chain_id;
+        chain.name = settings.name;
+        chain.enabled = settings.enabled;
+        chain.contract_address = settings.contract_address;
+        chain.min_usd_cents = settings.min_usd_cents;
+        chain.max_usd_cents = settings.max_usd_cents;
+        chain.max_total_usd_cents = settings.max_total_usd_cents;
+        chain.cooldown_seconds = settings.cooldown_seconds;
+        chain.hourly_limit_usd_cents = settings.hourly_limit_usd_cents;
+        chain.daily_limit_usd_cents = settings.daily_limit_usd_cents;
+
+        // Effective limits must be consistent
+        require!(
+            chain.min_usd() <= chain.max_usd() && chain.max_usd() <= chain.max_total_usd(),
+            ErrorCode::InvalidAmount
+        );
+        require!(chain.hourly_limit() <= chain.daily_limit(), ErrorCode::InvalidAmount);
+
+        msg!(
+            "Supported chain {} ({}) set: enabled={}, min={}, max={}, max_total={}, cooldown={}s, hourly={}, daily={}",
+            chain.chain_id,
+            chain.name,
+            chain.enabled,
+            chain.min_usd(),
+            chain.max_usd(),
+            chain.max_total_usd(),
+            chain.cooldown(),
+            chain.hourly_limit(),
+            chain.daily_limit()
+        );
+        Ok(())
+    }
+
+    /// Admin function to register our EVM presale contract as the Wormhole emitter for a chain
+    /// wormhole_chain: Wormhole chain ID (2=Ethereum, 4=BNB, 5=Polygon), chain_id: EVM chain ID
+    pub fn register_wormhole_emitter(
+        ctx: Context<RegisterWormholeEmitter>,
+        chain_id: u64,
+        wormhole_chain: u16,
+        emitter_address: [u8; 32],
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.config.admin == ctx.accounts.admin.key(),
+            ErrorCode::InvalidAdmin
+        );
+        require!(
+            wormhole_chain != 0 && emitter_address != [0u8; 32],
+            ErrorCode::InvalidAddress
+        );
+
+        let chain = &mut ctx.accounts.supported_chain;
+        chain.wormhole_chain = wormhole_chain;
+        chain.emitter_address = emitter_address;
+
+        msg!(
+            "Registered Wormhole emitter for chain {} (EVM chain {})",
+            wormhole_chain,
+            chain_id
+        );
+        Ok(())
+    }
+
+    /// Admin function to migrate a legacy (u8 chain id) cross-chain allocation to the u64 layout
+    /// The legacy account is closed and its rent returned to the admin
+    pub fn migrate_cross_chain_allocation(
+        ctx: Context<MigrateCrossChainAllocation>,
+        eth_address: [u8; 20],
+        legacy_chain_id: u8,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.config.admin == ctx.accounts.admin.key(),
+            ErrorCode::InvalidAdmin
+        );
+
+        let legacy_info = ctx.accounts.legacy_allocation.to_account_info();
+        require!(legacy_info.owner == ctx.program_id, ErrorCode::InvalidPDA);
+
+        let legacy = {
+            let data = legacy_info.try_borrow_data()?;
+            require!(
+                data.len() >= 8 + LegacyCrossChainAllocation::SPACE
+                    && &data[..8] == CrossChainAllocation::DISCRIMINATOR,
+                ErrorCode::InvalidAddress
+            );
+            LegacyCrossChainAllocation::deserialize(&mut &data[8..])?
+        };
+        require!(
+            legacy.eth_address == eth_address && legacy.chain_id == legacy_chain_id,
+            ErrorCode::InvalidAddress
+        );
+
+        // === EFFECTS ===
+        let allocation = &mut ctx.accounts.cross_chain_allocation;
+        allocation.eth_address = legacy.eth_address;
+        allocation.chain_id = legacy.chain_id as u64;
+        allocation.total_tokens = legacy.total_tokens;
+        allocation.total_usd_cents = legacy.total_usd_cents;
+        allocation.purchase_count = legacy.purchase_count;
+        allocation.first_purchase_at = legacy.first_purchase_at;
+        allocation.last_purchase_at = legacy.last_purchase_at;
+        allocation.referrer_eth = legacy.referrer_eth;
+        allocation.referral_bonus = legacy.referral_bonus;
+        allocation.linked_solana_wallet = legacy.linked_solana_wallet;
+        allocation.claimed = legacy.claimed;
+
+        // Close the legacy account (zero data so it cannot be reused in this transaction)
+        legacy_info.try_borrow_mut_data()?.fill(0);
+        let admin_info = ctx.accounts.admin.to_account_info();
+        let legacy_lamports = legacy_info.lamports();
+        **admin_info.try_borrow_mut_lamports()? = admin_info
+            .lamports()
+            .checked_add(legacy_lamports)
+            .ok_or(ErrorCode::Overflow)?;
+        **legacy_info.try_borrow_mut_lamports()? = 0;
+
+        msg!(
+            "Migrated cross-chain allocation {:?} on chain {}: {} tokens",
+            &eth_address[..4],
+            legacy_chain_id,
+            allocation.total_tokens
+        );
+        Ok(())
+    }
+
+    /// Admin function to update cross-chain price verification parameters
+    pub fn update_cross_chain_price_params(
+        ctx: Context<UpdateConfig>,
+        tolerance_bps: u64,
+        quote_window_seconds: i64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.config.admin == ctx.accounts.admin.key(),
+            ErrorCode::InvalidAdmin
+        );
+        require!(
+            tolerance_bps <= MAX_CROSS_CHAIN_PRICE_TOLERANCE_BPS && quote_window_seconds >= 0,
+            ErrorCode::InvalidAmount
+        );
+
+        ctx.accounts.config.cross_chain_price_tolerance_bps = tolerance_bps;
+        ctx.accounts.config.stage_quote_window_seconds = quote_window_seconds;
+        msg!(
+            "Cross-chain price tolerance set to {} bps, quote window {}s",
+            tolerance_bps,
+            quote_window_seconds
+        );
+        Ok(())
+    }
+
+    /// Admin function to set the global rolling cross-chain limits (all chains)
+    /// Set to 0 to use the default CROSS_CHAIN_HOURLY/DAILY_LIMIT_USD_CENTS constants
+    pub fn update_cross_chain_rate_limits(
+        ctx: Context<UpdateConfig>,
+        hourly_limit_usd_cents: u64,
+        daily_limit_usd_cents: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.config.admin == ctx.accounts.admin.key(),
+            ErrorCode::InvalidAdmin
+        );
+
+        let config = &mut ctx.accounts.config;
+        config.cross_chain_hourly_limit_usd_cents = hourly_limit_usd_cents;
+        config.cross_chain_daily_limit_usd_cents = daily_limit_usd_cents;
+        require!(
+            config.cross_chain_hourly_limit() <= config.cross_chain_daily_limit(),
+            ErrorCode::InvalidAmount
+        );
+
+        msg!(
+            "Cross-chain rate limits set: hourly={} cents, daily={} cents",
+            config.cross_chain_hourly_limit(),
+            config.cross_chain_daily_limit()
+        );
+        Ok(())
+    }
+
+    /// Admin function to reset a tripped cross-chain circuit breaker
+    /// Clears the global usage window and, if a chain is passed, that chain's usage window
+    pub fn reset_cross_chain_circuit_breaker(ctx: Context<ResetCrossChainCircuitBreaker>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(config.admin == ctx.accounts.admin.key(), ErrorCode::InvalidAdmin);
+
+        config.cross_chain_paused = false;
+        config.cross_chain_paused_at = 0;
+        config.cross_chain_usage = RollingUsage::default();
+
+        if let Some(chain) = ctx.accounts.supported_chain.as_mut() {
+            chain.usage = RollingUsage::default();
+            msg!("Cross-chain circuit breaker reset (chain {} usage cleared)", chain.chain_id);
+        } else {
+            msg!("Cross-chain circuit breaker reset");
+        }
+        Ok(())
+    }
+
+    /// Admin function to update maximum purchase limit per user
+    /// Set to 0 to use the default constant PRESALE_MAX_PURCHASE_USD
+    pub fn update_max_per_user(ctx: Context<UpdateConfig>, max_usd_cents: u64) -> Result<()> {
+        require!(
+            ctx.accounts.config.admin == ctx.accounts.admin.key(),
+            ErrorCode::InvalidAdmin
+        );
+
+        ctx.accounts.config.max_per_user_usd = max_usd_cents;
+        msg!("Updated max per user to {} cents", max_usd_cents);
         Ok(())
     }
 
@@ -1876,6 +2913,75 @@ pub mod ico {
         Ok(())
     }
 
+    /// Admin function to update the referee bonus (extra tokens for referred buyers on first purchase)
+    /// Set to 0 to disable the referee bonus
+    pub fn update_referee_bonus_percentage(ctx: Context<UpdateConfig>, percentage: u64) -> Result<()> {
+        require!(
+            ctx.accounts.config.admin == ctx.accounts.admin.key(),
+            ErrorCode::InvalidAdmin
+        );
+        require!(
+            percentage <= MAX_REFERRAL_PERCENTAGE,
+            ErrorCode::ReferralPercentageTooHigh
+        );
+
+        ctx.accounts.config.referee_bonus_percentage = percentage;
+        msg!("Updated referee bonus to {}%", percentage);
+        Ok(())
+    }
+
+    /// Admin function to update the flat referral percentage (used when no tiers are configured)
+    pub fn update_referral_reward_percentage(ctx: Context<UpdateConfig>, percentage: u64) -> Result<()> {
+        require!(
+            ctx.accounts.config.admin == ctx.accounts.admin.key(),
+            ErrorCode::InvalidAdmin
+        );
+        require!(
+            percentage <= MAX_REFERRAL_PERCENTAGE,
+            ErrorCode::ReferralPercentageTooHigh
+        );
+
+        ctx.accounts.config.referral_reward_percentage = percentage;
+        msg!("Updated referral reward percentage to {}%", percentage);
+        Ok(())
+    }
+
+    /// Admin function to set the referral tier table
+    /// Tiers must start at $0, have increasing volume thresholds and non-decreasing percentages
+    /// Pass an empty list to fall back to the flat referral_reward_percentage
+    pub fn set_referral_tiers(ctx: Context<UpdateConfig>, tiers: Vec<ReferralTier>) -> Result<()> {
+        require!(
+            ctx.accounts.config.admin == ctx.accounts.admin.key(),
+            ErrorCode::InvalidAdmin
+        );
+        require!(tiers.len() <= MAX_REFERRAL_TIERS, ErrorCode::InvalidReferralTiers);
+
+        for (i, tier) in tiers.iter().enumerate() {
+            require!(
+                tier.percentage <= MAX_REFERRAL_PERCENTAGE,
+                ErrorCode::ReferralPercentageTooHigh
+            );
+            if i == 0 {
+                require!(tier.min_referred_usd_cents == 0, ErrorCode::InvalidReferralTiers);
+            } else {
+                let previous = &tiers[i - 1];
+                require!(
+                    tier.min_referred_usd_cents > previous.min_referred_usd_cents
+                        && tier.percentage >= previous.percentage,
+                    ErrorCode::InvalidReferralTiers
+                );
+            }
+        }
+
+        let config = &mut ctx.accounts.config;
+        config.referral_tiers = [ReferralTier::default(); MAX_REFERRAL_TIERS];
+        config.referral_tiers[..tiers.len()].copy_from_slice(&tiers);
+        config.referral_tier_count = tiers.len() as u8;
+
+        msg!("Referral tiers updated: {} tiers", tiers.len());
+        Ok(())
+    }
+
     /// Admin function to update SOL treasury address (for Squads multisig)
     /// SOL payments will be sent to this address instead of admin
     pub fn update_sol_treasury(ctx: Context<UpdateConfig>, new_treasury: Pubkey) -> Result<()> {
@@ -1894,194 +3000,175 @@ pub mod ico {
     /// This function is called by the coordinator when a purchase is made on EVM chains
     pub fn record_cross_chain_purchase(
         ctx: Context<RecordCrossChainPurchase>,
-        buyer_eth_address: [u8; 20],
-        chain_id: u8,
-        noc_amount: u64,
-        usd_cents: u64,
-        _tx_hash: [u8; 32],
-        _stage: u8,
-        referrer_eth: [u8; 20],
+        purchase: CrossChainPurchase,
     ) -> Result<()> {
         let config = &mut ctx.accounts.config;
         let clock = Clock::get()?;
 
-        // Validate coordinator
+        // Validate coordinator (current, or either key during a rotation overlap)
+        require_active_coordinator(config, ctx.accounts.coordinator.key(), clock.unix_timestamp)?;
+
+        // Committee mode - purchases must be attested by M-of-N committee members
         require!(
-            ctx.accounts.coordinator.key() == config.coordinator,
-            ErrorCode::InvalidCoordinator
+            !config.coordinator_committee_enabled,
+            ErrorCode::CoordinatorCommitteeRequired
         );
 
-        // Validate presale is active
-        require!(config.presale_active, ErrorCode::PresaleNotStarted);
+        let credited = record_cross_chain_purchase_internal(
+            config,
+            &mut ctx.accounts.supported_chain,
+            &mut ctx.accounts.processed_tx,
+            &mut ctx.accounts.cross_chain_allocation,
+            &mut ctx.accounts.referrer_cross_chain_referral,
+            &purchase,
+            false,
+        )?;
+
+        if credited {
+            record_coordinator_activity(
+                &mut ctx.accounts.coordinator_stats,
+                ctx.accounts.coordinator.key(),
+                purchase.noc_amount,
+                purchase.usd_cents,
+                false,
+                clock.unix_timestamp,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Record a cross-chain purchase from a guardian-verified Wormhole VAA (anyone can relay)
+    /// The VAA must be posted by the Core Bridge and emitted by the registered presale contract
+    /// Replay protection is keyed by the source (tx_hash, log_index) like the coordinator paths,
+    /// so a purchase credited by the coordinator cannot be credited again from its VAA
+    pub fn record_cross_chain_purchase_from_vaa(
+        ctx: Context<RecordCrossChainPurchaseFromVaa>,
+        purchase: CrossChainPurchase,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let chain = &ctx.accounts.supported_chain;
+
+        // Only the Core Bridge can create accounts it owns, i.e. guardian-verified VAAs
+        let posted_vaa = &ctx.accounts.posted_vaa;
         require!(
-            config.tokens_sold.checked_add(noc_amount).ok_or(ErrorCode::Overflow)? 
-                <= PRESALE_TOTAL_ALLOCATION * TOKEN_DECIMALS,
-            ErrorCode::PresaleHardCapReached
+            *posted_vaa.owner == WORMHOLE_CORE_BRIDGE_PROGRAM_ID,
+            ErrorCode::InvalidVaa
         );
 
-        // Validate chain ID (1=ETH, 56=BNB, 137=Polygon)
+        let vaa_data = posted_vaa.try_borrow_data()?;
+        let (emitter_chain, emitter_address, payload) = parse_posted_vaa(&vaa_data)?;
         require!(
-            chain_id == 1 || chain_id == 56 || chain_id == 137,
-            ErrorCode::InvalidChainId
+            chain.wormhole_chain != 0
+                && emitter_chain == chain.wormhole_chain
+                && emitter_address == chain.emitter_address,
+            ErrorCode::UnregisteredEmitter
         );
 
-        // === CROSS-CHAIN SECURITY CHECKS ===
-        
-        // 1. Validate minimum purchase ($10)
+        let signed_purchase = decode_vaa_purchase_payload(payload, chain.chain_id)?;
+        drop(vaa_data);
+
+        // Instruction args only select the PDAs - they must match the signed payload
+        require!(signed_purchase == purchase, ErrorCode::InvalidVaaPayload);
+        record_cross_chain_purchase_internal(
+            config,
+            &mut ctx.accounts.supported_chain,
+            &mut ctx.accounts.processed_tx,
+            &mut ctx.accounts.cross_chain_allocation,
+            &mut ctx.accounts.referrer_cross_chain_referral,
+            &purchase,
+            false,
+        )?;
+
+        Ok(())
+    }
+
+    /// Attest a cross-chain purchase (coordinator committee member only)
+    /// The allocation is credited once `threshold` distinct members attested identical data
+    pub fn attest_cross_chain_purchase(
+        ctx: Context<AttestCrossChainPurchase>,
+        purchase: CrossChainPurchase,
+        attestation_hash: [u8; 32],
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let committee = &ctx.accounts.coordinator_committee;
+        let attestation = &mut ctx.accounts.attestation;
+        let attester = ctx.accounts.attester.key();
+        let clock = Clock::get()?;
+
         require!(
-            usd_cents >= CROSS_CHAIN_MIN_USD_CENTS,
-            ErrorCode::CrossChainBelowMinimum
+            config.coordinator_committee_enabled,
+            ErrorCode::InvalidCommittee
         );
-        
-        // 2. Validate maximum per transaction ($10,000)
         require!(
-            usd_cents <= CROSS_CHAIN_MAX_USD_CENTS,
-            ErrorCode::CrossChainExceedsMaximum
+            committee.members[..committee.member_count as usize].contains(&attester),
+            ErrorCode::NotCommitteeMember
         );
 
-        let allocation = &mut ctx.accounts.cross_chain_allocation;
-
-        // 3. Check cooldown (30 seconds) - only for existing allocations
-        if allocation.chain_id != 0 {
-            let time_since_last = clock.unix_timestamp
-                .checked_sub(allocation.last_purchase_at)
-                .ok_or(ErrorCode::Overflow)?;
-            require!(
-                time_since_last >= CROSS_CHAIN_COOLDOWN_SECONDS,
-                ErrorCode::CrossChainCooldown
-            );
-        }
-        
-        // 4. Check user total limit ($50,000)
-        let new_total_usd = if allocation.chain_id == 0 {
-            usd_cents
-        } else {
-            allocation.total_usd_cents
-                .checked_add(usd_cents)
-                .ok_or(ErrorCode::Overflow)?
-        };
+        // Attestations are keyed by the hash of the full purchase data
         require!(
-            new_total_usd <= CROSS_CHAIN_MAX_TOTAL_USD_CENTS,
-            ErrorCode::CrossChainUserLimitExceeded
+            cross_chain_attestation_hash(&purchase) == attestation_hash,
+            ErrorCode::AttestationMismatch
         );
-
-        // Initialize or update allocation
-        if allocation.chain_id == 0 {
-            // First purchase
-            allocation.eth_address = buyer_eth_address;
-            allocation.chain_id = chain_id;
-            allocation.total_tokens = noc_amount;
-            allocation.total_usd_cents = usd_cents;
-            allocation.purchase_count = 1;
-            allocation.first_purchase_at = clock.unix_timestamp;
-            allocation.last_purchase_at = clock.unix_timestamp;
-            allocation.referrer_eth = referrer_eth;
-            allocation.linked_solana_wallet = Pubkey::default();
-            allocation.claimed = false;
-        } else {
-            // Additional purchase
-            allocation.total_tokens = allocation
-                .total_tokens
-                .checked_add(noc_amount)
-                .ok_or(ErrorCode::Overflow)?;
-            allocation.total_usd_cents = allocation
-                .total_usd_cents
-                .checked_add(usd_cents)
-                .ok_or(ErrorCode::Overflow)?;
-            allocation.purchase_count = allocation
-                .purchase_count
-                .checked_add(1)
-                .ok_or(ErrorCode::Overflow)?;
-            allocation.last_purchase_at = clock.unix_timestamp;
+        require!(!attestation.executed, ErrorCode::TransactionAlreadyProcessed);
+
+        // Attestations from a previous committee do not count
+        if attestation.epoch != committee.epoch {
+            attestation.attestation_hash = attestation_hash;
+            attestation.epoch = committee.epoch;
+            attestation.attesters = [Pubkey::default(); MAX_COMMITTEE_MEMBERS];
+            attestation.attestation_count = 0;
+            attestation.created_at = clock.unix_timestamp;
         }
 
-        // Process referral bonus (10%) - ONE-TIME only on first purchase
-        let referral_bonus = if referrer_eth != [0u8; 20] && allocation.purchase_count == 1 {
-            let bonus = noc_amount
-                .checked_mul(REFERRAL_BONUS_PERCENTAGE)
-                .ok_or(ErrorCode::Overflow)?
-                .checked_div(100)
-                .ok_or(ErrorCode::Overflow)?;
-
-            // Check Community Rewards pool
-            let new_total_referral = config.total_referral_bonuses
-                .checked_add(bonus)
-                .ok_or(ErrorCode::Overflow)?;
-
-            if new_total_referral <= COMMUNITY_REWARDS_ALLOCATION * TOKEN_DECIMALS {
-                allocation.referral_bonus = bonus;
-                config.total_referral_bonuses = new_total_referral;
-                
-                // Update referrer's allocation if it exists
-                if !ctx.accounts.referrer_cross_chain_allocation.data_is_empty() {
-                    let mut referrer_data = ctx.accounts.referrer_cross_chain_allocation.try_borrow_mut_data()?;
-                    if referrer_data.len() >= 8 + CrossChainAllocation::SPACE {
-                        // Add bonus to referrer
-                        let mut referrer_alloc = CrossChainAllocation::try_deserialize(&mut &referrer_data[..])?;
-                        referrer_alloc.total_tokens = referrer_alloc
-                            .total_tokens
-                            .checked_add(bonus)
-                            .ok_or(ErrorCode::Overflow)?;
-                        referrer_alloc.try_serialize(&mut &mut referrer_data[..])?;
-                    }
-                }
-                
-                msg!("One-time referral bonus: {} tokens", bonus);
-                bonus
-            } else {
-                msg!("Referral pool exhausted");
-                0
-            }
+        // Once the threshold is reached any member may retry execution (e.g. after a breaker reset)
+        let count = attestation.attestation_count as usize;
+        if attestation.attesters[..count].contains(&attester) {
+            require!(
+                attestation.attestation_count >= committee.threshold,
+                ErrorCode::AlreadyAttested
+            );
         } else {
-            0
-        };
-
-        // Update global stats
-        config.tokens_sold = config
-            .tokens_sold
-            .checked_add(noc_amount)
-            .ok_or(ErrorCode::Overflow)?;
-        config.cross_chain_tokens_sold = config
-            .cross_chain_tokens_sold
-            .checked_add(noc_amount)
-            .ok_or(ErrorCode::Overflow)?;
-        config.total_usd_raised_cents = config
-            .total_usd_raised_cents
-            .checked_add(usd_cents)
-            .ok_or(ErrorCode::Overflow)?;
-
-        // Update stage progress
-        config.stage_tokens_sold = config
-            .stage_tokens_sold
-            .checked_add(noc_amount)
-            .ok_or(ErrorCode::Overflow)?;
-
-        // Advance stage if full
-        while config.stage_tokens_sold >= TOKENS_PER_STAGE * TOKEN_DECIMALS && config.current_stage < 9 {
-            config.current_stage += 1;
-            config.stage_tokens_sold = config.stage_tokens_sold
-                .checked_sub(TOKENS_PER_STAGE * TOKEN_DECIMALS)
-                .ok_or(ErrorCode::Overflow)?;
-            msg!("Stage advanced to {}", config.current_stage + 1);
+            attestation.attesters[count] = attester;
+            attestation.attestation_count += 1;
         }
 
         msg!(
-            "Cross-chain purchase recorded: chain={}, buyer={:?}, noc={}, usd_cents={}, referral_bonus={}",
-            chain_id,
-            &buyer_eth_address[..4],
-            noc_amount,
-            usd_cents,
-            referral_bonus
+            "Cross-chain purchase attested by {} ({}/{})",
+            attester,
+            attestation.attestation_count,
+            committee.threshold
         );
 
+        if attestation.attestation_count < committee.threshold {
+            return Ok(());
+        }
+
+        let credited = record_cross_chain_purchase_internal(
+            config,
+            &mut ctx.accounts.supported_chain,
+            &mut ctx.accounts.processed_tx,
+            &mut ctx.accounts.cross_chain_allocation,
+            &mut ctx.accounts.referrer_cross_chain_referral,
+            &purchase,
+            false,
+        )?;
+
+        if credited {
+            let attestation = &mut ctx.accounts.attestation;
+            attestation.executed = true;
+            attestation.executed_at = clock.unix_timestamp;
+        }
         Ok(())
     }
 
-    /// Link a Solana wallet to a cross-chain allocation (user signs with ETH signature verified off-chain)
+    /// Link a Solana wallet to a cross-chain allocation
+    /// Requires an EIP-191 signature from the ETH address over the Solana wallet and current nonce
     pub fn link_solana_wallet(
         ctx: Context<LinkSolanaWallet>,
         eth_address: [u8; 20],
-        chain_id: u8,
+        chain_id: u64,
+        signature: [u8; 65],
     ) -> Result<()> {
         let allocation = &mut ctx.accounts.cross_chain_allocation;
         
@@ -2090,6 +3177,17 @@ pub mod ico {
             allocation.eth_address == eth_address && allocation.chain_id == chain_id,
             ErrorCode::InvalidAddress
         );
+
+        // Verify the ETH address authorized this wallet (nonce makes every signature single-use)
+        verify_evm_link_signature(
+            &mut ctx.accounts.evm_link_nonce,
+            "allocation",
+            eth_address,
+            chain_id,
+            ctx.accounts.user.key(),
+            ctx.program_id,
+            &signature,
+        )?;
         
         // Link the Solana wallet
         allocation.linked_solana_wallet = ctx.accounts.user.key();
@@ -2105,7 +3203,7 @@ pub mod ico {
 
     /// Claim cross-chain allocation at TGE
     pub fn claim_cross_chain_allocation(ctx: Context<ClaimCrossChainAllocation>) -> Result<()> {
-        let config = &ctx.accounts.config;
+        let config = &mut ctx.accounts.config;
         let clock = Clock::get()?;
         let allocation = &mut ctx.accounts.cross_chain_allocation;
 
@@ -2130,6 +3228,7 @@ pub mod ico {
 
         // === CEI: EFFECTS FIRST (state changes before transfer) ===
         allocation.claimed = true;
+        debit_vault_bucket(config, VaultBucket::PresaleUnclaimed, total_to_claim)?;
 
         // === CEI: INTERACTIONS LAST ===
         // Transfer tokens
@@ -2167,481 +3266,676 @@ pub mod ico {
         Ok(())
     }
 
-    /// Coordinator-initiated mint and vesting stake for EVM buyers
-    /// This allows EVM buyers to receive tokens immediately (minted + staked) instead of waiting for TGE
-    /// Called by coordinator when EVM buyer provides a Solana address
-    pub fn coordinator_mint_and_vest_stake(
-        ctx: Context<CoordinatorMintAndVestStake>,
-        buyer_eth_address: [u8; 20],
-        chain_id: u8,
-        noc_amount: u64,
-        usd_cents: u64,
-        tier: StakeTier,
-        auto_compound: bool,
+    /// Merge a linked cross-chain allocation into the wallet's Solana PresaleAllocation
+    /// Unclaimed tokens and their spend move over (subject to the per-user cap) so the normal
+    /// claim, claim_and_stake and referral paths apply. Spend on vest-staked purchases stays behind. If the ETH address is also a
+    /// cross-chain referrer linked to this wallet, its unclaimed bonus moves over too.
+    /// Can be repeated - later cross-chain purchases are merged on the next call.
+    pub fn merge_cross_chain_allocation(
+        ctx: Context<MergeCrossChainAllocation>,
+        eth_address: [u8; 20],
+        chain_id: u64,
     ) -> Result<()> {
         let config = &mut ctx.accounts.config;
         let clock = Clock::get()?;
+        let allocation = &mut ctx.accounts.cross_chain_allocation;
+        let user_allocation = &mut ctx.accounts.user_allocation;
+        let user = ctx.accounts.user.key();
 
-        // Validate coordinator
+        // === CHECKS ===
         require!(
-            ctx.accounts.coordinator.key() == config.coordinator,
-            ErrorCode::InvalidCoordinator
+            allocation.eth_address == eth_address && allocation.chain_id == chain_id,
+            ErrorCode::InvalidAddress
         );
-
-        // Validate presale is active
-        require!(config.presale_active, ErrorCode::PresaleNotStarted);
         require!(
-            config.tokens_sold.checked_add(noc_amount).ok_or(ErrorCode::Overflow)? 
-                <= PRESALE_TOTAL_ALLOCATION * TOKEN_DECIMALS,
-            ErrorCode::PresaleHardCapReached
+            allocation.linked_solana_wallet == user,
+            ErrorCode::WalletNotLinked
         );
+        require!(!allocation.claimed, ErrorCode::AllocationAlreadyClaimed);
+        require!(!user_allocation.claimed, ErrorCode::AllocationAlreadyClaimed);
 
-        // Get lock period based on tier (using existing tier definitions)
-        // TierA = 365 days, TierB = 182 days, TierC = 90 days
-        let lock_period_days = match tier {
-            StakeTier::TierA => 365,
-            StakeTier::TierB => 182,
-            StakeTier::TierC => 90,
-        };
+        // Only spend on tokens still claimable at TGE moves over (vest-staked spend is excluded)
+        let tokens = allocation.total_tokens;
+        let mergeable_usd_cents = allocation
+            .total_usd_cents
+            .checked_sub(allocation.vest_staked_usd_cents)
+            .ok_or(ErrorCode::Overflow)?;
+        let usd_cents = mergeable_usd_cents.saturating_sub(allocation.merged_usd_cents);
 
-        // === CEI: EFFECTS FIRST (all state changes before transfer) ===
-        
-        // Initialize stake account
-        let stake_account = &mut ctx.accounts.stake_account;
-        stake_account.stake_id = config.next_stake_id;
-        stake_account.owner = ctx.accounts.beneficiary.key();
-        stake_account.amount = noc_amount;
-        stake_account.start_time = clock.unix_timestamp;
-        stake_account.lock_period_days = lock_period_days;
-        stake_account.last_reward_calculation = clock.unix_timestamp;
-        stake_account.pending_rewards = 0;
-        stake_account.active = true;
-        stake_account.tier = tier;
-        stake_account.auto_compound = auto_compound;
-        stake_account.cooldown_start = 0;
-        stake_account.is_vesting = true; // Mark as vesting stake - locked until TGE
+        // Unclaimed cross-chain referral bonus of the same ETH address
+        let mut bonus = 0u64;
+        if let Some(referral) = ctx.accounts.cross_chain_referral.as_mut() {
+            require!(
+                referral.linked_solana_wallet == user,
+                ErrorCode::WalletNotLinked
+            );
+            bonus = referral
+                .total_bonus
+                .checked_sub(referral.claimed_amount)
+                .ok_or(ErrorCode::Overflow)?;
+            referral.claimed_amount = referral.total_bonus;
+        }
+        require!(tokens > 0 || bonus > 0, ErrorCode::InvalidAmount);
 
-        // Update cross-chain allocation to track this
-        let allocation = &mut ctx.accounts.cross_chain_allocation;
-        if allocation.chain_id == 0 {
-            // First purchase - initialize
-            allocation.eth_address = buyer_eth_address;
-            allocation.chain_id = chain_id;
-            allocation.total_tokens = noc_amount;
-            allocation.total_usd_cents = usd_cents;
-            allocation.purchase_count = 1;
-            allocation.first_purchase_at = clock.unix_timestamp;
-            allocation.last_purchase_at = clock.unix_timestamp;
-            allocation.linked_solana_wallet = ctx.accounts.beneficiary.key();
-            allocation.claimed = true; // Mark as claimed since we're minting directly
-        } else {
-            // Additional purchase
-            allocation.total_tokens = allocation
-                .total_tokens
-                .checked_add(noc_amount)
+        // Respect the Solana per-user cap on combined spend
+        // (max_per_user_usd is a per-transaction cap; each merged purchase already passed its chain's per-tx limit)
+        let new_total_spent = user_allocation
+            .total_spent_cents
+            .checked_add(usd_cents)
+            .ok_or(ErrorCode::Overflow)?;
+        require!(
+            new_total_spent <= PRESALE_MAX_TOTAL_PER_USER_USD,
+            ErrorCode::ExceedsMaximumPurchase
+        );
+
+        // === EFFECTS ===
+        // Cross-chain tokens are already in the presale bucket; the bonus leaves escrow
+        if bonus > 0 {
+            debit_vault_bucket(config, VaultBucket::CommunityReserved, bonus)?;
+            credit_vault_bucket(config, VaultBucket::PresaleUnclaimed, bonus)?;
+            user_allocation.referral_bonus_tokens = user_allocation
+                .referral_bonus_tokens
+                .checked_add(bonus)
                 .ok_or(ErrorCode::Overflow)?;
-            allocation.total_usd_cents = allocation
-                .total_usd_cents
-                .checked_add(usd_cents)
+        }
+
+        user_allocation.user = user;
+        user_allocation.total_tokens = user_allocation
+            .total_tokens
+            .checked_add(tokens)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_add(bonus)
+            .ok_or(ErrorCode::Overflow)?;
+        user_allocation.total_spent_cents = new_total_spent;
+        if tokens > 0 {
+            let purchases = allocation
+                .purchase_count
+                .checked_sub(allocation.merged_purchase_count)
                 .ok_or(ErrorCode::Overflow)?;
-            allocation.purchase_count = allocation
+            if user_allocation.first_purchase_at == 0 {
+                user_allocation.first_purchase_at = allocation.first_purchase_at;
+            }
+            user_allocation.purchase_count = user_allocation
                 .purchase_count
-                .checked_add(1)
+                .checked_add(purchases)
                 .ok_or(ErrorCode::Overflow)?;
-            allocation.last_purchase_at = clock.unix_timestamp;
+            user_allocation.last_purchase_at = user_allocation
+                .last_purchase_at
+                .max(allocation.last_purchase_at);
         }
 
-        // Update global stats
-        config.tokens_sold = config
-            .tokens_sold
-            .checked_add(noc_amount)
-            .ok_or(ErrorCode::Overflow)?;
-        config.cross_chain_tokens_sold = config
-            .cross_chain_tokens_sold
-            .checked_add(noc_amount)
-            .ok_or(ErrorCode::Overflow)?;
-        config.total_usd_raised_cents = config
-            .total_usd_raised_cents
-            .checked_add(usd_cents)
+        // Mark the cross-chain record as merged (totals kept for cross-chain caps)
+        allocation.total_tokens = 0;
+        allocation.merged_tokens = allocation
+            .merged_tokens
+            .checked_add(tokens)
             .ok_or(ErrorCode::Overflow)?;
+        allocation.merged_usd_cents = mergeable_usd_cents;
+        allocation.merged_purchase_count = allocation.purchase_count;
+        allocation.merged_at = clock.unix_timestamp;
 
-        // Update staking stats
-        config.total_staked = config
-            .total_staked
-            .checked_add(noc_amount)
-            .ok_or(ErrorCode::Overflow)?;
-        config.next_stake_id = config
-            .next_stake_id
-            .checked_add(1)
-            .ok_or(ErrorCode::Overflow)?;
-        config.total_stakers = config
-            .total_stakers
-            .checked_add(1)
-            .ok_or(ErrorCode::Overflow)?;
+        msg!(
+            "Merged cross-chain allocation {:?} (chain {}) into {}: {} tokens, {} cents, {} referral bonus",
+            &eth_address[..4],
+            chain_id,
+            user,
+            tokens,
+            usd_cents,
+            bonus
+        );
+        Ok(())
+    }
 
-        // Update stage progress
-        config.stage_tokens_sold = config
-            .stage_tokens_sold
-            .checked_add(noc_amount)
+    /// Link a Solana wallet to a cross-chain referrer
+    /// Requires an EIP-191 signature from the referrer ETH address over the Solana wallet and current nonce
+    pub fn link_cross_chain_referral_wallet(
+        ctx: Context<LinkCrossChainReferralWallet>,
+        referrer_eth: [u8; 20],
+        chain_id: u64,
+        signature: [u8; 65],
+    ) -> Result<()> {
+        let referral = &mut ctx.accounts.cross_chain_referral;
+
+        // Verify the referral ledger belongs to this ETH address
+        require!(
+            referral.referrer_eth == referrer_eth && referral.chain_id == chain_id,
+            ErrorCode::InvalidAddress
+        );
+
+        // Verify the ETH address authorized this wallet (nonce makes every signature single-use)
+        verify_evm_link_signature(
+            &mut ctx.accounts.evm_link_nonce,
+            "referral",
+            referrer_eth,
+            chain_id,
+            ctx.accounts.user.key(),
+            ctx.program_id,
+            &signature,
+        )?;
+
+        referral.linked_solana_wallet = ctx.accounts.user.key();
+
+        msg!(
+            "Linked Solana wallet {} to referrer ETH address {:?}",
+            ctx.accounts.user.key(),
+            &referrer_eth[..4]
+        );
+
+        Ok(())
+    }
+
+    /// Claim accumulated cross-chain referral bonuses at TGE (linked Solana wallet only)
+    pub fn claim_cross_chain_referral_rewards(
+        ctx: Context<ClaimCrossChainReferralRewards>,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+        let referral = &mut ctx.accounts.cross_chain_referral;
+
+        // === CHECKS ===
+        require!(
+            clock.unix_timestamp >= config.tge_timestamp,
+            ErrorCode::TokensLocked
+        );
+
+        // Validate user is the linked wallet
+        require!(
+            referral.linked_solana_wallet == ctx.accounts.user.key(),
+            ErrorCode::WalletNotLinked
+        );
+
+        let amount = referral
+            .total_bonus
+            .checked_sub(referral.claimed_amount)
             .ok_or(ErrorCode::Overflow)?;
+        require!(amount > 0, ErrorCode::NoCrossChainReferralRewards);
 
-        // Advance stage if full
-        while config.stage_tokens_sold >= TOKENS_PER_STAGE * TOKEN_DECIMALS && config.current_stage < 9 {
-            config.current_stage += 1;
-            config.stage_tokens_sold = config.stage_tokens_sold
-                .checked_sub(TOKENS_PER_STAGE * TOKEN_DECIMALS)
-                .ok_or(ErrorCode::Overflow)?;
-            msg!("Stage advanced to {}", config.current_stage + 1);
-        }
+        // === CEI: EFFECTS FIRST (state changes before transfer) ===
+        referral.claimed_amount = referral.total_bonus;
+        debit_vault_bucket(config, VaultBucket::CommunityReserved, amount)?;
 
         // === CEI: INTERACTIONS LAST ===
-        // Transfer tokens from program treasury to stake pool
-        // The ico_ata_for_ico_program is a PDA token account with self-authority
         let ico_mint_key = ctx.accounts.ico_mint.key();
-        let (_, treasury_bump) = Pubkey::find_program_address(
+        let (expected_pda, bump) = Pubkey::find_program_address(
             &[ico_mint_key.as_ref()],
             ctx.program_id,
         );
-        let treasury_seeds = &[ico_mint_key.as_ref(), &[treasury_bump]];
-        let treasury_signer = &[&treasury_seeds[..]];
 
-        // Transfer tokens from treasury to stake pool
+        require!(
+            ctx.accounts.ico_ata_for_ico_program.key() == expected_pda,
+            ErrorCode::InvalidPDA
+        );
+
+        let seeds = &[ico_mint_key.as_ref(), &[bump]];
+        let signer = &[&seeds[..]];
+
         let cpi_ctx = CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
             Transfer {
                 from: ctx.accounts.ico_ata_for_ico_program.to_account_info(),
-                to: ctx.accounts.stake_pool_ata.to_account_info(),
+                to: ctx.accounts.ico_ata_for_user.to_account_info(),
                 authority: ctx.accounts.ico_ata_for_ico_program.to_account_info(),
             },
-            treasury_signer,
+            signer,
         );
-        token::transfer(cpi_ctx, noc_amount)?;
+        token::transfer(cpi_ctx, amount)?;
 
         msg!(
-            "Coordinator mint & vest stake: chain={}, eth={:?}, solana={}, noc={}, tier={:?}",
-            chain_id,
-            &buyer_eth_address[..4],
-            ctx.accounts.beneficiary.key(),
-            noc_amount,
-            tier
+            "Cross-chain referral claim: {} tokens to {}",
+            amount,
+            ctx.accounts.user.key()
         );
 
         Ok(())
     }
-}
-
-// =====================================================
-// HELPER FUNCTIONS
-// =====================================================
 
-/// Internal helper for stablecoin purchases (USDT/USDC)
-fn presale_purchase_with_stablecoin_internal<'info>(
-    config: &mut Account<'info, Config>,
-    user_account: &mut Account<'info, UserAccount>,
-    user_allocation: &mut Account<'info, PresaleAllocation>,
-    referrer_allocation: &AccountInfo<'info>,
-    stablecoin_ata_for_user: &Account<'info, TokenAccount>,
-    stablecoin_ata_for_admin: &Account<'info, TokenAccount>,
-    _stablecoin_mint: &Account<'info, Mint>,
-    user: &Signer<'info>,
-    token_program: &Program<'info, Token>,
-    stablecoin_amount: u64,
-    coin_name: &str,
-) -> Result<()> {
-    let clock = Clock::get()?;
+    /// Record a purchase paid on a non-EVM chain (Bitcoin, Tron, TON) into a foreign-address allocation
+    /// Coordinator only (single-coordinator mode). Uses the same replay protection, circuit
+    /// breaker, price verification and per-chain limits as EVM purchases. The referrer is
+    /// recorded for attribution; on-chain referral bonuses remain EVM-only.
+    /// Foreign purchases cannot be reversed (reverse_cross_chain_purchase is EVM-only), so the
+    /// coordinator must only record them once final on the source chain.
+    pub fn record_foreign_chain_purchase(
+        ctx: Context<RecordForeignChainPurchase>,
+        purchase: ForeignChainPurchase,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+        let ForeignChainPurchase {
+            address_hash,
+            chain_id,
+            tx_hash,
+            log_index,
+            buyer,
+            noc_amount,
+            usd_cents,
+            stage,
+            referrer,
+        } = purchase;
 
-    // Validate presale is active
-    require!(config.presale_active, ErrorCode::PresaleNotStarted);
-    require!(
-        clock.unix_timestamp >= config.presale_start_time,
-        ErrorCode::PresaleNotStarted
-    );
-    require!(
-        config.tokens_sold < PRESALE_TOTAL_ALLOCATION * TOKEN_DECIMALS,
-        ErrorCode::PresaleHardCapReached
-    );
+        require_active_coordinator(config, ctx.accounts.coordinator.key(), clock.unix_timestamp)?;
+        require!(
+            !config.coordinator_committee_enabled,
+            ErrorCode::CoordinatorCommitteeRequired
+        );
 
-    // Validate user not blocked
-    require!(!user_account.is_blocked, ErrorCode::AddressBlocked);
-    require!(stablecoin_amount > 0, ErrorCode::InvalidAmount);
+        // EVM buyers keep using the 20-byte CrossChainAllocation accounts
+        buyer.validate()?;
+        require!(buyer.family != ChainFamily::Evm, ErrorCode::InvalidForeignAddress);
+        require!(buyer.hash() == address_hash, ErrorCode::InvalidForeignAddress);
+        if !referrer.bytes.is_empty() {
+            referrer.validate()?;
+        }
 
-    // Convert stablecoin amount to USD cents
-    // USDT/USDC have 6 decimals, so 1_000_000 = $1.00 = 100 cents
-    let usd_cents = stablecoin_amount
-        .checked_mul(100)
-        .ok_or(ErrorCode::Overflow)?
-        .checked_div(1_000_000)
-        .ok_or(ErrorCode::Overflow)?;
+        let mut buyer_ref = [0u8; 20];
+        buyer_ref.copy_from_slice(&address_hash[..20]);
+        let evm_purchase = CrossChainPurchase {
+            buyer_eth_address: buyer_ref,
+            chain_id,
+            noc_amount,
+            usd_cents,
+            tx_hash,
+            log_index,
+            stage,
+            referrer_eth: [0u8; 20],
+        };
+        if !begin_cross_chain_purchase(
+            config,
+            &mut ctx.accounts.supported_chain,
+            &mut ctx.accounts.processed_tx,
+            &evm_purchase,
+            false,
+            clock.unix_timestamp,
+        )? {
+            return Ok(());
+        }
+        ctx.accounts.processed_tx.foreign = true;
 
-    msg!("{} amount: {}, USD value: {} cents", coin_name, stablecoin_amount, usd_cents);
+        let allocation = &mut ctx.accounts.foreign_allocation;
+        let supported_chain = &ctx.accounts.supported_chain;
+        let is_new = allocation.version == 0;
 
-    // Validate purchase limits (use config values if set, otherwise use constants)
-    let min_purchase = if config.min_purchase_usd > 0 { config.min_purchase_usd } else { PRESALE_MIN_PURCHASE_USD };
-    let max_purchase = if config.max_per_user_usd > 0 { config.max_per_user_usd } else { PRESALE_MAX_PURCHASE_USD };
-    
-    // Check minimum per transaction
-    require!(usd_cents >= min_purchase, ErrorCode::BelowMinimumPurchase);
-    
-    // Check maximum per transaction ($10,000)
-    require!(usd_cents <= max_purchase, ErrorCode::ExceedsMaximumPurchase);
-    
-    let new_total_spent = user_allocation.total_spent_cents
-        .checked_add(usd_cents)
-        .ok_or(ErrorCode::Overflow)?;
-    
-    // Check maximum total per user ($25,600)
-    require!(
-        new_total_spent <= PRESALE_MAX_TOTAL_PER_USER_USD,
-        ErrorCode::ExceedsMaximumPurchase
-    );
+        // Per-buyer cooldown and total limit
+        if !is_new {
+            let time_since_last = clock
+                .unix_timestamp
+                .checked_sub(allocation.last_purchase_at)
+                .ok_or(ErrorCode::Overflow)?;
+            require!(
+                time_since_last >= supported_chain.cooldown(),
+                ErrorCode::CrossChainCooldown
+            );
+        }
+        let new_total_usd = allocation
+            .total_usd_cents
+            .checked_add(usd_cents)
+            .ok_or(ErrorCode::Overflow)?;
+        require!(
+            new_total_usd <= supported_chain.max_total_usd(),
+            ErrorCode::CrossChainUserLimitExceeded
+        );
 
-    // Calculate tokens based on current stage price
-    let tokens_to_allocate = calculate_tokens_for_usd(usd_cents, config.current_stage)?;
+        if is_new {
+            allocation.version = FOREIGN_ALLOCATION_VERSION;
+            allocation.address = buyer;
+            allocation.address_hash = address_hash;
+            allocation.chain_id = chain_id;
+            allocation.first_purchase_at = clock.unix_timestamp;
+            allocation.referrer = referrer;
+        }
+        allocation.total_tokens = allocation
+            .total_tokens
+            .checked_add(noc_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        allocation.total_usd_cents = new_total_usd;
+        allocation.purchase_count = allocation
+            .purchase_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+        allocation.last_purchase_at = clock.unix_timestamp;
+        credit_vault_bucket(config, VaultBucket::PresaleUnclaimed, noc_amount)?;
 
-    // Transfer stablecoin from user to admin
-    let cpi_ctx = CpiContext::new(
-        token_program.to_account_info(),
-        Transfer {
-            from: stablecoin_ata_for_user.to_account_info(),
-            to: stablecoin_ata_for_admin.to_account_info(),
-            authority: user.to_account_info(),
-        },
-    );
-    token::transfer(cpi_ctx, stablecoin_amount)?;
-
-    // Process referral bonus (10%) - FROM COMMUNITY REWARDS POOL (not presale)
-    // ONE-TIME ONLY: Referral bonus is only given on the FIRST purchase
-    let referral_bonus = if user_allocation.referrer != Pubkey::default() && user_allocation.purchase_count == 0 {
-        let bonus = tokens_to_allocate
-            .checked_mul(REFERRAL_BONUS_PERCENTAGE)
-            .ok_or(ErrorCode::Overflow)?
-            .checked_div(100)
-            .ok_or(ErrorCode::Overflow)?;
-
-        // Check if Community Rewards pool has enough tokens
-        let new_total_referral = config.total_referral_bonuses
-            .checked_add(bonus)
-            .ok_or(ErrorCode::Overflow)?;
-        
-        if new_total_referral > COMMUNITY_REWARDS_ALLOCATION * TOKEN_DECIMALS {
-            // Pool exhausted - no bonus but purchase continues
-            msg!("Referral pool exhausted - no bonus awarded");
-            0
-        } else if !referrer_allocation.data_is_empty() {
-            // SECURITY: Validate referrer_allocation PDA matches expected seeds
-            let referrer_pubkey = user_allocation.referrer;
-            let (expected_pda, _bump) = Pubkey::find_program_address(
-                &[b"allocation", referrer_pubkey.as_ref()],
-                &crate::ID
-            );
-            if referrer_allocation.key() != expected_pda {
-                msg!("Invalid referrer PDA - skipping bonus");
-                0
-            } else {
-            
-            let mut referrer_data = referrer_allocation.try_borrow_mut_data()?;
-            let mut referrer_alloc = PresaleAllocation::try_deserialize(&mut &referrer_data[..])?;
-            
-            referrer_alloc.referral_bonus_tokens = referrer_alloc
-                .referral_bonus_tokens
-                .checked_add(bonus)
-                .ok_or(ErrorCode::Overflow)?;
-            referrer_alloc.total_tokens = referrer_alloc
-                .total_tokens
-                .checked_add(bonus)
-                .ok_or(ErrorCode::Overflow)?;
-            
-            referrer_alloc.try_serialize(&mut &mut referrer_data[..])?;
-            
-            // Track total referral bonuses issued (from Community Rewards pool)
-            config.total_referral_bonuses = new_total_referral;
-            
-            msg!("One-time referral bonus awarded: {} tokens", bonus);
-            bonus
-            }
-        } else {
-            0
-        }
-    } else {
-        if user_allocation.purchase_count > 0 {
-            msg!("Referral bonus skipped - not first purchase");
-        }
-        0
-    };
+        finish_cross_chain_purchase(config, noc_amount, usd_cents, clock.unix_timestamp)?;
+        record_coordinator_activity(
+            &mut ctx.accounts.coordinator_stats,
+            ctx.accounts.coordinator.key(),
+            noc_amount,
+            usd_cents,
+            false,
+            clock.unix_timestamp,
+        )?;
 
-    // Update user allocation
-    user_allocation.user = user.key();
-    user_allocation.total_tokens = user_allocation
-        .total_tokens
-        .checked_add(tokens_to_allocate)
-        .ok_or(ErrorCode::Overflow)?;
-    user_allocation.total_spent_cents = new_total_spent;
-    user_allocation.purchase_count = user_allocation.purchase_count.checked_add(1).ok_or(ErrorCode::Overflow)?;
-    
-    if user_allocation.purchase_count == 1 {
-        user_allocation.first_purchase_at = clock.unix_timestamp;
+        msg!(
+            "Foreign-chain purchase recorded: chain={}, family={:?}, buyer_hash={:?}, noc={}, usd_cents={}",
+            chain_id,
+            allocation.address.family,
+            &address_hash[..4],
+            noc_amount,
+            usd_cents
+        );
+        Ok(())
     }
-    user_allocation.last_purchase_at = clock.unix_timestamp;
-
-    // Update stage progress
-    config.stage_tokens_sold = config
-        .stage_tokens_sold
-        .checked_add(tokens_to_allocate)
-        .ok_or(ErrorCode::Overflow)?;
-    config.tokens_sold = config
-        .tokens_sold
-        .checked_add(tokens_to_allocate)
-        .ok_or(ErrorCode::Overflow)?;
-    config.total_usd_raised_cents = config
-        .total_usd_raised_cents
-        .checked_add(usd_cents)
-        .ok_or(ErrorCode::Overflow)?;
 
-    // Advance stage if current stage is full
-    while config.stage_tokens_sold >= TOKENS_PER_STAGE * TOKEN_DECIMALS && config.current_stage < 9 {
-        config.current_stage += 1;
-        config.stage_tokens_sold = config.stage_tokens_sold
-            .checked_sub(TOKENS_PER_STAGE * TOKEN_DECIMALS)
-            .ok_or(ErrorCode::Overflow)?;
-        
-        msg!("Advanced to stage {}", config.current_stage + 1);
-    }
+    /// Admin function to link a Solana wallet to a foreign-address allocation
+    /// Ownership of the non-EVM address is verified off-chain (signature schemes differ per chain),
+    /// so the link is one-time and the claim stays time-locked for FOREIGN_LINK_TIMELOCK_SECONDS
+    /// to let the buyer dispute a wrong link before any tokens move
+    pub fn link_foreign_allocation_wallet(
+        ctx: Context<LinkForeignAllocationWallet>,
+        address_hash: [u8; 32],
+        chain_id: u64,
+        wallet: Pubkey,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.config.admin == ctx.accounts.admin.key(),
+            ErrorCode::InvalidAdmin
+        );
+        let allocation = &mut ctx.accounts.foreign_allocation;
+        require!(
+            allocation.address_hash == address_hash && allocation.chain_id == chain_id,
+            ErrorCode::InvalidAddress
+        );
+        require!(!allocation.claimed, ErrorCode::AllocationAlreadyClaimed);
+        require!(
+            allocation.linked_solana_wallet == Pubkey::default(),
+            ErrorCode::WalletAlreadyLinked
+        );
+        require!(wallet != Pubkey::default(), ErrorCode::InvalidAddress);
 
-    // Close presale if hard cap reached
-    if config.tokens_sold >= PRESALE_TOTAL_ALLOCATION * TOKEN_DECIMALS {
-        config.presale_active = false;
-        msg!("Presale hard cap reached - presale closed");
+        let clock = Clock::get()?;
+        allocation.linked_solana_wallet = wallet;
+        allocation.linked_at = clock.unix_timestamp;
+        msg!(
+            "Linked Solana wallet {} to foreign address {:?} on chain {} (claimable after {})",
+            wallet,
+            &address_hash[..4],
+            chain_id,
+            clock.unix_timestamp + FOREIGN_LINK_TIMELOCK_SECONDS
+        );
+        Ok(())
     }
 
-    msg!(
-        "{} Allocation recorded: {} tokens to user, {} referral bonus",
-        coin_name,
-        tokens_to_allocate,
-        referral_bonus
-    );
-
-    Ok(())
-}
-
-/// Internal function for stablecoin (USDT/USDC) purchase with vesting stake
-/// Combines stablecoin purchase with automatic vesting stake creation/update
-fn presale_purchase_stablecoin_and_vest_stake_internal<'info>(
-    config: &mut Account<'info, Config>,
-    user_account: &mut Account<'info, UserAccount>,
-    user_allocation: &mut Account<'info, PresaleAllocation>,
-    stake_account: &mut Account<'info, StakeAccount>,
-    stablecoin_ata_for_user: &Account<'info, TokenAccount>,
-    stablecoin_ata_for_admin: &Account<'info, TokenAccount>,
-    user: &Signer<'info>,
-    token_program: &Program<'info, Token>,
-    stablecoin_amount: u64,
-    tier: StakeTier,
-    auto_compound: bool,
-    coin_name: &str,
-) -> Result<()> {
-    let clock = Clock::get()?;
+    /// Claim a foreign-address allocation at TGE (linked wallet only)
+    pub fn claim_foreign_allocation(ctx: Context<ClaimForeignAllocation>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+        let allocation = &mut ctx.accounts.foreign_allocation;
 
-    // Validate presale is active
-    require!(config.presale_active, ErrorCode::PresaleNotStarted);
-    require!(
-        clock.unix_timestamp >= config.presale_start_time,
-        ErrorCode::PresaleNotStarted
-    );
-    require!(
-        config.tokens_sold < PRESALE_TOTAL_ALLOCATION * TOKEN_DECIMALS,
-        ErrorCode::PresaleHardCapReached
-    );
+        // === CHECKS ===
+        require!(
+            clock.unix_timestamp >= config.tge_timestamp,
+            ErrorCode::TokensLocked
+        );
+        require!(allocation.total_tokens > 0, ErrorCode::InvalidAmount);
+        require!(!allocation.claimed, ErrorCode::AllocationAlreadyClaimed);
+        require!(
+            allocation.linked_solana_wallet == ctx.accounts.user.key(),
+            ErrorCode::WalletNotLinked
+        );
+        require!(
+            clock.unix_timestamp
+                >= allocation
+                    .linked_at
+                    .checked_add(FOREIGN_LINK_TIMELOCK_SECONDS)
+                    .ok_or(ErrorCode::Overflow)?,
+            ErrorCode::WalletLinkTimelocked
+        );
 
-    // Validate user not blocked
-    require!(!user_account.is_blocked, ErrorCode::AddressBlocked);
-    require!(stablecoin_amount > 0, ErrorCode::InvalidAmount);
+        let total_to_claim = allocation.total_tokens;
 
-    // Convert stablecoin amount to USD cents
-    // USDT/USDC have 6 decimals, so 1_000_000 = $1.00 = 100 cents
-    let usd_cents = stablecoin_amount
-        .checked_mul(100)
-        .ok_or(ErrorCode::Overflow)?
-        .checked_div(1_000_000)
-        .ok_or(ErrorCode::Overflow)?;
+        // === CEI: EFFECTS FIRST (state changes before transfer) ===
+        allocation.claimed = true;
+        debit_vault_bucket(config, VaultBucket::PresaleUnclaimed, total_to_claim)?;
 
-    msg!("{} Vesting Stake: {} amount: {}, USD value: {} cents", coin_name, coin_name, stablecoin_amount, usd_cents);
+        // === CEI: INTERACTIONS LAST ===
+        let ico_mint_key = ctx.accounts.ico_mint.key();
+        let bump = ctx.bumps.ico_ata_for_ico_program;
+        let seeds = &[ico_mint_key.as_ref(), &[bump]];
+        let signer = &[&seeds[..]];
 
-    // Validate purchase limits (use config values if set, otherwise use constants)
-    let min_purchase = if config.min_purchase_usd > 0 { config.min_purchase_usd } else { PRESALE_MIN_PURCHASE_USD };
-    let max_purchase = if config.max_per_user_usd > 0 { config.max_per_user_usd } else { PRESALE_MAX_PURCHASE_USD };
-    
-    // Check minimum per transaction
-    require!(usd_cents >= min_purchase, ErrorCode::BelowMinimumPurchase);
-    
-    // Check maximum per transaction ($10,000)
-    require!(usd_cents <= max_purchase, ErrorCode::ExceedsMaximumPurchase);
-    
-    let new_total_spent = user_allocation.total_spent_cents
-        .checked_add(usd_cents)
-        .ok_or(ErrorCode::Overflow)?;
-    
-    // Check maximum total per user ($25,600)
-    require!(
-        new_total_spent <= PRESALE_MAX_TOTAL_PER_USER_USD,
-        ErrorCode::ExceedsMaximumPurchase
-    );
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.ico_ata_for_ico_program.to_account_info(),
+                to: ctx.accounts.ico_ata_for_user.to_account_info(),
+                authority: ctx.accounts.ico_ata_for_ico_program.to_account_info(),
+            },
+            signer,
+        );
+        token::transfer(cpi_ctx, total_to_claim)?;
 
-    // Calculate tokens based on current stage price
-    let tokens_to_stake = calculate_tokens_for_usd(usd_cents, config.current_stage)?;
+        msg!(
+            "Foreign-chain claim: {} tokens to {}",
+            total_to_claim,
+            ctx.accounts.user.key()
+        );
+        Ok(())
+    }
 
-    // Check global staking cap
-    require!(
-        config.total_staked.checked_add(tokens_to_stake).ok_or(ErrorCode::Overflow)? <= MAX_TOTAL_STAKED,
-        ErrorCode::StakingCapReached
-    );
+    /// Reverse a recorded cross-chain purchase (EVM reorg or chargeback)
+    /// Coordinator or admin only (admin only in committee mode). Not allowed once the tokens were claimed, merged or vest-staked.
+    /// The processed tx record is kept (marked reversed) so the event cannot be replayed.
+    pub fn reverse_cross_chain_purchase(
+        ctx: Context<ReverseCrossChainPurchase>,
+        chain_id: u64,
+        tx_hash: [u8; 32],
+        log_index: u32,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+        let processed_tx = &mut ctx.accounts.processed_tx;
+        let allocation = &mut ctx.accounts.cross_chain_allocation;
 
-    // Validate tier-specific constraints
-    let lock_period_days = match tier {
-        StakeTier::TierA => {
+        // === CHECKS ===
+        // In committee mode a single coordinator cannot undo attested purchases - admin only
+        let authority = ctx.accounts.authority.key();
+        if authority != config.admin {
             require!(
-                config.total_staked_tier_a.checked_add(tokens_to_stake).ok_or(ErrorCode::Overflow)? <= MAX_STAKE_TIER_A,
-                ErrorCode::TierAFull
+                !config.coordinator_committee_enabled,
+                ErrorCode::CoordinatorCommitteeRequired
             );
-            LOCK_PERIOD_TIER_A
+            require_active_coordinator(config, authority, clock.unix_timestamp)?;
         }
-        StakeTier::TierB => LOCK_PERIOD_TIER_B,
-        StakeTier::TierC => LOCK_PERIOD_TIER_C,
-    };
+        require!(
+            processed_tx.chain_id == chain_id
+                && processed_tx.tx_hash == tx_hash
+                && processed_tx.log_index == log_index,
+            ErrorCode::InvalidAddress
+        );
+        require!(processed_tx.processed_at != 0, ErrorCode::CrossChainPurchaseNotReversible);
+        require!(processed_tx.reversed_at == 0, ErrorCode::CrossChainPurchaseAlreadyReversed);
+        require!(
+            !processed_tx.vest_staked && !processed_tx.foreign,
+            ErrorCode::CrossChainPurchaseNotReversible
+        );
+        require!(!allocation.claimed, ErrorCode::AllocationAlreadyClaimed);
 
-    // Transfer stablecoin from user to admin
-    let cpi_ctx = CpiContext::new(
-        token_program.to_account_info(),
-        Transfer {
-            from: stablecoin_ata_for_user.to_account_info(),
-            to: stablecoin_ata_for_admin.to_account_info(),
-            authority: user.to_account_info(),
-        },
-    );
-    token::transfer(cpi_ctx, stablecoin_amount)?;
+        // Purchases already merged into a Solana allocation can no longer be taken back here
+        let noc_amount = processed_tx.noc_amount;
+        let usd_cents = processed_tx.usd_cents;
+        require!(
+            processed_tx.processed_at > allocation.merged_at && allocation.total_tokens >= noc_amount,
+            ErrorCode::CrossChainPurchaseNotReversible
+        );
 
-    // Update user allocation (record keeping)
-    user_allocation.user = user.key();
-    user_allocation.total_tokens = user_allocation
-        .total_tokens
-        .checked_add(tokens_to_stake)
-        .ok_or(ErrorCode::Overflow)?;
-    user_allocation.total_spent_cents = new_total_spent;
-    user_allocation.purchase_count = user_allocation.purchase_count.checked_add(1).ok_or(ErrorCode::Overflow)?;
-    
-    if user_allocation.purchase_count == 1 {
-        user_allocation.first_purchase_at = clock.unix_timestamp;
+        // === EFFECTS ===
+        allocation.total_tokens = allocation
+            .total_tokens
+            .checked_sub(noc_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        allocation.total_usd_cents = allocation
+            .total_usd_cents
+            .checked_sub(usd_cents)
+            .ok_or(ErrorCode::Overflow)?;
+        allocation.purchase_count = allocation
+            .purchase_count
+            .checked_sub(1)
+            .ok_or(ErrorCode::Overflow)?;
+        debit_vault_bucket(config, VaultBucket::PresaleUnclaimed, noc_amount)?;
+
+        // Reverse the referral bonus and referred volume this purchase added (bonus only while unclaimed)
+        let bonus = processed_tx.referral_bonus;
+        if processed_tx.referred {
+            let referral = ctx
+                .accounts
+                .referrer_cross_chain_referral
+                .as_mut()
+                .ok_or(ErrorCode::ReferralAccountsRequired)?;
+            let unclaimed = referral
+                .total_bonus
+                .checked_sub(referral.claimed_amount)
+                .ok_or(ErrorCode::Overflow)?;
+            require!(unclaimed >= bonus, ErrorCode::CrossChainPurchaseNotReversible);
+
+            referral.total_bonus = referral
+                .total_bonus
+                .checked_sub(bonus)
+                .ok_or(ErrorCode::Overflow)?;
+            referral.referred_usd_cents = referral
+                .referred_usd_cents
+                .checked_sub(usd_cents)
+                .ok_or(ErrorCode::Overflow)?;
+            // The referee no longer counts once its only purchase is reversed
+            if allocation.purchase_count == 0 {
+                referral.referral_count = referral
+                    .referral_count
+                    .checked_sub(1)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
+            allocation.referral_bonus = allocation
+                .referral_bonus
+                .checked_sub(bonus)
+                .ok_or(ErrorCode::Overflow)?;
+            config.total_referral_bonuses = config
+                .total_referral_bonuses
+                .checked_sub(bonus)
+                .ok_or(ErrorCode::Overflow)?;
+            debit_vault_bucket(config, VaultBucket::CommunityReserved, bonus)?;
+        }
+
+        // Global stats (stage progress only if still in the stage it was recorded in)
+        config.tokens_sold = config
+            .tokens_sold
+            .checked_sub(noc_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        config.cross_chain_tokens_sold = config
+            .cross_chain_tokens_sold
+            .checked_sub(noc_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        config.total_usd_raised_cents = config
+            .total_usd_raised_cents
+            .checked_sub(usd_cents)
+            .ok_or(ErrorCode::Overflow)?;
+        if config.current_stage == processed_tx.recorded_stage {
+            config.stage_tokens_sold = config.stage_tokens_sold.saturating_sub(noc_amount);
+        }
+
+        // Free the reversed USD in the rolling rate-limit windows it was counted in
+        let supported_chain = &mut ctx.accounts.supported_chain;
+        config.cross_chain_usage.roll(clock.unix_timestamp);
+        config.cross_chain_usage.unrecord(usd_cents, processed_tx.processed_at, clock.unix_timestamp);
+        supported_chain.usage.roll(clock.unix_timestamp);
+        supported_chain.usage.unrecord(usd_cents, processed_tx.processed_at, clock.unix_timestamp);
+
+        processed_tx.reversed_at = clock.unix_timestamp;
+
+        msg!(
+            "Cross-chain purchase reversed: chain={}, buyer={:?}, noc={}, usd_cents={}, referral_bonus={}",
+            chain_id,
+            &processed_tx.buyer_eth_address[..4],
+            noc_amount,
+            usd_cents,
+            bonus
+        );
+        Ok(())
     }
-    user_allocation.last_purchase_at = clock.unix_timestamp;
 
-    // Create or update vesting stake (PDA per user per tier)
-    let is_new_stake = stake_account.owner == Pubkey::default();
-    
-    if is_new_stake {
-        // New stake account - initialize
-        let stake_id = config.next_stake_id;
-        stake_account.stake_id = stake_id;
-        stake_account.owner = user.key();
-        stake_account.amount = tokens_to_stake;
+    /// Coordinator-initiated mint and vesting stake for EVM buyers
+    /// This allows EVM buyers to receive tokens immediately (minted + staked) instead of waiting for TGE
+    /// Called by coordinator when EVM buyer provides a Solana address
+    pub fn coordinator_mint_and_vest_stake(
+        ctx: Context<CoordinatorMintAndVestStake>,
+        purchase: CrossChainPurchase,
+        tier: StakeTier,
+        auto_compound: bool,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let clock = Clock::get()?;
+        let CrossChainPurchase { buyer_eth_address, chain_id, noc_amount, usd_cents, .. } = purchase;
+
+        // Validate coordinator (current, or either key during a rotation overlap)
+        require_active_coordinator(config, ctx.accounts.coordinator.key(), clock.unix_timestamp)?;
+
+        // Committee mode - single-coordinator credits are disabled (no attested vest-stake path yet)
+        require!(
+            !config.coordinator_committee_enabled,
+            ErrorCode::CoordinatorCommitteeRequired
+        );
+
+        // Validate beneficiary not blocked
+        require!(
+            !ctx.accounts.beneficiary_user_account.is_blocked,
+            ErrorCode::AddressBlocked
+        );
+
+        // Check global staking cap
+        require!(
+            config.total_staked.checked_add(noc_amount).ok_or(ErrorCode::Overflow)? <= MAX_TOTAL_STAKED,
+            ErrorCode::StakingCapReached
+        );
+
+        // Validate tier-specific constraints
+        let lock_period_days = match tier {
+            StakeTier::TierA => {
+                require!(
+                    config.total_staked_tier_a.checked_add(noc_amount).ok_or(ErrorCode::Overflow)? <= MAX_STAKE_TIER_A,
+                    ErrorCode::TierAFull
+                );
+                LOCK_PERIOD_TIER_A
+            }
+            StakeTier::TierB => LOCK_PERIOD_TIER_B,
+            StakeTier::TierC => LOCK_PERIOD_TIER_C,
+        };
+
+        // Same replay protection, price verification, per-chain limits, cooldown,
+        // referral bonus and sale accounting as record_cross_chain_purchase
+        let credited = record_cross_chain_purchase_internal(
+            config,
+            &mut ctx.accounts.supported_chain,
+            &mut ctx.accounts.processed_tx,
+            &mut ctx.accounts.cross_chain_allocation,
+            &mut ctx.accounts.referrer_cross_chain_referral,
+            &purchase,
+            true,
+        )?;
+
+        // Circuit breaker tripped - release the unused stake account and stop
+        if !credited {
+            ctx.accounts
+                .stake_account
+                .close(ctx.accounts.coordinator.to_account_info())?;
+            ctx.accounts
+                .evm_stake_link
+                .close(ctx.accounts.coordinator.to_account_info())?;
+            return Ok(());
+        }
+
+        record_coordinator_activity(
+            &mut ctx.accounts.coordinator_stats,
+            ctx.accounts.coordinator.key(),
+            noc_amount,
+            usd_cents,
+            true,
+            clock.unix_timestamp,
+        )?;
+
+        // === CEI: EFFECTS FIRST (all state changes before transfer) ===
+        
+        // Initialize stake account
+        let stake_account = &mut ctx.accounts.stake_account;
+        stake_account.stake_id = config.next_stake_id;
+        stake_account.owner = ctx.accounts.beneficiary.key();
+        stake_account.amount = noc_amount;
         stake_account.start_time = clock.unix_timestamp;
         stake_account.lock_period_days = lock_period_days;
         stake_account.last_reward_calculation = clock.unix_timestamp;
@@ -2650,417 +3944,3733 @@ fn presale_purchase_stablecoin_and_vest_stake_internal<'info>(
         stake_account.tier = tier;
         stake_account.auto_compound = auto_compound;
         stake_account.cooldown_start = 0;
-        stake_account.is_vesting = true;
-        stake_account.total_added = tokens_to_stake;
-        
-        config.next_stake_id = stake_id.checked_add(1).ok_or(ErrorCode::Overflow)?;
-        
-        msg!("{}: Created new vesting stake: {} tokens in {:?} tier", coin_name, tokens_to_stake, tier);
-    } else {
-        // Existing stake - calculate pending rewards first, then add new tokens
-        let apy = match stake_account.tier {
-            StakeTier::TierA => APY_TIER_A,
-            StakeTier::TierB => APY_TIER_B,
-            StakeTier::TierC => APY_TIER_C,
-        };
-        
-        let time_elapsed = clock.unix_timestamp
-            .checked_sub(stake_account.last_reward_calculation)
+        stake_account.is_vesting = true; // Mark as vesting stake - locked until TGE
+        stake_account.total_added = noc_amount;
+
+        // Update staking stats
+        config.total_staked = config
+            .total_staked
+            .checked_add(noc_amount)
             .ok_or(ErrorCode::Overflow)?;
-        
-        if time_elapsed > 0 {
-            let rewards = (stake_account.amount as u128)
-                .checked_mul(apy as u128)
-                .ok_or(ErrorCode::Overflow)?
-                .checked_mul(time_elapsed as u128)
-                .ok_or(ErrorCode::Overflow)?
-                .checked_div(100 * SECONDS_PER_YEAR as u128)
-                .ok_or(ErrorCode::Overflow)? as u64;
-            
-            if stake_account.auto_compound {
-                stake_account.amount = stake_account.amount
-                    .checked_add(rewards)
-                    .ok_or(ErrorCode::Overflow)?;
-            } else {
-                stake_account.pending_rewards = stake_account.pending_rewards
-                    .checked_add(rewards)
-                    .ok_or(ErrorCode::Overflow)?;
-            }
+        if tier == StakeTier::TierA {
+            config.total_staked_tier_a = config
+                .total_staked_tier_a
+                .checked_add(noc_amount)
+                .ok_or(ErrorCode::Overflow)?;
         }
-        
-        // Add new tokens to existing stake
-        stake_account.amount = stake_account.amount
-            .checked_add(tokens_to_stake)
-            .ok_or(ErrorCode::Overflow)?;
-        stake_account.total_added = stake_account.total_added
-            .checked_add(tokens_to_stake)
+        add_pool_stake(config, stake_account, noc_amount, clock.unix_timestamp)?;
+        config.next_stake_id = config
+            .next_stake_id
+            .checked_add(1)
             .ok_or(ErrorCode::Overflow)?;
-        stake_account.last_reward_calculation = clock.unix_timestamp;
-        stake_account.auto_compound = auto_compound; // Update auto_compound preference
-        
-        msg!("{}: Added {} tokens to existing {:?} stake (total: {})", coin_name, tokens_to_stake, tier, stake_account.amount);
+
+        // The EVM buyer can manage this stake with EVM signatures
+        let evm_stake_link = &mut ctx.accounts.evm_stake_link;
+        evm_stake_link.stake = ctx.accounts.stake_account.key();
+        evm_stake_link.eth_address = buyer_eth_address;
+        evm_stake_link.chain_id = chain_id;
+
+        // Register the stake PDA so the beneficiary can discover it without scanning
+        register_indexed_stake(
+            &mut ctx.accounts.beneficiary_stake_index,
+            ctx.accounts.beneficiary.key(),
+            ctx.accounts.stake_account.key(),
+        )?;
+
+        // Count each beneficiary once
+        let beneficiary_user_account = &mut ctx.accounts.beneficiary_user_account;
+        if !beneficiary_user_account.has_staked {
+            beneficiary_user_account.has_staked = true;
+            config.total_stakers = config
+                .total_stakers
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+
+        // Staked principal stays in the ICO vault (tracked by total_staked) like Solana vesting stakes
+
+        msg!(
+            "Coordinator mint & vest stake: chain={}, eth={:?}, solana={}, noc={}, tier={:?}",
+            chain_id,
+            &buyer_eth_address[..4],
+            ctx.accounts.beneficiary.key(),
+            noc_amount,
+            tier
+        );
+
+        Ok(())
     }
 
-    // Update staking config
-    config.total_staked = config
-        .total_staked
-        .checked_add(tokens_to_stake)
-        .ok_or(ErrorCode::Overflow)?;
-    
-    if tier == StakeTier::TierA {
-        config.total_staked_tier_a = config
-            .total_staked_tier_a
-            .checked_add(tokens_to_stake)
-            .ok_or(ErrorCode::Overflow)?;
+    /// Register an existing keypair stake account (created by coordinator_mint_and_vest_stake
+    /// before stakes were PDAs) in its owner's stake index. Callable by the owner or admin.
+    pub fn register_legacy_stake(ctx: Context<RegisterLegacyStake>) -> Result<()> {
+        let stake_account = &ctx.accounts.stake_account;
+        let authority = ctx.accounts.authority.key();
+        require!(
+            authority == stake_account.owner || authority == ctx.accounts.config.admin,
+            ErrorCode::NotStakeOwner
+        );
+        require!(stake_account.active, ErrorCode::StakeNotActive);
+
+        // Reject PDA stakes (regular, coordinator and vesting) - only keypair accounts need indexing
+        let (regular_pda, _) = Pubkey::find_program_address(
+            &[b"stake", stake_account.owner.as_ref(), &stake_account.stake_id.to_le_bytes()],
+            ctx.program_id,
+        );
+        let (vesting_pda, _) = Pubkey::find_program_address(
+            &[b"vesting_stake", stake_account.owner.as_ref(), &[stake_account.tier as u8]],
+            ctx.program_id,
+        );
+        require!(
+            stake_account.key() != regular_pda && stake_account.key() != vesting_pda,
+            ErrorCode::NotLegacyStake
+        );
+
+        register_indexed_stake(
+            &mut ctx.accounts.stake_index,
+            stake_account.owner,
+            stake_account.key(),
+        )?;
+
+        msg!(
+            "Registered legacy stake {} (id {}) for {}",
+            stake_account.key(),
+            stake_account.stake_id,
+            stake_account.owner
+        );
+        Ok(())
     }
 
-    // Update stage progress
-    config.stage_tokens_sold = config
-        .stage_tokens_sold
-        .checked_add(tokens_to_stake)
-        .ok_or(ErrorCode::Overflow)?;
-    config.tokens_sold = config
-        .tokens_sold
-        .checked_add(tokens_to_stake)
-        .ok_or(ErrorCode::Overflow)?;
-    config.total_usd_raised_cents = config
-        .total_usd_raised_cents
-        .checked_add(usd_cents)
-        .ok_or(ErrorCode::Overflow)?;
+    /// Toggle auto-compound on a coordinator-created stake, signed by its EVM buyer (relayer-paid)
+    pub fn evm_toggle_auto_compound(
+        ctx: Context<EvmManageStake>,
+        signature: [u8; 65],
+    ) -> Result<()> {
+        let stake_key = ctx.accounts.stake_account.key();
+        verify_evm_stake_action(
+            &mut ctx.accounts.evm_link_nonce,
+            &ctx.accounts.cross_chain_allocation,
+            "toggle auto-compound",
+            stake_key,
+            ctx.program_id,
+            &signature,
+        )?;
+        require!(ctx.accounts.stake_account.active, ErrorCode::StakeNotActive);
 
-    // Advance stage if current stage is full
-    while config.stage_tokens_sold >= TOKENS_PER_STAGE * TOKEN_DECIMALS && config.current_stage < 9 {
-        config.current_stage += 1;
-        config.stage_tokens_sold = config.stage_tokens_sold
-            .checked_sub(TOKENS_PER_STAGE * TOKEN_DECIMALS)
-            .ok_or(ErrorCode::Overflow)?;
-        
-        msg!("Advanced to stage {}", config.current_stage + 1);
+        let stake_account = &mut ctx.accounts.stake_account;
+        stake_account.auto_compound = !stake_account.auto_compound;
+
+        msg!(
+            "EVM-signed: auto-compound toggled to {} for stake {}",
+            stake_account.auto_compound,
+            stake_account.stake_id
+        );
+        Ok(())
+    }
+
+    /// Initiate unstake of a coordinator-created stake, signed by its EVM buyer (relayer-paid)
+    pub fn evm_initiate_unstake(
+        ctx: Context<EvmManageStake>,
+        signature: [u8; 65],
+    ) -> Result<()> {
+        let stake_key = ctx.accounts.stake_account.key();
+        verify_evm_stake_action(
+            &mut ctx.accounts.evm_link_nonce,
+            &ctx.accounts.cross_chain_allocation,
+            "initiate unstake",
+            stake_key,
+            ctx.program_id,
+            &signature,
+        )?;
+        require!(
+            !ctx.accounts.beneficiary_user_account.is_blocked,
+            ErrorCode::AddressBlocked
+        );
+        require!(ctx.accounts.stake_account.active, ErrorCode::StakeNotActive);
+
+        // EVM-signed unstakes always cover the whole stake
+        let clock = Clock::get()?;
+        let amount = ctx.accounts.stake_account.amount;
+        begin_unstake_cooldown(
+            &mut ctx.accounts.config,
+            &mut ctx.accounts.stake_account,
+            amount,
+            clock.unix_timestamp,
+        )
     }
 
-    // Close presale if hard cap reached
-    if config.tokens_sold >= PRESALE_TOTAL_ALLOCATION * TOKEN_DECIMALS {
-        config.presale_active = false;
-        msg!("Presale hard cap reached - presale closed");
-    }
+    /// Finalize unstake of a coordinator-created stake, signed by its EVM buyer (relayer-paid)
+    /// Tokens always go to a token account owned by the stake's beneficiary
+    pub fn evm_finalize_unstake(
+        ctx: Context<EvmFinalizeUnstake>,
+        signature: [u8; 65],
+    ) -> Result<()> {
+        let stake_key = ctx.accounts.stake_account.key();
+        verify_evm_stake_action(
+            &mut ctx.accounts.evm_link_nonce,
+            &ctx.accounts.cross_chain_allocation,
+            "finalize unstake",
+            stake_key,
+            ctx.program_id,
+            &signature,
+        )?;
+        require!(
+            !ctx.accounts.beneficiary_user_account.is_blocked,
+            ErrorCode::AddressBlocked
+        );
+        require!(ctx.accounts.stake_account.active, ErrorCode::StakeNotActive);
+
+        let clock = Clock::get()?;
+        settle_unstake(
+            &mut ctx.accounts.config,
+            &mut ctx.accounts.stake_account,
+            &ctx.accounts.ico_ata_for_ico_program,
+            ctx.accounts.reward_vault.as_ref().zip(ctx.bumps.reward_vault),
+            &ctx.accounts.ico_ata_for_beneficiary,
+            &ctx.accounts.token_program,
+            clock.unix_timestamp,
+        )?;
+        if let Some(index) = ctx.accounts.stake_index.as_mut() {
+            unindex_closed_stake(index, &ctx.accounts.stake_account);
+        }
+
+        Ok(())
+    }
+
+    /// Move a coordinator-created stake to a new Solana beneficiary, signed by its EVM buyer
+    /// (relayer-paid). The stake moves between the old and new beneficiaries' stake indexes.
+    pub fn evm_change_stake_beneficiary(
+        ctx: Context<EvmChangeStakeBeneficiary>,
+        new_beneficiary: Pubkey,
+        signature: [u8; 65],
+    ) -> Result<()> {
+        let stake_key = ctx.accounts.stake_account.key();
+        let action = format!("change beneficiary to {}", new_beneficiary);
+        verify_evm_stake_action(
+            &mut ctx.accounts.evm_link_nonce,
+            &ctx.accounts.cross_chain_allocation,
+            &action,
+            stake_key,
+            ctx.program_id,
+            &signature,
+        )?;
+        require!(ctx.accounts.stake_account.active, ErrorCode::StakeNotActive);
+        require!(new_beneficiary != Pubkey::default(), ErrorCode::InvalidAddress);
+
+        let old_beneficiary = ctx.accounts.stake_account.owner;
+        require!(new_beneficiary != old_beneficiary, ErrorCode::InvalidAddress);
+
+        unregister_indexed_stake(&mut ctx.accounts.old_stake_index, stake_key);
+        register_indexed_stake(&mut ctx.accounts.new_stake_index, new_beneficiary, stake_key)?;
+        ctx.accounts.stake_account.owner = new_beneficiary;
+
+        msg!(
+            "EVM-signed: stake {} beneficiary changed {} -> {}",
+            ctx.accounts.stake_account.stake_id,
+            old_beneficiary,
+            new_beneficiary
+        );
+        Ok(())
+    }
+}
+
+// =====================================================
+// HELPER FUNCTIONS
+// =====================================================
+
+/// Internal helper for stablecoin purchases (USDT/USDC)
+fn presale_purchase_with_stablecoin_internal<'info>(
+    config: &mut Account<'info, Config>,
+    user_account: &mut Account<'info, UserAccount>,
+    user_allocation: &mut Account<'info, PresaleAllocation>,
+    referrer_allocation: &AccountInfo<'info>,
+    referrer_stats: &mut Option<Account<'info, ReferrerStats>>,
+    referral_record: &mut Option<Account<'info, ReferralRecord>>,
+    referrer_user_account: &mut Option<Account<'info, UserAccount>>,
+    pending_referral_rewards: &mut Option<Account<'info, PendingReferralRewards>>,
+    stablecoin_ata_for_user: &Account<'info, TokenAccount>,
+    stablecoin_ata_for_admin: &Account<'info, TokenAccount>,
+    _stablecoin_mint: &Account<'info, Mint>,
+    user: &Signer<'info>,
+    token_program: &Program<'info, Token>,
+    stablecoin_amount: u64,
+    coin_name: &str,
+) -> Result<()> {
+    let clock = Clock::get()?;
+
+    // Validate presale is active
+    require!(config.presale_active, ErrorCode::PresaleNotStarted);
+    require!(
+        clock.unix_timestamp >= config.presale_start_time,
+        ErrorCode::PresaleNotStarted
+    );
+    require!(
+        config.tokens_sold < PRESALE_TOTAL_ALLOCATION * TOKEN_DECIMALS,
+        ErrorCode::PresaleHardCapReached
+    );
+
+    // Validate user not blocked
+    require!(!user_account.is_blocked, ErrorCode::AddressBlocked);
+    require!(stablecoin_amount > 0, ErrorCode::InvalidAmount);
+
+    // Convert stablecoin amount to USD cents
+    // USDT/USDC have 6 decimals, so 1_000_000 = $1.00 = 100 cents
+    let usd_cents = stablecoin_amount
+        .checked_mul(100)
+        .ok_or(ErrorCode::Overflow)?
+        .checked_div(1_000_000)
+        .ok_or(ErrorCode::Overflow)?;
+
+    msg!("{} amount: {}, USD value: {} cents", coin_name, stablecoin_amount, usd_cents);
+
+    // Validate purchase limits (use config values if set, otherwise use constants)
+    let min_purchase = if config.min_purchase_usd > 0 { config.min_purchase_usd } else { PRESALE_MIN_PURCHASE_USD };
+    let max_purchase = if config.max_per_user_usd > 0 { config.max_per_user_usd } else { PRESALE_MAX_PURCHASE_USD };
+    
+    // Check minimum per transaction
+    require!(usd_cents >= min_purchase, ErrorCode::BelowMinimumPurchase);
+    
+    // Check maximum per transaction ($10,000)
+    require!(usd_cents <= max_purchase, ErrorCode::ExceedsMaximumPurchase);
+    
+    let new_total_spent = user_allocation.total_spent_cents
+        .checked_add(usd_cents)
+        .ok_or(ErrorCode::Overflow)?;
+    
+    // Check maximum total per user ($25,600)
+    require!(
+        new_total_spent <= PRESALE_MAX_TOTAL_PER_USER_USD,
+        ErrorCode::ExceedsMaximumPurchase
+    );
+
+    // Calculate tokens based on current stage price
+    let tokens_to_allocate = calculate_tokens_for_usd(usd_cents, config.current_stage)?;
+
+    // Transfer stablecoin from user to admin
+    let cpi_ctx = CpiContext::new(
+        token_program.to_account_info(),
+        Transfer {
+            from: stablecoin_ata_for_user.to_account_info(),
+            to: stablecoin_ata_for_admin.to_account_info(),
+            authority: user.to_account_info(),
+        },
+    );
+    token::transfer(cpi_ctx, stablecoin_amount)?;
+
+    // Process referral bonus (volume-tiered) - FROM COMMUNITY REWARDS POOL (not presale)
+    // Paid on every purchase by a referred buyer
+    let referrer_volume = referrer_stats
+        .as_ref()
+        .map_or(0, |stats| stats.referred_usd_cents);
+    let referred_purchase = ReferredPurchase {
+        referrer: user_allocation.referrer,
+        referee: user.key(),
+        tokens: tokens_to_allocate,
+        usd_cents,
+        referrer_volume_usd_cents: referrer_volume,
+        timestamp: clock.unix_timestamp,
+    };
+    let referral_bonus = pay_referrer_bonus(
+        config,
+        &referred_purchase,
+        referrer_allocation,
+        true,
+        pending_referral_rewards,
+        &mut None,
+        None,
+    )?;
+
+    // Referee bonus for the buyer (first purchase only) - FROM COMMUNITY REWARDS POOL
+    let referee_bonus = apply_referee_bonus(config, user_allocation, tokens_to_allocate)?;
+
+    // Update referrer ledger (counts, referred volume, bonus) on every referred purchase
+    if user_allocation.referrer != Pubkey::default() {
+        record_referral_event(
+            referrer_stats,
+            referral_record,
+            referrer_user_account,
+            &referred_purchase,
+            referral_bonus,
+            referee_bonus,
+        )?;
+    }
+
+    // Update user allocation
+    user_allocation.user = user.key();
+    user_allocation.total_tokens = user_allocation
+        .total_tokens
+        .checked_add(tokens_to_allocate)
+        .ok_or(ErrorCode::Overflow)?;
+    credit_vault_bucket(config, VaultBucket::PresaleUnclaimed, tokens_to_allocate)?;
+    user_allocation.total_spent_cents = new_total_spent;
+    user_allocation.purchase_count = user_allocation.purchase_count.checked_add(1).ok_or(ErrorCode::Overflow)?;
+    
+    if user_allocation.purchase_count == 1 {
+        user_allocation.first_purchase_at = clock.unix_timestamp;
+    }
+    user_allocation.last_purchase_at = clock.unix_timestamp;
+
+    // Update stage progress
+    config.stage_tokens_sold = config
+        .stage_tokens_sold
+        .checked_add(tokens_to_allocate)
+        .ok_or(ErrorCode::Overflow)?;
+    config.tokens_sold = config
+        .tokens_sold
+        .checked_add(tokens_to_allocate)
+        .ok_or(ErrorCode::Overflow)?;
+    config.total_usd_raised_cents = config
+        .total_usd_raised_cents
+        .checked_add(usd_cents)
+        .ok_or(ErrorCode::Overflow)?;
+
+    // Advance stage if current stage is full
+    advance_stage_if_full(config, clock.unix_timestamp)?;
+
+    // Close presale if hard cap reached
+    if config.tokens_sold >= PRESALE_TOTAL_ALLOCATION * TOKEN_DECIMALS {
+        config.presale_active = false;
+        msg!("Presale hard cap reached - presale closed");
+    }
+
+    msg!(
+        "{} Allocation recorded: {} tokens to user, {} referral bonus, {} referee bonus",
+        coin_name,
+        tokens_to_allocate,
+        referral_bonus,
+        referee_bonus
+    );
+
+    Ok(())
+}
+
+/// Internal function for stablecoin (USDT/USDC) purchase with vesting stake
+/// Combines stablecoin purchase with automatic vesting stake creation/update
+fn presale_purchase_stablecoin_and_vest_stake_internal<'info>(
+    config: &mut Account<'info, Config>,
+    user_account: &mut Account<'info, UserAccount>,
+    user_allocation: &mut Account<'info, PresaleAllocation>,
+    stake_account: &mut Account<'info, StakeAccount>,
+    referrer_allocation: &AccountInfo<'info>,
+    referrer_stats: &mut Option<Account<'info, ReferrerStats>>,
+    referral_record: &mut Option<Account<'info, ReferralRecord>>,
+    referrer_user_account: &mut Option<Account<'info, UserAccount>>,
+    pending_referral_rewards: &mut Option<Account<'info, PendingReferralRewards>>,
+    referrer_vesting_stake: &mut Option<Account<'info, StakeAccount>>,
+    stablecoin_ata_for_user: &Account<'info, TokenAccount>,
+    stablecoin_ata_for_admin: &Account<'info, TokenAccount>,
+    user: &Signer<'info>,
+    token_program: &Program<'info, Token>,
+    stablecoin_amount: u64,
+    tier: StakeTier,
+    auto_compound: bool,
+    coin_name: &str,
+) -> Result<()> {
+    let clock = Clock::get()?;
+
+    // Validate presale is active
+    require!(config.presale_active, ErrorCode::PresaleNotStarted);
+    require!(
+        clock.unix_timestamp >= config.presale_start_time,
+        ErrorCode::PresaleNotStarted
+    );
+    require!(
+        config.tokens_sold < PRESALE_TOTAL_ALLOCATION * TOKEN_DECIMALS,
+        ErrorCode::PresaleHardCapReached
+    );
+
+    // Validate user not blocked
+    require!(!user_account.is_blocked, ErrorCode::AddressBlocked);
+    require!(stablecoin_amount > 0, ErrorCode::InvalidAmount);
+
+    // Convert stablecoin amount to USD cents
+    // USDT/USDC have 6 decimals, so 1_000_000 = $1.00 = 100 cents
+    let usd_cents = stablecoin_amount
+        .checked_mul(100)
+        .ok_or(ErrorCode::Overflow)?
+        .checked_div(1_000_000)
+        .ok_or(ErrorCode::Overflow)?;
+
+    msg!("{} Vesting Stake: {} amount: {}, USD value: {} cents", coin_name, coin_name, stablecoin_amount, usd_cents);
+
+    // Validate purchase limits (use config values if set, otherwise use constants)
+    let min_purchase = if config.min_purchase_usd > 0 { config.min_purchase_usd } else { PRESALE_MIN_PURCHASE_USD };
+    let max_purchase = if config.max_per_user_usd > 0 { config.max_per_user_usd } else { PRESALE_MAX_PURCHASE_USD };
+    
+    // Check minimum per transaction
+    require!(usd_cents >= min_purchase, ErrorCode::BelowMinimumPurchase);
+    
+    // Check maximum per transaction ($10,000)
+    require!(usd_cents <= max_purchase, ErrorCode::ExceedsMaximumPurchase);
+    
+    let new_total_spent = user_allocation.total_spent_cents
+        .checked_add(usd_cents)
+        .ok_or(ErrorCode::Overflow)?;
+    
+    // Check maximum total per user ($25,600)
+    require!(
+        new_total_spent <= PRESALE_MAX_TOTAL_PER_USER_USD,
+        ErrorCode::ExceedsMaximumPurchase
+    );
+
+    // Calculate tokens based on current stage price
+    let tokens_to_stake = calculate_tokens_for_usd(usd_cents, config.current_stage)?;
+
+    // Process referral bonuses exactly like the allocation-only paths
+    // Referrers that opted in receive their bonus as additional vesting stake
+    let referrer_volume = referrer_stats
+        .as_ref()
+        .map_or(0, |stats| stats.referred_usd_cents);
+    let stake_tier_preference = referral_stake_preference(referrer_stats);
+    let referred_purchase = ReferredPurchase {
+        referrer: user_allocation.referrer,
+        referee: user.key(),
+        tokens: tokens_to_stake,
+        usd_cents,
+        referrer_volume_usd_cents: referrer_volume,
+        timestamp: clock.unix_timestamp,
+    };
+    let referral_bonus = pay_referrer_bonus(
+        config,
+        &referred_purchase,
+        referrer_allocation,
+        true,
+        pending_referral_rewards,
+        referrer_vesting_stake,
+        stake_tier_preference,
+    )?;
+    let referee_bonus = apply_referee_bonus(config, user_allocation, tokens_to_stake)?;
+
+    if user_allocation.referrer != Pubkey::default() {
+        record_referral_event(
+            referrer_stats,
+            referral_record,
+            referrer_user_account,
+            &referred_purchase,
+            referral_bonus,
+            referee_bonus,
+        )?;
+    }
+
+    // Check global staking cap
+    require!(
+        config.total_staked.checked_add(tokens_to_stake).ok_or(ErrorCode::Overflow)? <= MAX_TOTAL_STAKED,
+        ErrorCode::StakingCapReached
+    );
+
+    // Validate tier-specific constraints
+    let lock_period_days = match tier {
+        StakeTier::TierA => {
+            require!(
+                config.total_staked_tier_a.checked_add(tokens_to_stake).ok_or(ErrorCode::Overflow)? <= MAX_STAKE_TIER_A,
+                ErrorCode::TierAFull
+            );
+            LOCK_PERIOD_TIER_A
+        }
+        StakeTier::TierB => LOCK_PERIOD_TIER_B,
+        StakeTier::TierC => LOCK_PERIOD_TIER_C,
+    };
+
+    // Transfer stablecoin from user to admin
+    let cpi_ctx = CpiContext::new(
+        token_program.to_account_info(),
+        Transfer {
+            from: stablecoin_ata_for_user.to_account_info(),
+            to: stablecoin_ata_for_admin.to_account_info(),
+            authority: user.to_account_info(),
+        },
+    );
+    token::transfer(cpi_ctx, stablecoin_amount)?;
+
+    // Update user allocation (record keeping)
+    user_allocation.user = user.key();
+    user_allocation.total_tokens = user_allocation
+        .total_tokens
+        .checked_add(tokens_to_stake)
+        .ok_or(ErrorCode::Overflow)?;
+    user_allocation.vest_staked_tokens = user_allocation
+        .vest_staked_tokens
+        .checked_add(tokens_to_stake)
+        .ok_or(ErrorCode::Overflow)?;
+    user_allocation.total_spent_cents = new_total_spent;
+    user_allocation.purchase_count = user_allocation.purchase_count.checked_add(1).ok_or(ErrorCode::Overflow)?;
+    
+    if user_allocation.purchase_count == 1 {
+        user_allocation.first_purchase_at = clock.unix_timestamp;
+    }
+    user_allocation.last_purchase_at = clock.unix_timestamp;
+
+    // Create or update vesting stake (PDA per user per tier)
+    let is_new_stake = stake_account.owner == Pubkey::default();
+    
+    if is_new_stake {
+        // New stake account - initialize
+        let stake_id = config.next_stake_id;
+        stake_account.stake_id = stake_id;
+        stake_account.owner = user.key();
+        stake_account.amount = tokens_to_stake;
+        stake_account.start_time = clock.unix_timestamp;
+        stake_account.lock_period_days = lock_period_days;
+        stake_account.last_reward_calculation = clock.unix_timestamp;
+        stake_account.pending_rewards = 0;
+        stake_account.active = true;
+        stake_account.tier = tier;
+        stake_account.auto_compound = auto_compound;
+        stake_account.cooldown_start = 0;
+        stake_account.is_vesting = true;
+        stake_account.total_added = tokens_to_stake;
+        
+        config.next_stake_id = stake_id.checked_add(1).ok_or(ErrorCode::Overflow)?;
+        
+        msg!("{}: Created new vesting stake: {} tokens in {:?} tier", coin_name, tokens_to_stake, tier);
+    } else {
+        // Existing stake - calculate pending rewards first, then add new tokens
+        checkpoint_stake_rewards(config, stake_account, clock.unix_timestamp)?;
+        
+        // Add new tokens to existing stake
+        stake_account.amount = stake_account.amount
+            .checked_add(tokens_to_stake)
+            .ok_or(ErrorCode::Overflow)?;
+        stake_account.total_added = stake_account.total_added
+            .checked_add(tokens_to_stake)
+            .ok_or(ErrorCode::Overflow)?;
+        stake_account.last_reward_calculation = clock.unix_timestamp;
+        stake_account.auto_compound = auto_compound; // Update auto_compound preference
+        
+        msg!("{}: Added {} tokens to existing {:?} stake (total: {})", coin_name, tokens_to_stake, tier, stake_account.amount);
+    }
+
+    // Update staking config
+    config.total_staked = config
+        .total_staked
+        .checked_add(tokens_to_stake)
+        .ok_or(ErrorCode::Overflow)?;
+    
+    if tier == StakeTier::TierA {
+        config.total_staked_tier_a = config
+            .total_staked_tier_a
+            .checked_add(tokens_to_stake)
+            .ok_or(ErrorCode::Overflow)?;
+    }
+    add_pool_stake(config, stake_account, tokens_to_stake, clock.unix_timestamp)?;
+
+    // Update stage progress
+    config.stage_tokens_sold = config
+        .stage_tokens_sold
+        .checked_add(tokens_to_stake)
+        .ok_or(ErrorCode::Overflow)?;
+    config.tokens_sold = config
+        .tokens_sold
+        .checked_add(tokens_to_stake)
+        .ok_or(ErrorCode::Overflow)?;
+    config.total_usd_raised_cents = config
+        .total_usd_raised_cents
+        .checked_add(usd_cents)
+        .ok_or(ErrorCode::Overflow)?;
+
+    // Advance stage if current stage is full
+    advance_stage_if_full(config, clock.unix_timestamp)?;
+
+    // Close presale if hard cap reached
+    if config.tokens_sold >= PRESALE_TOTAL_ALLOCATION * TOKEN_DECIMALS {
+        config.presale_active = false;
+        msg!("Presale hard cap reached - presale closed");
+    }
+
+    msg!(
+        "{} Vesting Stake complete: {} tokens staked to {:?} tier",
+        coin_name,
+        tokens_to_stake,
+        tier
+    );
+
+    Ok(())
+}
+
+/// A purchase by a referred buyer, as seen by the referral ledger
+#[derive(Clone, Copy)]
+struct ReferredPurchase {
+    referrer: Pubkey,
+    referee: Pubkey,
+    tokens: u64,
+    usd_cents: u64,
+    referrer_volume_usd_cents: u64, // referrer's referred volume before this purchase (selects the tier)
+    timestamp: i64,
+}
+
+/// Record a referral event on the referrer's ledger
+/// The referral record is keyed per (referrer, referee) so each referee is only counted once
+fn record_referral_event<'info>(
+    referrer_stats: &mut Option<Account<'info, ReferrerStats>>,
+    referral_record: &mut Option<Account<'info, ReferralRecord>>,
+    referrer_user_account: &mut Option<Account<'info, UserAccount>>,
+    purchase: &ReferredPurchase,
+    bonus: u64,
+    referee_bonus: u64,
+) -> Result<()> {
+    let ReferredPurchase { referrer, referee, tokens, usd_cents, timestamp, .. } = *purchase;
+
+    let (Some(stats), Some(record)) = (referrer_stats.as_mut(), referral_record.as_mut()) else {
+        return Err(ErrorCode::ReferralAccountsRequired.into());
+    };
+
+    // First purchase by this referee - initialize the per-referee record
+    let is_new_referee = record.referee == Pubkey::default();
+    if is_new_referee {
+        record.referrer = referrer;
+        record.referee = referee;
+        record.first_purchase_at = timestamp;
+    }
+    record.purchase_count = record.purchase_count.checked_add(1).ok_or(ErrorCode::Overflow)?;
+    record.usd_cents = record.usd_cents.checked_add(usd_cents).ok_or(ErrorCode::Overflow)?;
+    record.tokens = record.tokens.checked_add(tokens).ok_or(ErrorCode::Overflow)?;
+    record.bonus_tokens = record.bonus_tokens.checked_add(bonus).ok_or(ErrorCode::Overflow)?;
+    record.referee_bonus_tokens = record
+        .referee_bonus_tokens
+        .checked_add(referee_bonus)
+        .ok_or(ErrorCode::Overflow)?;
+    record.last_purchase_at = timestamp;
+
+    // Referrer totals
+    if stats.referrer == Pubkey::default() {
+        stats.referrer = referrer;
+    }
+    if stats.first_referral_at == 0 {
+        stats.first_referral_at = timestamp;
+    }
+    if is_new_referee {
+        stats.total_referrals = stats.total_referrals.checked_add(1).ok_or(ErrorCode::Overflow)?;
+    }
+    stats.referred_usd_cents = stats
+        .referred_usd_cents
+        .checked_add(usd_cents)
+        .ok_or(ErrorCode::Overflow)?;
+    stats.referred_tokens = stats
+        .referred_tokens
+        .checked_add(tokens)
+        .ok_or(ErrorCode::Overflow)?;
+    stats.bonus_tokens = stats.bonus_tokens.checked_add(bonus).ok_or(ErrorCode::Overflow)?;
+    stats.last_referral_at = timestamp;
+
+    // Mirror counters on the referrer's user account (if the referrer has one)
+    if let Some(referrer_user) = referrer_user_account.as_mut() {
+        if is_new_referee {
+            referrer_user.total_referrals = referrer_user
+                .total_referrals
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+        referrer_user.referral_rewards = referrer_user
+            .referral_rewards
+            .checked_add(bonus)
+            .ok_or(ErrorCode::Overflow)?;
+    }
+
+    msg!(
+        "Referral recorded: referrer={}, referee={}, usd_cents={}, bonus={}, total_referrals={}",
+        referrer,
+        referee,
+        usd_cents,
+        bonus,
+        stats.total_referrals
+    );
+
+    Ok(())
+}
+
+/// Referrer bonus percentage for a referrer's cumulative referred USD volume
+/// Uses the highest tier reached; falls back to the flat config rate if no tiers are set
+fn referral_percentage_for_volume(config: &Config, referred_usd_cents: u64) -> u64 {
+    let tier_count = (config.referral_tier_count as usize).min(MAX_REFERRAL_TIERS);
+    let percentage = if tier_count == 0 {
+        config.referral_reward_percentage
+    } else {
+        // Tiers are sorted by ascending threshold
+        config.referral_tiers[..tier_count]
+            .iter()
+            .rev()
+            .find(|tier| referred_usd_cents >= tier.min_referred_usd_cents)
+            .map_or(0, |tier| tier.percentage)
+    };
+
+    percentage.min(MAX_REFERRAL_PERCENTAGE)
+}
+
+/// Pay the referrer bonus for a referred purchase - FROM COMMUNITY REWARDS POOL (not presale)
+/// Added to the referrer's vesting stake if they opted in and it is provided,
+/// otherwise credited to their allocation, or escrowed if they have no (unclaimed) allocation
+/// An invalid referrer allocation PDA fails the purchase, or skips the bonus if `skip_invalid_referrer_pda`
+/// (stablecoin paths)
+fn pay_referrer_bonus<'info>(
+    config: &mut Config,
+    purchase: &ReferredPurchase,
+    referrer_allocation: &AccountInfo<'info>,
+    skip_invalid_referrer_pda: bool,
+    pending_referral_rewards: &mut Option<Account<'info, PendingReferralRewards>>,
+    referrer_vesting_stake: &mut Option<Account<'info, StakeAccount>>,
+    stake_tier_preference: Option<StakeTier>,
+) -> Result<u64> {
+    let ReferredPurchase { referrer, tokens: tokens_purchased, timestamp, .. } = *purchase;
+
+    if referrer == Pubkey::default() {
+        return Ok(0);
+    }
+
+    // SECURITY: Validate referrer_allocation PDA matches expected seeds
+    if !referrer_allocation.data_is_empty() {
+        let (expected_pda, _bump) = Pubkey::find_program_address(
+            &[b"allocation", referrer.as_ref()],
+            &crate::ID
+        );
+        if referrer_allocation.key() != expected_pda {
+            require!(skip_invalid_referrer_pda, ErrorCode::InvalidPDA);
+            msg!("Invalid referrer PDA - skipping bonus");
+            return Ok(0);
+        }
+    }
+
+    let percentage = referral_percentage_for_volume(config, purchase.referrer_volume_usd_cents);
+    let bonus = tokens_purchased
+        .checked_mul(percentage)
+        .ok_or(ErrorCode::Overflow)?
+        .checked_div(100)
+        .ok_or(ErrorCode::Overflow)?;
+    if bonus == 0 {
+        return Ok(0);
+    }
+
+    // Check if Community Rewards pool has enough tokens
+    let new_total_referral = config.total_referral_bonuses
+        .checked_add(bonus)
+        .ok_or(ErrorCode::Overflow)?;
+
+    if new_total_referral > COMMUNITY_REWARDS_ALLOCATION * TOKEN_DECIMALS {
+        // Pool exhausted - no bonus but purchase continues
+        msg!("Referral pool exhausted - no bonus awarded");
+        return Ok(0);
+    }
+
+    // Referrer opted in to receive bonuses as additional vesting stake
+    let mut staked = false;
+    if let (Some(tier), Some(stake_account)) = (stake_tier_preference, referrer_vesting_stake.as_mut()) {
+        staked = credit_referral_bonus_stake(
+            config,
+            stake_account,
+            referrer,
+            tier,
+            bonus,
+            timestamp,
+        )?;
+    }
+
+    // Credit the referrer's allocation if it exists and is not yet claimed
+    let mut credited = staked;
+    if !credited && !referrer_allocation.data_is_empty() {
+        let mut referrer_data = referrer_allocation.try_borrow_mut_data()?;
+        let mut referrer_alloc = PresaleAllocation::try_deserialize(&mut &referrer_data[..])?;
+
+        if !referrer_alloc.claimed {
+            referrer_alloc.referral_bonus_tokens = referrer_alloc
+                .referral_bonus_tokens
+                .checked_add(bonus)
+                .ok_or(ErrorCode::Overflow)?;
+            referrer_alloc.total_tokens = referrer_alloc
+                .total_tokens
+                .checked_add(bonus)
+                .ok_or(ErrorCode::Overflow)?;
+
+            referrer_alloc.try_serialize(&mut &mut referrer_data[..])?;
+            credit_vault_bucket(config, VaultBucket::PresaleUnclaimed, bonus)?;
+            credited = true;
+        }
+    }
+
+    // Otherwise escrow the bonus until the referrer sweeps or claims it
+    if !credited {
+        let pending = pending_referral_rewards
+            .as_mut()
+            .ok_or(ErrorCode::PendingReferralAccountRequired)?;
+        escrow_referral_bonus(pending, referrer, bonus, timestamp)?;
+        credit_vault_bucket(config, VaultBucket::CommunityReserved, bonus)?;
+    }
+
+    // Track total referral bonuses issued (from Community Rewards pool)
+    config.total_referral_bonuses = new_total_referral;
+
+    msg!(
+        "Referral bonus {}: {} tokens ({}% tier)",
+        if staked { "staked" } else if credited { "awarded" } else { "escrowed" },
+        bonus,
+        percentage
+    );
+    Ok(bonus)
+}
+
+/// Credit the referee bonus to a referred buyer's allocation (first purchase only)
+/// Sized independently of the referrer's share, paid from the Community Rewards pool
+/// Tracked in ReferralRecord::referee_bonus_tokens - referral_bonus_tokens is for bonuses earned as a referrer
+fn apply_referee_bonus(
+    config: &mut Config,
+    user_allocation: &mut PresaleAllocation,
+    tokens_purchased: u64,
+) -> Result<u64> {
+    if user_allocation.referrer == Pubkey::default()
+        || user_allocation.purchase_count > 0
+        || config.referee_bonus_percentage == 0
+    {
+        return Ok(0);
+    }
+
+    let bonus = tokens_purchased
+        .checked_mul(config.referee_bonus_percentage)
+        .ok_or(ErrorCode::Overflow)?
+        .checked_div(100)
+        .ok_or(ErrorCode::Overflow)?;
+
+    // Same pool limit as the referrer bonus
+    let new_total_referral = config.total_referral_bonuses
+        .checked_add(bonus)
+        .ok_or(ErrorCode::Overflow)?;
+
+    if new_total_referral > COMMUNITY_REWARDS_ALLOCATION * TOKEN_DECIMALS {
+        msg!("Referral pool exhausted - no referee bonus awarded");
+        return Ok(0);
+    }
+
+    user_allocation.total_tokens = user_allocation
+        .total_tokens
+        .checked_add(bonus)
+        .ok_or(ErrorCode::Overflow)?;
+    credit_vault_bucket(config, VaultBucket::PresaleUnclaimed, bonus)?;
+    config.total_referral_bonuses = new_total_referral;
+
+    msg!("Referee bonus awarded: {} tokens", bonus);
+    Ok(bonus)
+}
+
+/// Park a referral bonus in the referrer's pending rewards escrow
+/// Used when the referrer has no PresaleAllocation account yet
+fn escrow_referral_bonus(
+    pending: &mut PendingReferralRewards,
+    referrer: Pubkey,
+    bonus: u64,
+    timestamp: i64,
+) -> Result<()> {
+    if pending.referrer == Pubkey::default() {
+        pending.referrer = referrer;
+    }
+    pending.pending_tokens = pending
+        .pending_tokens
+        .checked_add(bonus)
+        .ok_or(ErrorCode::Overflow)?;
+    pending.total_escrowed = pending
+        .total_escrowed
+        .checked_add(bonus)
+        .ok_or(ErrorCode::Overflow)?;
+    pending.last_escrowed_at = timestamp;
+    Ok(())
+}
+
+/// Tier of the vesting stake a referrer wants bonuses added to (None = credit allocation)
+fn referral_stake_preference(referrer_stats: &Option<Account<ReferrerStats>>) -> Option<StakeTier> {
+    referrer_stats
+        .as_ref()
+        .filter(|stats| stats.bonus_as_stake)
+        .map(|stats| stats.bonus_stake_tier)
+}
+
+/// Rewards the reward vault can pay now - bounded by the funded budget and the vault balance
+fn payable_rewards(config: &Config, reward_vault: &TokenAccount, rewards: u64) -> u64 {
+    rewards.min(config.reward_budget).min(reward_vault.amount)
+}
+
+/// Book a payout of `paid` out of `owed` (pending + newly accrued rewards) for a stake
+/// `penalty_share` is the part of the new accruals already booked as liability at early unstake
+/// The unpaid remainder stays in pending_rewards and in the aggregate reward liability
+fn settle_reward_liability(
+    config: &mut Config,
+    stake_account: &mut StakeAccount,
+    owed: u64,
+    penalty_share: u64,
+    paid: u64,
+) -> Result<()> {
+    let remainder = owed.checked_sub(paid).ok_or(ErrorCode::Overflow)?;
+    // Stakes accrued before liability tracking may not be fully counted - saturate
+    config.total_reward_liability = config
+        .total_reward_liability
+        .saturating_sub(stake_account.pending_rewards)
+        .saturating_sub(penalty_share)
+        .checked_add(remainder)
+        .ok_or(ErrorCode::Overflow)?;
+    config.reward_budget = config
+        .reward_budget
+        .checked_sub(paid)
+        .ok_or(ErrorCode::RewardVaultInsufficient)?;
+    stake_account.pending_rewards = remainder;
+    Ok(())
+}
+
+/// Transfer tokens out of the reward vault (self-authority PDA)
+fn transfer_from_reward_vault<'info>(
+    reward_vault: &Account<'info, TokenAccount>,
+    destination: AccountInfo<'info>,
+    token_program: &Program<'info, Token>,
+    ico_mint_key: Pubkey,
+    bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let seeds = &[b"reward_vault".as_ref(), ico_mint_key.as_ref(), &[bump]];
+    let signer = &[&seeds[..]];
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        Transfer {
+            from: reward_vault.to_account_info(),
+            to: destination,
+            authority: reward_vault.to_account_info(),
+        },
+        signer,
+    );
+    token::transfer(cpi_ctx, amount)
+}
+
+/// Start the 48-hour unstake cooldown for `amount` once the lock period (and TGE for vesting stakes) has passed
+/// A partial amount must leave at least the minimum stake behind; the cooldown portion stops
+/// earning (APY and pool share) from now on and is paid out as principal only
+fn begin_unstake_cooldown(
+    config: &mut Config,
+    stake_account: &mut StakeAccount,
+    amount: u64,
+    timestamp: i64,
+) -> Result<()> {
+    require!(stake_account.cooldown_start == 0, ErrorCode::StillInCooldown);
+    require!(
+        amount > 0 && amount <= stake_account.amount,
+        ErrorCode::InvalidAmount
+    );
+    let remainder = stake_account.amount - amount;
+    require!(
+        remainder == 0 || remainder >= config.min_stake_amount,
+        ErrorCode::InvalidAmount
+    );
+
+    // VESTING CHECK: If this is a vesting stake, check if TGE has passed
+    if stake_account.is_vesting {
+        require!(
+            timestamp >= config.tge_timestamp,
+            ErrorCode::VestingStakeLocked
+        );
+        msg!("Vesting stake - TGE has passed, unstake allowed");
+    }
+
+    // Check if lock period has passed
+    let unlock_time = stake_account
+        .start_time
+        .checked_add(
+            (stake_account.lock_period_days as i64)
+                .checked_mul(SECONDS_PER_DAY)
+                .ok_or(ErrorCode::Overflow)?,
+        )
+        .ok_or(ErrorCode::Overflow)?;
+
+    require!(
+        timestamp >= unlock_time,
+        ErrorCode::StillInLockPeriod
+    );
+
+    // Book rewards on the full principal, then take the cooldown portion out of reward accrual
+    checkpoint_stake_rewards(config, stake_account, timestamp)?;
+    stake_account.cooldown_start = timestamp;
+    stake_account.unstake_amount = amount;
+    stake_account.unstake_partial = remainder > 0;
+    remove_pool_stake(config, stake_account, amount, timestamp)?;
+    reset_reward_debt(config, stake_account)?;
+
+    msg!(
+        "Unstake of {} tokens initiated for stake {}. Can finalize after 48 hours.",
+        amount,
+        stake_account.stake_id
+    );
+
+    Ok(())
+}
+
+/// Deactivate a stake and book its final rewards: returns (principal, rewards payable now, rewards owed)
+/// Unpaid rewards (all of them without a reward vault) stay pending for claim_unpaid_rewards;
+/// token transfers are left to the caller
+fn deactivate_stake(
+    config: &mut Config,
+    stake_account: &mut StakeAccount,
+    reward_vault: Option<&Account<'_, TokenAccount>>,
+    timestamp: i64,
+) -> Result<(u64, u64, u64)> {
+    // Calculate final rewards
+    update_reward_pool(config, timestamp)?;
+    let rewards = calculate_rewards_internal(
+        config,
+        stake_account,
+        timestamp,
+    )?;
+
+    let stake_amount = stake_account.amount;
+    let earning_amount = stake_account.earning_amount();
+    let tier = stake_account.tier;
+    let paid = reward_vault.map_or(0, |vault| payable_rewards(config, vault, rewards));
+    let penalty_share = unbooked_penalty_share(config, stake_account)?;
+
+    // Deactivate stake - unpaid rewards stay pending for claim_unpaid_rewards
+    stake_account.active = false;
+    settle_reward_liability(config, stake_account, rewards, penalty_share, paid)?;
+
+    // Update config
+    config.total_staked = config
+        .total_staked
+        .checked_sub(stake_amount)
+        .ok_or(ErrorCode::Overflow)?;
+
+    if tier == StakeTier::TierA {
+        config.total_staked_tier_a = config
+            .total_staked_tier_a
+            .checked_sub(stake_amount)
+            .ok_or(ErrorCode::Overflow)?;
+    }
+    // A cooldown portion already left pool weighting when the cooldown started
+    remove_pool_stake(config, stake_account, earning_amount, timestamp)?;
+    stake_account.pool_counted = false;
+
+    if paid > 0 {
+        config.total_rewards_distributed = config
+            .total_rewards_distributed
+            .checked_add(paid)
+            .ok_or(ErrorCode::Overflow)?;
+    }
+
+    Ok((stake_amount, paid, rewards))
+}
+
+/// Withdraw the cooldown portion of a stake, leaving the rest staked under the same tier and start time
+/// The portion stopped earning when the cooldown started; returns the withdrawn principal
+fn withdraw_stake_portion(
+    config: &mut Config,
+    stake_account: &mut StakeAccount,
+    timestamp: i64,
+) -> Result<u64> {
+    let portion = stake_account.unstake_amount;
+    checkpoint_stake_rewards(config, stake_account, timestamp)?;
+
+    stake_account.amount = stake_account
+        .amount
+        .checked_sub(portion)
+        .ok_or(ErrorCode::Overflow)?;
+    stake_account.cooldown_start = 0;
+    stake_account.unstake_amount = 0;
+    stake_account.unstake_partial = false;
+
+    config.total_staked = config
+        .total_staked
+        .checked_sub(portion)
+        .ok_or(ErrorCode::Overflow)?;
+    if stake_account.tier == StakeTier::TierA {
+        config.total_staked_tier_a = config
+            .total_staked_tier_a
+            .checked_sub(portion)
+            .ok_or(ErrorCode::Overflow)?;
+    }
+    reset_reward_debt(config, stake_account)?;
+
+    msg!(
+        "Partial unstake of stake {}: {} tokens withdrawn, {} still staked",
+        stake_account.stake_id,
+        portion,
+        stake_account.amount
+    );
+    Ok(portion)
+}
+
+/// Early unstake penalty in bps: the configured maximum, declining linearly to 0 at unlock
+fn early_unstake_penalty_bps(config: &Config, stake_account: &StakeAccount, timestamp: i64) -> Result<u64> {
+    let lock_seconds = (stake_account.lock_period_days as i64)
+        .checked_mul(SECONDS_PER_DAY)
+        .ok_or(ErrorCode::Overflow)?;
+    let unlock_time = stake_account
+        .start_time
+        .checked_add(lock_seconds)
+        .ok_or(ErrorCode::Overflow)?;
+    require!(timestamp < unlock_time, ErrorCode::LockPeriodEnded);
+
+    let remaining = unlock_time.checked_sub(timestamp).ok_or(ErrorCode::Overflow)?.min(lock_seconds);
+    let bps = (config.early_unstake_max_penalty_bps as u128)
+        .checked_mul(remaining as u128)
+        .ok_or(ErrorCode::Overflow)?
+        .checked_div(lock_seconds as u128)
+        .ok_or(ErrorCode::Overflow)?;
+    Ok(bps as u64)
+}
+
+/// Pay out principal + final rewards of a stake whose cooldown has ended and deactivate it
+/// Principal comes from the ICO vault, rewards from the reward vault (shortfall stays pending)
+/// The reward vault (with its bump) is optional so principal can always be withdrawn
+/// Callers pin the config to the sale mint, so config.sale_token seeds the ICO vault
+fn settle_unstake<'info>(
+    config: &mut Account<'info, Config>,
+    stake_account: &mut Account<'info, StakeAccount>,
+    ico_ata_for_ico_program: &Account<'info, TokenAccount>,
+    reward_vault: Option<(&Account<'info, TokenAccount>, u8)>,
+    destination: &Account<'info, TokenAccount>,
+    token_program: &Program<'info, Token>,
+    timestamp: i64,
+) -> Result<()> {
+    // Validate cooldown period has passed
+    require!(stake_account.cooldown_start > 0, ErrorCode::InvalidAmount);
+    let cooldown_end = stake_account
+        .cooldown_start
+        .checked_add(COOLDOWN_PERIOD)
+        .ok_or(ErrorCode::Overflow)?;
+    require!(
+        timestamp >= cooldown_end,
+        ErrorCode::StillInCooldown
+    );
+
+    // A partial unstake keeps the stake active; decided when the cooldown started, so a stake that
+    // grew during cooldown (top-up, compounding) still closes fully on a full unstake
+    let partial = stake_account.unstake_partial;
+    let (stake_amount, paid, rewards) = if partial {
+        (withdraw_stake_portion(config, stake_account, timestamp)?, 0, 0)
+    } else {
+        deactivate_stake(config, stake_account, reward_vault.map(|(vault, _)| vault), timestamp)?
+    };
+
+    // Transfer principal to the stake owner
+    let ico_mint_key = config.sale_token;
+    let (expected_pda, bump) = Pubkey::find_program_address(
+        &[ico_mint_key.as_ref()],
+        &crate::ID,
+    );
+    
+    require!(
+        ico_ata_for_ico_program.key() == expected_pda,
+        ErrorCode::InvalidPDA
+    );
+    
+    let seeds = &[ico_mint_key.as_ref(), &[bump]];
+    let signer = &[&seeds[..]];
+
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        Transfer {
+            from: ico_ata_for_ico_program.to_account_info(),
+            to: destination.to_account_info(),
+            authority: ico_ata_for_ico_program.to_account_info(),
+        },
+        signer,
+    );
+    token::transfer(cpi_ctx, stake_amount)?;
+
+    if let Some((vault, vault_bump)) = reward_vault.filter(|_| paid > 0) {
+        transfer_from_reward_vault(
+            vault,
+            destination.to_account_info(),
+            token_program,
+            ico_mint_key,
+            vault_bump,
+            paid,
+        )?;
+    }
+
+    msg!(
+        "Unstaked {} tokens with {} rewards ({} left pending)",
+        stake_amount,
+        paid,
+        rewards - paid
+    );
+
+    Ok(())
+}
+
+/// Book rewards accrued on an existing stake into pending_rewards before its principal changes
+/// Unlike before the reward vault, top-ups no longer compound these rewards into principal even
+/// with auto_compound on (that would stake tokens never moved out of the reward vault); they
+/// compound when harvested instead
+fn checkpoint_stake_rewards(
+    config: &mut Config,
+    stake_account: &mut StakeAccount,
+    current_time: i64,
+) -> Result<()> {
+    update_reward_pool(config, current_time)?;
+    let rewards = accrued_stake_rewards(config, stake_account, current_time)?;
+    let penalty_share = unbooked_penalty_share(config, stake_account)?;
+
+    // Rewards stay pending (a reward vault liability) - auto-compound applies on harvest
+    // Penalty shares were booked as liability when the early unstake distributed them
+    stake_account.pending_rewards = stake_account.pending_rewards
+        .checked_add(rewards)
+        .ok_or(ErrorCode::Overflow)?;
+    config.total_reward_liability = config.total_reward_liability
+        .checked_add(rewards.saturating_sub(penalty_share))
+        .ok_or(ErrorCode::Overflow)?;
+    stake_account.last_reward_calculation = current_time;
+    reset_reward_debt(config, stake_account)?;
+
+    Ok(())
+}
+
+/// Fixed tier APY rewards on a stake's principal between two timestamps
+fn apy_rewards(stake: &StakeAccount, from: i64, to: i64) -> Result<u64> {
+    if to <= from {
+        return Ok(0);
+    }
+
+    let apy = match stake.tier {
+        StakeTier::TierA => APY_TIER_A,
+        StakeTier::TierB => APY_TIER_B,
+        StakeTier::TierC => APY_TIER_C,
+    };
+
+    // Calculate rewards: (amount * APY * time) / (seconds_per_year * 100)
+    let rewards = (stake.earning_amount() as u128)
+        .checked_mul(apy as u128)
+        .ok_or(ErrorCode::Overflow)?
+        .checked_mul((to - from) as u128)
+        .ok_or(ErrorCode::Overflow)?
+        .checked_div(
+            (SECONDS_PER_YEAR as u128)
+                .checked_mul(100)
+                .ok_or(ErrorCode::Overflow)?,
+        )
+        .ok_or(ErrorCode::Overflow)?;
+
+    u64::try_from(rewards).map_err(|_| ErrorCode::Overflow.into())
+}
+
+/// Rewards accrued since the stake's last checkpoint (excluding pending_rewards)
+fn accrued_stake_rewards(config: &Config, stake: &StakeAccount, current_time: i64) -> Result<u64> {
+    // Fixed tier APY applies until pool mode starts
+    let apy_until = if config.reward_pool_enabled {
+        config.reward_pool_started_at
+    } else {
+        current_time
+    };
+    let apy = apy_rewards(stake, stake.last_reward_calculation, apy_until)?;
+
+    // Pool emissions and redistributed early-unstake penalties
+    let (acc, _) = reward_pool_state_at(config, current_time)?;
+    let shared = stake_reward_debt(config, stake, acc)?.saturating_sub(stake.reward_debt);
+    let shared = u64::try_from(shared).map_err(|_| ErrorCode::Overflow)?;
+
+    Ok(apy.checked_add(shared).ok_or(ErrorCode::Overflow)?)
+}
+
+/// Sum of active principal times tier weight across all tiers
+fn total_weighted_stake(config: &Config) -> Result<u128> {
+    config
+        .tier_staked
+        .iter()
+        .zip(config.reward_tier_weights.iter())
+        .try_fold(0u128, |total, (staked, weight)| {
+            (*staked as u128)
+                .checked_mul(*weight as u128)
+                .and_then(|weighted| total.checked_add(weighted))
+        })
+        .ok_or(ErrorCode::Overflow.into())
+}
+
+/// Pool accumulator and tokens emitted if the pool were updated to `now`
+/// Nothing is emitted while no stake is active or the emission budget is spent
+fn reward_pool_state_at(config: &Config, now: i64) -> Result<(u128, u64)> {
+    if !config.reward_pool_enabled || now <= config.last_reward_pool_update {
+        return Ok((config.acc_reward_per_share, 0));
+    }
+    let total_weighted = total_weighted_stake(config)?;
+    if total_weighted == 0 {
+        return Ok((config.acc_reward_per_share, 0));
+    }
+
+    let elapsed = (now - config.last_reward_pool_update) as u64;
+    let emitted = config
+        .reward_emission_per_second
+        .saturating_mul(elapsed)
+        .min(config.reward_emission_budget);
+    let acc = (emitted as u128)
+        .checked_mul(REWARD_POOL_PRECISION)
+        .ok_or(ErrorCode::Overflow)?
+        .checked_div(total_weighted)
+        .and_then(|per_share| config.acc_reward_per_share.checked_add(per_share))
+        .ok_or(ErrorCode::Overflow)?;
+
+    Ok((acc, emitted))
+}
+
+/// Advance the pool accumulator to `now`, spending the emission budget
+/// Must run before tier_staked changes so elapsed emissions use the old weighting
+fn update_reward_pool(config: &mut Config, now: i64) -> Result<()> {
+    if !config.reward_pool_enabled {
+        return Ok(());
+    }
+    let (acc, emitted) = reward_pool_state_at(config, now)?;
+    config.acc_reward_per_share = acc;
+    config.reward_emission_budget = config
+        .reward_emission_budget
+        .checked_sub(emitted)
+        .ok_or(ErrorCode::Overflow)?;
+    config.last_reward_pool_update = config.last_reward_pool_update.max(now);
+    Ok(())
+}
+
+/// A stake's entitlement at pool accumulator `acc` plus its tier's penalty accumulator, unscaled
+/// (stored as the stake's reward debt at each checkpoint)
+fn stake_reward_debt(config: &Config, stake: &StakeAccount, acc: u128) -> Result<u128> {
+    let tier = stake.tier as usize;
+    let weight = config.reward_tier_weights[tier] as u128;
+    let earning = stake.earning_amount() as u128;
+    let pool = earning
+        .checked_mul(weight)
+        .and_then(|weighted| weighted.checked_mul(acc));
+    let penalty = earning.checked_mul(config.tier_penalty_acc_per_share[tier]);
+    pool.zip(penalty)
+        .and_then(|(pool, penalty)| pool.checked_add(penalty))
+        .map(|scaled| scaled / REWARD_POOL_PRECISION)
+        .ok_or(ErrorCode::Overflow.into())
+}
+
+/// Count principal just added to a stake toward pool weighting and reset its reward debt
+fn add_pool_stake(
+    config: &mut Config,
+    stake_account: &mut StakeAccount,
+    amount: u64,
+    current_time: i64,
+) -> Result<()> {
+    update_reward_pool(config, current_time)?;
+    let tier = stake_account.tier as usize;
+    config.tier_staked[tier] = config.tier_staked[tier]
+        .checked_add(amount)
+        .ok_or(ErrorCode::Overflow)?;
+    stake_account.pool_counted = true;
+    reset_reward_debt(config, stake_account)
+}
+
+/// Reset a stake's reward debt and penalty checkpoint to the current accumulators
+fn reset_reward_debt(config: &Config, stake_account: &mut StakeAccount) -> Result<()> {
+    stake_account.reward_debt = stake_reward_debt(config, stake_account, config.acc_reward_per_share)?;
+    stake_account.penalty_acc_checkpoint = config.tier_penalty_acc_per_share[stake_account.tier as usize];
+    Ok(())
+}
+
+/// Early-unstake penalty share accrued by an active stake since its last checkpoint
+fn unbooked_penalty_share(config: &Config, stake: &StakeAccount) -> Result<u64> {
+    if !stake.active {
+        return Ok(0);
+    }
+    let acc = config.tier_penalty_acc_per_share[stake.tier as usize];
+    let share = (stake.earning_amount() as u128)
+        .checked_mul(acc.saturating_sub(stake.penalty_acc_checkpoint))
+        .ok_or(ErrorCode::Overflow)?
+        / REWARD_POOL_PRECISION;
+    Ok(u64::try_from(share).map_err(|_| ErrorCode::Overflow)?)
+}
+
+/// Remove withdrawn principal from pool weighting (no-op for stakes never counted)
+fn remove_pool_stake(
+    config: &mut Config,
+    stake_account: &StakeAccount,
+    amount: u64,
+    current_time: i64,
+) -> Result<()> {
+    update_reward_pool(config, current_time)?;
+    if !stake_account.pool_counted {
+        return Ok(());
+    }
+    let tier = stake_account.tier as usize;
+    config.tier_staked[tier] = config.tier_staked[tier]
+        .checked_sub(amount)
+        .ok_or(ErrorCode::Overflow)?;
+    Ok(())
+}
+
+/// Add a referral bonus to the referrer's existing vesting stake (referrer opted in)
+/// Returns false if the stake cannot take the bonus (inactive or staking caps reached)
+fn credit_referral_bonus_stake(
+    config: &mut Config,
+    stake_account: &mut Account<StakeAccount>,
+    referrer: Pubkey,
+    tier: StakeTier,
+    bonus: u64,
+    current_time: i64,
+) -> Result<bool> {
+    // SECURITY: Validate the stake is the referrer's vesting stake PDA for their chosen tier
+    let (expected_pda, _bump) = Pubkey::find_program_address(
+        &[b"vesting_stake", referrer.as_ref(), &[tier as u8]],
+        &crate::ID
+    );
+    require!(stake_account.key() == expected_pda, ErrorCode::InvalidPDA);
+    require!(stake_account.owner == referrer, ErrorCode::NotStakeOwner);
+
+    if !stake_account.active || !stake_account.is_vesting {
+        return Ok(false);
+    }
+
+    // Respect global and Tier A staking caps
+    let new_total_staked = config.total_staked.checked_add(bonus).ok_or(ErrorCode::Overflow)?;
+    if new_total_staked > MAX_TOTAL_STAKED {
+        return Ok(false);
+    }
+    if tier == StakeTier::TierA {
+        let new_tier_a = config.total_staked_tier_a.checked_add(bonus).ok_or(ErrorCode::Overflow)?;
+        if new_tier_a > MAX_STAKE_TIER_A {
+            return Ok(false);
+        }
+        config.total_staked_tier_a = new_tier_a;
+    }
+    config.total_staked = new_total_staked;
+
+    checkpoint_stake_rewards(config, stake_account, current_time)?;
+    stake_account.amount = stake_account.amount
+        .checked_add(bonus)
+        .ok_or(ErrorCode::Overflow)?;
+    stake_account.total_added = stake_account.total_added
+        .checked_add(bonus)
+        .ok_or(ErrorCode::Overflow)?;
+    add_pool_stake(config, stake_account, bonus, current_time)?;
+
+    Ok(true)
+}
+
+/// Calculate tokens to allocate based on USD value and current stage
+fn calculate_tokens_for_usd(usd_cents: u64, stage: u8) -> Result<u64> {
+    if stage >= 10 {
+        return Err(ErrorCode::PresaleEnded.into());
+    }
+
+    let price_with_4_decimals = STAGE_PRICES[stage as usize]; // e.g., 1501 = $0.1501
+    
+    // Convert price from 4-decimal format to cents
+    // $0.1501 is stored as 1501 (10000x multiplier for 4 decimals)
+    // To get price in cents: 1501 / 10000 * 100 = 15.01 cents
+    // Simplified: price_cents = price_with_4_decimals / 100
+    
+    // tokens = (usd_cents * TOKEN_DECIMALS * 100) / price_with_4_decimals
+    // Example: $100 at stage 1 ($0.1501)
+    // = (10000 cents * 1e9 * 100) / 1501
+    // = 666,222,518,320,453 raw units = 666,222.52 tokens
+    let tokens = (usd_cents as u128)
+        .checked_mul(TOKEN_DECIMALS as u128)
+        .ok_or(ErrorCode::Overflow)?
+        .checked_mul(100) // Adjust for 4-decimal price format
+        .ok_or(ErrorCode::Overflow)?
+        .checked_div(price_with_4_decimals as u128)
+        .ok_or(ErrorCode::Overflow)?;
+
+    Ok(tokens as u64)
+}
+
+fn calculate_rewards_internal(
+    config: &Config,
+    stake: &StakeAccount,
+    current_time: i64,
+) -> Result<u64> {
+    require!(stake.active, ErrorCode::StakeNotActive);
+
+    let new_rewards = accrued_stake_rewards(config, stake, current_time)?;
+
+    Ok(stake
+        .pending_rewards
+        .checked_add(new_rewards)
+        .ok_or(ErrorCode::Overflow)?)
+}
+
+/// Promote the scheduled coordinator once its overlap window has ended
+fn promote_coordinator_if_due(config: &mut Config, timestamp: i64) {
+    if config.next_coordinator == Pubkey::default() {
+        return;
+    }
+    let overlap_end = config
+        .next_coordinator_activates_at
+        .saturating_add(config.coordinator_overlap_seconds);
+    if timestamp >= overlap_end {
+        msg!(
+            "Coordinator rotated: {} -> {}",
+            config.coordinator,
+            config.next_coordinator
+        );
+        config.coordinator = config.next_coordinator;
+        config.next_coordinator = Pubkey::default();
+        config.next_coordinator_activates_at = 0;
+        config.coordinator_overlap_seconds = 0;
+    }
+}
+
+/// Require `key` to be an accepted coordinator: the current one, or the scheduled next
+/// coordinator once active (both are accepted during the overlap window)
+fn require_active_coordinator(config: &mut Config, key: Pubkey, timestamp: i64) -> Result<()> {
+    promote_coordinator_if_due(config, timestamp);
+
+    let next_active = config.next_coordinator != Pubkey::default()
+        && timestamp >= config.next_coordinator_activates_at;
+    require!(
+        key == config.coordinator || (next_active && key == config.next_coordinator),
+        ErrorCode::InvalidCoordinator
+    );
+    Ok(())
+}
+
+/// Accumulate audit stats for the coordinator that credited a purchase
+fn record_coordinator_activity(
+    stats: &mut Account<CoordinatorStats>,
+    coordinator: Pubkey,
+    noc_amount: u64,
+    usd_cents: u64,
+    vest_staked: bool,
+    timestamp: i64,
+) -> Result<()> {
+    if stats.first_recorded_at == 0 {
+        stats.coordinator = coordinator;
+        stats.first_recorded_at = timestamp;
+    }
+    stats.purchases_recorded = stats
+        .purchases_recorded
+        .checked_add(1)
+        .ok_or(ErrorCode::Overflow)?;
+    if vest_staked {
+        stats.vest_stakes_created = stats
+            .vest_stakes_created
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
+    }
+    stats.usd_volume_cents = stats
+        .usd_volume_cents
+        .checked_add(usd_cents)
+        .ok_or(ErrorCode::Overflow)?;
+    stats.noc_volume = stats
+        .noc_volume
+        .checked_add(noc_amount)
+        .ok_or(ErrorCode::Overflow)?;
+    stats.last_recorded_at = timestamp;
+    Ok(())
+}
+
+/// Enforce the rolling global and per-chain USD limits for a cross-chain purchase
+/// Returns false (and pauses cross-chain ingestion) if the purchase would exceed a limit
+fn apply_cross_chain_rate_limits(
+    config: &mut Config,
+    supported_chain: &mut SupportedChain,
+    usd_cents: u64,
+    timestamp: i64,
+) -> Result<bool> {
+    require!(!config.cross_chain_paused, ErrorCode::CrossChainPaused);
+
+    config.cross_chain_usage.roll(timestamp);
+    supported_chain.usage.roll(timestamp);
+
+    let global_hour = config.cross_chain_usage.hour_total().saturating_add(usd_cents);
+    let global_day = config.cross_chain_usage.day_total().saturating_add(usd_cents);
+    let chain_hour = supported_chain.usage.hour_total().saturating_add(usd_cents);
+    let chain_day = supported_chain.usage.day_total().saturating_add(usd_cents);
+
+    if global_hour > config.cross_chain_hourly_limit()
+        || global_day > config.cross_chain_daily_limit()
+        || chain_hour > supported_chain.hourly_limit()
+        || chain_day > supported_chain.daily_limit()
+    {
+        config.cross_chain_paused = true;
+        config.cross_chain_paused_at = timestamp;
+        msg!(
+            "Cross-chain circuit breaker tripped: chain={}, usd_cents={}, global hour/day={}/{}, chain hour/day={}/{}",
+            supported_chain.chain_id,
+            usd_cents,
+            global_hour,
+            global_day,
+            chain_hour,
+            chain_day
+        );
+        return Ok(false);
+    }
+
+    config.cross_chain_usage.record(usd_cents, timestamp)?;
+    supported_chain.usage.record(usd_cents, timestamp)?;
+    Ok(true)
+}
+
+/// Zero the buckets of a ring that were skipped between `last` and `now`
+fn roll_usage_buckets(buckets: &mut [u64], width: i64, last: i64, now: i64) {
+    let len = buckets.len() as i64;
+    let last_slot = last / width;
+    let now_slot = now / width;
+    if now_slot <= last_slot {
+        return;
+    }
+    if now_slot - last_slot >= len {
+        buckets.fill(0);
+    } else {
+        for slot in (last_slot + 1)..=now_slot {
+            buckets[(slot % len) as usize] = 0;
+        }
+    }
+}
+
+/// Remove a stake account from a stake index (no-op if it is not listed)
+fn unregister_indexed_stake(index: &mut Account<UserStakeIndex>, stake: Pubkey) {
+    let count = index.stake_count as usize;
+    if let Some(pos) = index.stakes[..count].iter().position(|s| *s == stake) {
+        index.stakes[pos] = index.stakes[count - 1];
+        index.stakes[count - 1] = Pubkey::default();
+        index.stake_count -= 1;
+    }
+}
+
+/// Drop a closed stake from its owner's index once nothing is left to claim on it
+fn unindex_closed_stake(index: &mut Account<UserStakeIndex>, stake_account: &Account<StakeAccount>) {
+    if !stake_account.active && stake_account.pending_rewards == 0 {
+        unregister_indexed_stake(index, stake_account.key());
+    }
+}
+
+/// Append a stake account to its owner's stake index (duplicates rejected)
+fn register_indexed_stake(
+    index: &mut Account<UserStakeIndex>,
+    owner: Pubkey,
+    stake: Pubkey,
+) -> Result<()> {
+    index.owner = owner;
+    let count = index.stake_count as usize;
+    require!(
+        !index.stakes[..count].contains(&stake),
+        ErrorCode::StakeAlreadyIndexed
+    );
+    require!(count < MAX_INDEXED_STAKES, ErrorCode::StakeIndexFull);
+    index.stakes[count] = stake;
+    index.stake_count += 1;
+    Ok(())
+}
+
+/// Add tokens to a vault sub-ledger bucket
+fn credit_vault_bucket(config: &mut Config, bucket: VaultBucket, amount: u64) -> Result<()> {
+    let bucket = config.vault_bucket_mut(bucket)?;
+    *bucket = bucket.checked_add(amount).ok_or(ErrorCode::Overflow)?;
+    Ok(())
+}
+
+/// Remove tokens from a vault sub-ledger bucket (fails if the bucket cannot cover it)
+fn debit_vault_bucket(config: &mut Config, bucket: VaultBucket, amount: u64) -> Result<()> {
+    let bucket = config.vault_bucket_mut(bucket)?;
+    *bucket = bucket
+        .checked_sub(amount)
+        .ok_or(ErrorCode::VaultBucketInsufficient)?;
+    Ok(())
+}
+
+/// Advance through full stages, recording when the stage last changed
+fn advance_stage_if_full(config: &mut Config, timestamp: i64) -> Result<()> {
+    while config.stage_tokens_sold >= TOKENS_PER_STAGE * TOKEN_DECIMALS && config.current_stage < 9 {
+        config.current_stage += 1;
+        config.stage_tokens_sold = config.stage_tokens_sold
+            .checked_sub(TOKENS_PER_STAGE * TOKEN_DECIMALS)
+            .ok_or(ErrorCode::Overflow)?;
+        config.stage_advanced_at = timestamp;
+
+        msg!("Advanced to stage {}", config.current_stage + 1);
+    }
+    Ok(())
+}
+
+/// Verify a reported cross-chain NOC amount against on-chain stage pricing
+/// The quoted stage must be current, or the previous one within the quote window
+fn verify_cross_chain_price(
+    config: &Config,
+    noc_amount: u64,
+    usd_cents: u64,
+    quoted_stage: u8,
+    timestamp: i64,
+) -> Result<()> {
+    let quote_still_valid = quoted_stage
+        .checked_add(1)
+        .is_some_and(|next| next == config.current_stage)
+        && timestamp
+            .checked_sub(config.stage_advanced_at)
+            .ok_or(ErrorCode::Overflow)?
+            <= config.stage_quote_window_seconds;
+    require!(
+        quoted_stage == config.current_stage || quote_still_valid,
+        ErrorCode::InvalidQuotedStage
+    );
+
+    let expected = calculate_tokens_for_usd(usd_cents, quoted_stage)?;
+    let difference = noc_amount.abs_diff(expected) as u128;
+    let allowed = (expected as u128)
+        .checked_mul(config.cross_chain_price_tolerance_bps as u128)
+        .ok_or(ErrorCode::Overflow)?
+        / 10_000;
+    require!(difference <= allowed, ErrorCode::CrossChainPriceMismatch);
+
+    Ok(())
+}
+
+/// Common start of every cross-chain purchase: sale state, stage price and per-transaction limits,
+/// then circuit breaker and replay protection. Returns false if the circuit breaker tripped.
+/// `purchase.buyer_eth_address` is the EVM buyer (non-EVM: first 20 bytes of the foreign address hash).
+fn begin_cross_chain_purchase(
+    config: &mut Config,
+    supported_chain: &mut SupportedChain,
+    processed_tx: &mut Account<ProcessedCrossChainTx>,
+    purchase: &CrossChainPurchase,
+    vest_staked: bool,
+    timestamp: i64,
+) -> Result<bool> {
+    let CrossChainPurchase { chain_id, noc_amount, usd_cents, stage, .. } = *purchase;
+
+    require!(
+        processed_tx.processed_at == 0,
+        ErrorCode::TransactionAlreadyProcessed
+    );
+
+    // Validate presale is active
+    require!(config.presale_active, ErrorCode::PresaleNotStarted);
+    require!(
+        config.tokens_sold.checked_add(noc_amount).ok_or(ErrorCode::Overflow)? 
+            <= PRESALE_TOTAL_ALLOCATION * TOKEN_DECIMALS,
+        ErrorCode::PresaleHardCapReached
+    );
+
+    // Validate chain is registered and enabled
+    require!(
+        supported_chain.enabled && supported_chain.chain_id == chain_id,
+        ErrorCode::InvalidChainId
+    );
+
+    // Reported tokens must match the quoted stage price
+    verify_cross_chain_price(config, noc_amount, usd_cents, stage, timestamp)?;
+
+    // === CROSS-CHAIN SECURITY CHECKS (per-chain limits) ===
+    
+    // 1. Validate minimum purchase
+    require!(
+        usd_cents >= supported_chain.min_usd(),
+        ErrorCode::CrossChainBelowMinimum
+    );
+    
+    // 2. Validate maximum per transaction
+    require!(
+        usd_cents <= supported_chain.max_usd(),
+        ErrorCode::CrossChainExceedsMaximum
+    );
+
+    // Circuit breaker (only for otherwise valid purchases) - a purchase over the rolling limits
+    // trips the cross-chain pause and is not credited (nor marked processed), so it can be
+    // resubmitted after the admin reset
+    if !apply_cross_chain_rate_limits(config, supported_chain, usd_cents, timestamp)? {
+        return Ok(false);
+    }
+
+    // Replay protection - each purchase event can only be credited once
+    mark_cross_chain_tx_processed(
+        processed_tx,
+        purchase,
+        config.current_stage,
+        vest_staked,
+        timestamp,
+    )?;
+
+    Ok(true)
+}
+
+/// Common end of every credited cross-chain purchase: sale totals and stage progress
+fn finish_cross_chain_purchase(
+    config: &mut Config,
+    noc_amount: u64,
+    usd_cents: u64,
+    timestamp: i64,
+) -> Result<()> {
+    // Update global stats
+    config.tokens_sold = config
+        .tokens_sold
+        .checked_add(noc_amount)
+        .ok_or(ErrorCode::Overflow)?;
+    config.cross_chain_tokens_sold = config
+        .cross_chain_tokens_sold
+        .checked_add(noc_amount)
+        .ok_or(ErrorCode::Overflow)?;
+    config.total_usd_raised_cents = config
+        .total_usd_raised_cents
+        .checked_add(usd_cents)
+        .ok_or(ErrorCode::Overflow)?;
+
+    // Update stage progress
+    config.stage_tokens_sold = config
+        .stage_tokens_sold
+        .checked_add(noc_amount)
+        .ok_or(ErrorCode::Overflow)?;
+
+    // Advance stage if current stage is full
+    advance_stage_if_full(config, timestamp)?;
+
+    Ok(())
+}
+
+/// Shared cross-chain purchase recording (single coordinator or committee attestation)
+fn record_cross_chain_purchase_internal<'info>(
+    config: &mut Account<'info, Config>,
+    supported_chain: &mut Account<'info, SupportedChain>,
+    processed_tx: &mut Account<'info, ProcessedCrossChainTx>,
+    allocation: &mut Account<'info, CrossChainAllocation>,
+    referrer_cross_chain_referral: &mut Option<Account<'info, CrossChainReferral>>,
+    purchase: &CrossChainPurchase,
+    vest_staked: bool,
+) -> Result<bool> {
+    let CrossChainPurchase { buyer_eth_address, chain_id, noc_amount, usd_cents, referrer_eth, .. } = *purchase;
+    let timestamp = Clock::get()?.unix_timestamp;
+
+    // Replay protection, circuit breaker, price and per-transaction checks
+    if !begin_cross_chain_purchase(
+        config,
+        supported_chain,
+        processed_tx,
+        purchase,
+        vest_staked,
+        timestamp,
+    )? {
+        return Ok(false);
+    }
+
+    // 3. Check cooldown - only for existing allocations
+    if allocation.chain_id != 0 {
+        let time_since_last = timestamp
+            .checked_sub(allocation.last_purchase_at)
+            .ok_or(ErrorCode::Overflow)?;
+        require!(
+            time_since_last >= supported_chain.cooldown(),
+            ErrorCode::CrossChainCooldown
+        );
+    }
+    
+    // 4. Check user total limit
+    let new_total_usd = if allocation.chain_id == 0 {
+        usd_cents
+    } else {
+        allocation.total_usd_cents
+            .checked_add(usd_cents)
+            .ok_or(ErrorCode::Overflow)?
+    };
+    require!(
+        new_total_usd <= supported_chain.max_total_usd(),
+        ErrorCode::CrossChainUserLimitExceeded
+    );
+
+    // Initialize or update allocation
+    if allocation.chain_id == 0 {
+        // First purchase
+        allocation.eth_address = buyer_eth_address;
+        allocation.chain_id = chain_id;
+        allocation.first_purchase_at = timestamp;
+        allocation.referrer_eth = referrer_eth;
+        allocation.linked_solana_wallet = Pubkey::default();
+        allocation.claimed = false;
+    }
+
+    // Vest-staked tokens are delivered as stake immediately and are not claimable at TGE
+    if vest_staked {
+        allocation.vest_staked_tokens = allocation
+            .vest_staked_tokens
+            .checked_add(noc_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        allocation.vest_staked_usd_cents = allocation
+            .vest_staked_usd_cents
+            .checked_add(usd_cents)
+            .ok_or(ErrorCode::Overflow)?;
+    } else {
+        allocation.total_tokens = allocation
+            .total_tokens
+            .checked_add(noc_amount)
+            .ok_or(ErrorCode::Overflow)?;
+        credit_vault_bucket(config, VaultBucket::PresaleUnclaimed, noc_amount)?;
+    }
+    allocation.total_usd_cents = allocation
+        .total_usd_cents
+        .checked_add(usd_cents)
+        .ok_or(ErrorCode::Overflow)?;
+    allocation.purchase_count = allocation
+        .purchase_count
+        .checked_add(1)
+        .ok_or(ErrorCode::Overflow)?;
+    allocation.last_purchase_at = timestamp;
+
+    // Process referral bonus (volume-tiered) - paid on every purchase by a referred buyer
+    // The buyer's referrer is fixed by its first purchase; a different reported referrer earns nothing
+    let referred = allocation.referrer_eth != [0u8; 20] && referrer_eth == allocation.referrer_eth;
+    let referral_bonus = if referred {
+        // Referrer ledger must be supplied so the bonus is never lost
+        let referral = referrer_cross_chain_referral
+            .as_mut()
+            .ok_or(ErrorCode::ReferralAccountsRequired)?;
+
+        let percentage = referral_percentage_for_volume(config, referral.referred_usd_cents);
+        let bonus = noc_amount
+            .checked_mul(percentage)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_div(100)
+            .ok_or(ErrorCode::Overflow)?;
+
+        // Check Community Rewards pool
+        let new_total_referral = config.total_referral_bonuses
+            .checked_add(bonus)
+            .ok_or(ErrorCode::Overflow)?;
+        let bonus = if new_total_referral <= COMMUNITY_REWARDS_ALLOCATION * TOKEN_DECIMALS {
+            config.total_referral_bonuses = new_total_referral;
+            credit_vault_bucket(config, VaultBucket::CommunityReserved, bonus)?;
+            msg!("Referral bonus: {} tokens ({}%)", bonus, percentage);
+            bonus
+        } else {
+            msg!("Referral pool exhausted");
+            0
+        };
+
+        // Accumulate bonus and referred volume for the referrer (claimable on Solana after TGE)
+        if referral.first_referral_at == 0 {
+            referral.referrer_eth = referrer_eth;
+            referral.chain_id = chain_id;
+            referral.first_referral_at = timestamp;
+        }
+        if allocation.purchase_count == 1 {
+            referral.referral_count = referral
+                .referral_count
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+        referral.referred_usd_cents = referral
+            .referred_usd_cents
+            .checked_add(usd_cents)
+            .ok_or(ErrorCode::Overflow)?;
+        referral.total_bonus = referral
+            .total_bonus
+            .checked_add(bonus)
+            .ok_or(ErrorCode::Overflow)?;
+        referral.last_referral_at = timestamp;
+        allocation.referral_bonus = allocation
+            .referral_bonus
+            .checked_add(bonus)
+            .ok_or(ErrorCode::Overflow)?;
+        bonus
+    } else {
+        0
+    };
+    processed_tx.referred = referred;
+    processed_tx.referral_bonus = referral_bonus;
+
+    finish_cross_chain_purchase(config, noc_amount, usd_cents, timestamp)?;
+
+    msg!(
+        "Cross-chain purchase recorded: chain={}, buyer={:?}, noc={}, usd_cents={}, referral_bonus={}",
+        chain_id,
+        &buyer_eth_address[..4],
+        noc_amount,
+        usd_cents,
+        referral_bonus
+    );
+
+    Ok(true)
+}
+
+/// Parse a Core Bridge PostedVAA account into (emitter chain, emitter address, payload)
+/// Layout: "vaa" | version u8 | consistency u8 | vaa_time u32 | signature_set [32] |
+///         submission_time u32 | nonce u32 | sequence u64 | emitter_chain u16 | emitter [32] | payload vec
+fn parse_posted_vaa(data: &[u8]) -> Result<(u16, [u8; 32], &[u8])> {
+    require!(data.len() >= POSTED_VAA_HEADER_LEN, ErrorCode::InvalidVaa);
+    require!(&data[..3] == POSTED_VAA_DISCRIMINATOR, ErrorCode::InvalidVaa);
+
+    let emitter_chain = u16::from_le_bytes([data[57], data[58]]);
+    let mut emitter_address = [0u8; 32];
+    emitter_address.copy_from_slice(&data[59..91]);
+
+    let payload_len = u32::from_le_bytes([data[91], data[92], data[93], data[94]]) as usize;
+    let payload_end = POSTED_VAA_HEADER_LEN
+        .checked_add(payload_len)
+        .ok_or(ErrorCode::Overflow)?;
+    require!(data.len() >= payload_end, ErrorCode::InvalidVaa);
+
+    Ok((emitter_chain, emitter_address, &data[POSTED_VAA_HEADER_LEN..payload_end]))
+}
+
+/// Decode a purchase payload (big-endian, abi.encodePacked) emitted on EVM chain `chain_id`:
+/// payload_id u8 | buyer [20] | noc_amount u64 | usd_cents u64 | stage u8 | referrer [20] |
+/// tx_hash [32] | log_index u32
+fn decode_vaa_purchase_payload(payload: &[u8], chain_id: u64) -> Result<CrossChainPurchase> {
+    require!(
+        payload.len() == VAA_PURCHASE_PAYLOAD_LEN && payload[0] == VAA_PURCHASE_PAYLOAD_ID,
+        ErrorCode::InvalidVaaPayload
+    );
+
+    let mut buyer_eth_address = [0u8; 20];
+    buyer_eth_address.copy_from_slice(&payload[1..21]);
+    let mut noc_amount = [0u8; 8];
+    noc_amount.copy_from_slice(&payload[21..29]);
+    let mut usd_cents = [0u8; 8];
+    usd_cents.copy_from_slice(&payload[29..37]);
+    let mut referrer_eth = [0u8; 20];
+    referrer_eth.copy_from_slice(&payload[38..58]);
+    let mut tx_hash = [0u8; 32];
+    tx_hash.copy_from_slice(&payload[58..90]);
+    let mut log_index = [0u8; 4];
+    log_index.copy_from_slice(&payload[90..94]);
+
+    Ok(CrossChainPurchase {
+        buyer_eth_address,
+        chain_id,
+        noc_amount: u64::from_be_bytes(noc_amount),
+        usd_cents: u64::from_be_bytes(usd_cents),
+        tx_hash,
+        log_index: u32::from_be_bytes(log_index),
+        stage: payload[37],
+        referrer_eth,
+    })
+}
+
+/// Hash of the purchase data committee members attest to
+fn cross_chain_attestation_hash(purchase: &CrossChainPurchase) -> [u8; 32] {
+    keccak::hashv(&[
+        purchase.buyer_eth_address.as_ref(),
+        &purchase.chain_id.to_le_bytes(),
+        &purchase.noc_amount.to_le_bytes(),
+        &purchase.usd_cents.to_le_bytes(),
+        purchase.tx_hash.as_ref(),
+        &purchase.log_index.to_le_bytes(),
+        &[purchase.stage],
+        purchase.referrer_eth.as_ref(),
+    ])
+    .to_bytes()
+}
+
+/// Record an EVM purchase event as processed, rejecting replays
+fn mark_cross_chain_tx_processed(
+    processed_tx: &mut Account<ProcessedCrossChainTx>,
+    purchase: &CrossChainPurchase,
+    current_stage: u8,
+    vest_staked: bool,
+    timestamp: i64,
+) -> Result<()> {
+    require!(
+        processed_tx.processed_at == 0,
+        ErrorCode::TransactionAlreadyProcessed
+    );
+
+    let quoted_stage = purchase.stage;
+    processed_tx.chain_id = purchase.chain_id;
+    processed_tx.tx_hash = purchase.tx_hash;
+    processed_tx.log_index = purchase.log_index;
+    processed_tx.buyer_eth_address = purchase.buyer_eth_address;
+    processed_tx.noc_amount = purchase.noc_amount;
+    processed_tx.usd_cents = purchase.usd_cents;
+    processed_tx.quoted_stage = quoted_stage;
+    processed_tx.recorded_stage = current_stage;
+    processed_tx.vest_staked = vest_staked;
+    processed_tx.processed_at = timestamp;
+
+    if quoted_stage != current_stage {
+        msg!(
+            "Stage mismatch: EVM quoted stage {}, Solana stage {}",
+            quoted_stage,
+            current_stage
+        );
+    }
+
+    Ok(())
+}
+
+/// Lowercase 0x-prefixed hex for an EVM address (as shown by wallets)
+fn evm_address_hex(address: &[u8; 20]) -> String {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(42);
+    out.push_str("0x");
+    for byte in address {
+        out.push(HEX[(byte >> 4) as usize] as char);
+        out.push(HEX[(byte & 0x0f) as usize] as char);
+    }
+    out
+}
+
+/// Message the EVM wallet signs (personal_sign) to link a Solana wallet
+fn evm_link_message(
+    purpose: &str,
+    eth_address: [u8; 20],
+    chain_id: u64,
+    wallet: Pubkey,
+    nonce: u64,
+    program_id: &Pubkey,
+) -> String {
+    format!(
+        "Noctura presale: link {} of {} on chain {} to Solana wallet {}\nNonce: {}\nProgram: {}",
+        purpose,
+        evm_address_hex(&eth_address),
+        chain_id,
+        wallet,
+        nonce,
+        program_id
+    )
+}
+
+/// Verify an EIP-191 signature from `eth_address` authorizing `wallet`, then bump the nonce
+fn verify_evm_link_signature(
+    evm_link_nonce: &mut Account<EvmLinkNonce>,
+    purpose: &str,
+    eth_address: [u8; 20],
+    chain_id: u64,
+    wallet: Pubkey,
+    program_id: &Pubkey,
+    signature: &[u8; 65],
+) -> Result<()> {
+    let message = evm_link_message(
+        purpose,
+        eth_address,
+        chain_id,
+        wallet,
+        evm_link_nonce.nonce,
+        program_id,
+    );
+    verify_evm_signed_message(evm_link_nonce, eth_address, &message, signature)
+}
+
+/// Message an EVM buyer signs to manage a coordinator-created stake
+fn evm_stake_action_message(
+    action: &str,
+    stake: Pubkey,
+    eth_address: [u8; 20],
+    chain_id: u64,
+    nonce: u64,
+    program_id: &Pubkey,
+) -> String {
+    format!(
+        "Noctura staking: {} for stake {} of {} on chain {}\nNonce: {}\nProgram: {}",
+        action,
+        stake,
+        evm_address_hex(&eth_address),
+        chain_id,
+        nonce,
+        program_id
+    )
+}
+
+/// Verify the stake's EVM owner signed `action` for this stake, then bump the nonce
+fn verify_evm_stake_action(
+    evm_link_nonce: &mut Account<EvmLinkNonce>,
+    allocation: &CrossChainAllocation,
+    action: &str,
+    stake: Pubkey,
+    program_id: &Pubkey,
+    signature: &[u8; 65],
+) -> Result<()> {
+    let message = evm_stake_action_message(
+        action,
+        stake,
+        allocation.eth_address,
+        allocation.chain_id,
+        evm_link_nonce.nonce,
+        program_id,
+    );
+    verify_evm_signed_message(evm_link_nonce, allocation.eth_address, &message, signature)
+}
+
+/// EVM address that produced an EIP-191 `personal_sign` signature of `message`
+fn recover_evm_signer(message: &str, signature: &[u8; 65]) -> Result<[u8; 20]> {
+    let message_len = message.len().to_string();
+    let digest = keccak::hashv(&[
+        EVM_SIGNED_MESSAGE_PREFIX,
+        message_len.as_bytes(),
+        message.as_bytes(),
+    ]);
+
+    // Accept both v = 27/28 (wallets) and v = 0/1 (raw)
+    let recovery_id = match signature[64] {
+        27 | 28 => signature[64] - 27,
+        0 | 1 => signature[64],
+        _ => return Err(ErrorCode::InvalidEvmSignature.into()),
+    };
+
+    let recovered = secp256k1_recover(&digest.to_bytes(), recovery_id, &signature[..64])
+        .map_err(|_| ErrorCode::InvalidEvmSignature)?;
+    let mut signer = [0u8; 20];
+    signer.copy_from_slice(&keccak::hash(&recovered.to_bytes()).to_bytes()[12..]);
+    Ok(signer)
+}
+
+/// Verify an EIP-191 `personal_sign` signature of `message` by `eth_address`, then bump the nonce
+fn verify_evm_signed_message(
+    evm_link_nonce: &mut Account<EvmLinkNonce>,
+    eth_address: [u8; 20],
+    message: &str,
+    signature: &[u8; 65],
+) -> Result<()> {
+    if evm_link_nonce.eth_address == [0u8; 20] {
+        evm_link_nonce.eth_address = eth_address;
+    }
+
+    require!(
+        recover_evm_signer(message, signature)? == eth_address,
+        ErrorCode::InvalidEvmSignature
+    );
+
+    evm_link_nonce.nonce = evm_link_nonce
+        .nonce
+        .checked_add(1)
+        .ok_or(ErrorCode::Overflow)?;
+
+    Ok(())
+}
+
+// =====================================================
+// ACCOUNT STRUCTURES
+// =====================================================
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"config", admin.key().as_ref()],
+        bump,
+        space = 8 + Config::SPACE
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [ico_mint.key().as_ref()],
+        bump,
+        token::mint = ico_mint,
+        token::authority = ico_ata_for_ico_program,
+    )]
+    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
+
+    pub ico_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub ico_ata_for_admin: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct PresalePurchaseWithSol<'info> {
+    #[account(mut)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"user", user.key().as_ref()],
+        bump,
+        space = 8 + UserAccount::SPACE
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"allocation", user.key().as_ref()],
+        bump,
+        space = 8 + PresaleAllocation::SPACE
+    )]
+    pub user_allocation: Account<'info, PresaleAllocation>,
+
+    /// CHECK: Optional referrer allocation account - validated manually in function
+    #[account(mut)]
+    pub referrer_allocation: UncheckedAccount<'info>,
+
+    /// Referrer ledger - required when the buyer has a registered referrer
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"referrer_stats", user_allocation.referrer.as_ref()],
+        bump,
+        space = 8 + ReferrerStats::SPACE
+    )]
+    pub referrer_stats: Option<Account<'info, ReferrerStats>>,
+
+    /// Per-referee referral record - required when the buyer has a registered referrer
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"referral", user_allocation.referrer.as_ref(), user.key().as_ref()],
+        bump,
+        space = 8 + ReferralRecord::SPACE
+    )]
+    pub referral_record: Option<Account<'info, ReferralRecord>>,
+
+    /// Referrer's user account - referral counters are mirrored here if it exists
+    #[account(
+        mut,
+        seeds = [b"user", user_allocation.referrer.as_ref()],
+        bump,
+    )]
+    pub referrer_user_account: Option<Account<'info, UserAccount>>,
+
+    /// Referral bonus escrow - required when the referrer has no allocation account yet
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"pending_referral", user_allocation.referrer.as_ref()],
+        bump,
+        space = 8 + PendingReferralRewards::SPACE
+    )]
+    pub pending_referral_rewards: Option<Account<'info, PendingReferralRewards>>,
+
+    /// Pyth SOL/USD price account
+    pub pyth_sol_usd_price: Account<'info, PriceUpdateV2>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: SOL Treasury receives SOL payments - VALIDATED against config.sol_treasury
+    #[account(
+        mut,
+        constraint = sol_treasury.key() == config.sol_treasury @ ErrorCode::InvalidAdmin
+    )]
+    pub sol_treasury: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Account struct for presale purchase with automatic vesting stake
+#[derive(Accounts)]
+#[instruction(sol_amount: u64, tier: StakeTier)]
+pub struct PresalePurchaseAndVestStake<'info> {
+    #[account(
+        mut,
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub config: Account<'info, Config>,
+
+    pub ico_mint: Account<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"user", user.key().as_ref()],
+        bump,
+        space = 8 + UserAccount::SPACE
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"allocation", user.key().as_ref()],
+        bump,
+        space = 8 + PresaleAllocation::SPACE
+    )]
+    pub user_allocation: Account<'info, PresaleAllocation>,
+
+    /// Stake account - PDA per user per tier (max 3 accounts per user)
+    /// Seeds: ["vesting_stake", user_pubkey, tier_byte]
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"vesting_stake", user.key().as_ref(), &[tier as u8]],
+        bump,
+        space = 8 + StakeAccount::SPACE
+    )]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    /// CHECK: Optional referrer allocation account - validated manually in function
+    #[account(mut)]
+    pub referrer_allocation: UncheckedAccount<'info>,
+
+    /// Referrer ledger - required when the buyer has a registered referrer
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"referrer_stats", user_allocation.referrer.as_ref()],
+        bump,
+        space = 8 + ReferrerStats::SPACE
+    )]
+    pub referrer_stats: Option<Account<'info, ReferrerStats>>,
+
+    /// Per-referee referral record - required when the buyer has a registered referrer
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"referral", user_allocation.referrer.as_ref(), user.key().as_ref()],
+        bump,
+        space = 8 + ReferralRecord::SPACE
+    )]
+    pub referral_record: Option<Account<'info, ReferralRecord>>,
+
+    /// Referrer's user account - referral counters are mirrored here if it exists
+    #[account(
+        mut,
+        seeds = [b"user", user_allocation.referrer.as_ref()],
+        bump,
+    )]
+    pub referrer_user_account: Option<Account<'info, UserAccount>>,
+
+    /// Referral bonus escrow - required when the referrer has no allocation account yet
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"pending_referral", user_allocation.referrer.as_ref()],
+        bump,
+        space = 8 + PendingReferralRewards::SPACE
+    )]
+    pub pending_referral_rewards: Option<Account<'info, PendingReferralRewards>>,
+
+    /// Referrer's vesting stake for their preferred tier - credited if they opted in to stake bonuses
+    /// Validated manually in function
+    #[account(mut)]
+    pub referrer_vesting_stake: Option<Account<'info, StakeAccount>>,
+
+    /// Pyth SOL/USD price account
+    pub pyth_sol_usd_price: Account<'info, PriceUpdateV2>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: SOL Treasury receives SOL payments - VALIDATED against config.sol_treasury
+    #[account(
+        mut,
+        constraint = sol_treasury.key() == config.sol_treasury @ ErrorCode::InvalidAdmin
+    )]
+    pub sol_treasury: AccountInfo<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PresalePurchaseWithStablecoin<'info> {
+    #[account(mut)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"user", user.key().as_ref()],
+        bump,
+        space = 8 + UserAccount::SPACE
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"allocation", user.key().as_ref()],
+        bump,
+        space = 8 + PresaleAllocation::SPACE
+    )]
+    pub user_allocation: Account<'info, PresaleAllocation>,
+
+    /// CHECK: Optional referrer allocation account - validated manually in function
+    #[account(mut)]
+    pub referrer_allocation: UncheckedAccount<'info>,
+
+    /// Referrer ledger - required when the buyer has a registered referrer
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"referrer_stats", user_allocation.referrer.as_ref()],
+        bump,
+        space = 8 + ReferrerStats::SPACE
+    )]
+    pub referrer_stats: Option<Account<'info, ReferrerStats>>,
+
+    /// Per-referee referral record - required when the buyer has a registered referrer
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"referral", user_allocation.referrer.as_ref(), user.key().as_ref()],
+        bump,
+        space = 8 + ReferralRecord::SPACE
+    )]
+    pub referral_record: Option<Account<'info, ReferralRecord>>,
+
+    /// Referrer's user account - referral counters are mirrored here if it exists
+    #[account(
+        mut,
+        seeds = [b"user", user_allocation.referrer.as_ref()],
+        bump,
+    )]
+    pub referrer_user_account: Option<Account<'info, UserAccount>>,
+
+    /// Referral bonus escrow - required when the referrer has no allocation account yet
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"pending_referral", user_allocation.referrer.as_ref()],
+        bump,
+        space = 8 + PendingReferralRewards::SPACE
+    )]
+    pub pending_referral_rewards: Option<Account<'info, PendingReferralRewards>>,
+
+    #[account(mut)]
+    pub stablecoin_ata_for_user: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub stablecoin_ata_for_admin: Account<'info, TokenAccount>,
+
+    pub stablecoin_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Account struct for presale purchase with stablecoin (USDT/USDC) and automatic vesting stake
+/// Uses PDA per user per tier (max 3 stake accounts per user)
+#[derive(Accounts)]
+#[instruction(stablecoin_amount: u64, tier: StakeTier)]
+pub struct PresalePurchaseStablecoinAndVestStake<'info> {
+    #[account(
+        mut,
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub config: Account<'info, Config>,
+
+    pub ico_mint: Account<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"user", user.key().as_ref()],
+        bump,
+        space = 8 + UserAccount::SPACE
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"allocation", user.key().as_ref()],
+        bump,
+        space = 8 + PresaleAllocation::SPACE
+    )]
+    pub user_allocation: Account<'info, PresaleAllocation>,
+
+    /// Stake account - PDA per user per tier (max 3 accounts per user)
+    /// Seeds: ["vesting_stake", user_pubkey, tier_byte]
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"vesting_stake", user.key().as_ref(), &[tier as u8]],
+        bump,
+        space = 8 + StakeAccount::SPACE
+    )]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    /// CHECK: Optional referrer allocation account - validated manually in function
+    #[account(mut)]
+    pub referrer_allocation: UncheckedAccount<'info>,
+
+    /// Referrer ledger - required when the buyer has a registered referrer
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"referrer_stats", user_allocation.referrer.as_ref()],
+        bump,
+        space = 8 + ReferrerStats::SPACE
+    )]
+    pub referrer_stats: Option<Account<'info, ReferrerStats>>,
+
+    /// Per-referee referral record - required when the buyer has a registered referrer
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"referral", user_allocation.referrer.as_ref(), user.key().as_ref()],
+        bump,
+        space = 8 + ReferralRecord::SPACE
+    )]
+    pub referral_record: Option<Account<'info, ReferralRecord>>,
+
+    /// Referrer's user account - referral counters are mirrored here if it exists
+    #[account(
+        mut,
+        seeds = [b"user", user_allocation.referrer.as_ref()],
+        bump,
+    )]
+    pub referrer_user_account: Option<Account<'info, UserAccount>>,
+
+    /// Referral bonus escrow - required when the referrer has no allocation account yet
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"pending_referral", user_allocation.referrer.as_ref()],
+        bump,
+        space = 8 + PendingReferralRewards::SPACE
+    )]
+    pub pending_referral_rewards: Option<Account<'info, PendingReferralRewards>>,
+
+    /// Referrer's vesting stake for their preferred tier - credited if they opted in to stake bonuses
+    /// Validated manually in function
+    #[account(mut)]
+    pub referrer_vesting_stake: Option<Account<'info, StakeAccount>>,
+
+    #[account(mut)]
+    pub stablecoin_ata_for_user: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub stablecoin_ata_for_admin: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPresaleAllocation<'info> {
+    #[account(mut)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [b"allocation", user.key().as_ref()],
+        bump,
+    )]
+    pub user_allocation: Account<'info, PresaleAllocation>,
+
+    #[account(mut)]
+    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub ico_ata_for_user: Account<'info, TokenAccount>,
+
+    pub ico_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for claiming rewards left unpaid at unstake
+#[derive(Accounts)]
+pub struct ClaimUnpaidRewards<'info> {
+    #[account(
+        mut,
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(mut)]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    /// Owner's stake index - the stake is dropped from it once closed (optional)
+    #[account(
+        mut,
+        seeds = [b"stake_index", stake_account.owner.as_ref()],
+        bump,
+    )]
+    pub stake_index: Option<Account<'info, UserStakeIndex>>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_vault", ico_mint.key().as_ref()],
+        bump,
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub ico_ata_for_user: Account<'info, TokenAccount>,
+
+    pub ico_mint: Account<'info, Mint>,
+
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for creating the staking reward vault (admin only)
+#[derive(Accounts)]
+pub struct InitializeRewardVault<'info> {
+    #[account(
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"reward_vault", ico_mint.key().as_ref()],
+        bump,
+        token::mint = ico_mint,
+        token::authority = reward_vault,
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
+
+    pub ico_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for funding the reward vault from the admin (admin only)
+#[derive(Accounts)]
+pub struct FundRewardVault<'info> {
+    #[account(
+        mut,
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_vault", ico_mint.key().as_ref()],
+        bump,
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub ico_ata_for_admin: Account<'info, TokenAccount>,
+
+    pub ico_mint: Account<'info, Mint>,
+
+    pub admin: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for moving the reward reserve bucket from the ICO vault (admin only)
+#[derive(Accounts)]
+pub struct MoveRewardReserveToVault<'info> {
+    #[account(
+        mut,
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [ico_mint.key().as_ref()],
+        bump,
+    )]
+    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_vault", ico_mint.key().as_ref()],
+        bump,
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
+
+    pub ico_mint: Account<'info, Mint>,
+
+    pub admin: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for reconciling the vault sub-ledgers (admin only)
+#[derive(Accounts)]
+pub struct ReconcileVaultLedger<'info> {
+    #[account(mut)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        seeds = [config.sale_token.as_ref()],
+        bump,
+    )]
+    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
+
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for merging the legacy stake pool ATA into the ICO vault (admin only)
+#[derive(Accounts)]
+pub struct MergeStakePoolVault<'info> {
+    #[account(
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [ico_mint.key().as_ref()],
+        bump,
+    )]
+    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = ico_mint,
+        associated_token::authority = stake_pool_authority,
+    )]
+    pub stake_pool_ata: Account<'info, TokenAccount>,
+
+    /// Stake pool authority PDA
+    /// CHECK: PDA for stake pool
+    #[account(
+        seeds = [b"stake_pool"],
+        bump,
+    )]
+    pub stake_pool_authority: AccountInfo<'info>,
+
+    pub ico_mint: Account<'info, Mint>,
+
+    pub admin: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Admin claim for user - allows admin to claim tokens on behalf of a user
+#[derive(Accounts)]
+pub struct AdminClaimForUser<'info> {
+    #[account(mut)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [b"allocation", user.key().as_ref()],
+        bump,
+    )]
+    pub user_allocation: Account<'info, PresaleAllocation>,
+
+    #[account(mut)]
+    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub ico_ata_for_user: Account<'info, TokenAccount>,
+
+    pub ico_mint: Account<'info, Mint>,
+
+    /// CHECK: The user account to claim for (not a signer - admin is claiming for them)
+    pub user: AccountInfo<'info>,
+
+    /// Admin must be the config admin
+    #[account(
+        mut,
+        constraint = admin.key() == config.admin @ ErrorCode::InvalidAdmin
+    )]
+    pub admin: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Admin add allocation for giveaways/airdrops
+/// Creates or updates allocation without requiring payment
+#[derive(Accounts)]
+pub struct AdminAddAllocation<'info> {
+    /// Config account - contains admin pubkey
+    #[account(
+        mut,
+        seeds = [b"config", admin.key().as_ref()],
+        bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    /// Admin signer - MUST match config.admin
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    /// Recipient wallet (giveaway recipient) - does NOT need to sign
+    /// CHECK: This is just the target wallet address for the giveaway
+    pub recipient: AccountInfo<'info>,
+
+    /// User allocation PDA - created if doesn't exist
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + PresaleAllocation::SPACE,
+        seeds = [b"allocation", recipient.key().as_ref()],
+        bump,
+    )]
+    pub user_allocation: Account<'info, PresaleAllocation>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(tier: StakeTier)]
+pub struct ClaimAndStake<'info> {
+    #[account(
+        mut,
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"user", user.key().as_ref()],
+        bump,
+        space = 8 + UserAccount::SPACE
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"allocation", user.key().as_ref()],
+        bump,
+    )]
+    pub user_allocation: Account<'info, PresaleAllocation>,
+
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"stake", user.key().as_ref(), config.next_stake_id.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + StakeAccount::SPACE
+    )]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    #[account(mut)]
+    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
+
+    pub ico_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterReferrer<'info> {
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"user", user.key().as_ref()],
+        bump,
+        space = 8 + UserAccount::SPACE
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"allocation", user.key().as_ref()],
+        bump,
+        space = 8 + PresaleAllocation::SPACE
+    )]
+    pub user_allocation: Account<'info, PresaleAllocation>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for viewing a referrer's stats
+#[derive(Accounts)]
+pub struct GetReferrerStats<'info> {
+    #[account(
+        seeds = [b"referrer_stats", referrer_stats.referrer.as_ref()],
+        bump,
+    )]
+    pub referrer_stats: Account<'info, ReferrerStats>,
+}
+
+/// Accounts for setting how a referrer receives referral bonuses
+#[derive(Accounts)]
+pub struct SetReferralBonusPreference<'info> {
+    #[account(
+        init_if_needed,
+        payer = referrer,
+        seeds = [b"referrer_stats", referrer.key().as_ref()],
+        bump,
+        space = 8 + ReferrerStats::SPACE
+    )]
+    pub referrer_stats: Account<'info, ReferrerStats>,
+
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for sweeping escrowed referral bonuses into the referrer's allocation
+#[derive(Accounts)]
+pub struct SweepPendingReferralRewards<'info> {
+    #[account(mut)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [b"pending_referral", referrer.key().as_ref()],
+        bump,
+    )]
+    pub pending_referral_rewards: Account<'info, PendingReferralRewards>,
+
+    #[account(
+        init_if_needed,
+        payer = referrer,
+        seeds = [b"allocation", referrer.key().as_ref()],
+        bump,
+        space = 8 + PresaleAllocation::SPACE
+    )]
+    pub referrer_allocation: Account<'info, PresaleAllocation>,
+
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for claiming escrowed referral bonuses as tokens after TGE
+#[derive(Accounts)]
+pub struct ClaimPendingReferralRewards<'info> {
+    #[account(
+        mut,
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [b"pending_referral", referrer.key().as_ref()],
+        bump,
+    )]
+    pub pending_referral_rewards: Account<'info, PendingReferralRewards>,
+
+    #[account(mut)]
+    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub ico_ata_for_referrer: Account<'info, TokenAccount>,
+
+    pub ico_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct StakeTokens<'info> {
+    #[account(
+        mut,
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"user", user.key().as_ref()],
+        bump,
+        space = 8 + UserAccount::SPACE
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    #[account(
+        init,
+        payer = user,
+        seeds = [b"stake", user.key().as_ref(), config.next_stake_id.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + StakeAccount::SPACE
+    )]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    #[account(mut)]
+    pub ico_ata_for_user: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
+
+    pub ico_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ToggleAutoCompound<'info> {
+    #[account(mut)]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    pub user: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct HarvestRewards<'info> {
+    #[account(
+        mut,
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"user", user.key().as_ref()],
+        bump,
+        space = 8 + UserAccount::SPACE
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    #[account(mut)]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    #[account(mut)]
+    pub ico_ata_for_user: Account<'info, TokenAccount>,
+
+    /// ICO vault - receives auto-compounded rewards as staked principal
+    #[account(
+        mut,
+        seeds = [ico_mint.key().as_ref()],
+        bump,
+    )]
+    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
+
+    /// Dedicated staking reward vault - rewards are never paid from the ICO vault
+    #[account(
+        mut,
+        seeds = [b"reward_vault", ico_mint.key().as_ref()],
+        bump,
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
+
+    pub ico_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitiateUnstake<'info> {
+    #[account(
+        mut,
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub config: Account<'info, Config>,
+
+    pub ico_mint: Account<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"user", user.key().as_ref()],
+        bump,
+        space = 8 + UserAccount::SPACE
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    #[account(mut)]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeUnstake<'info> {
+    #[account(
+        mut,
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"user", user.key().as_ref()],
+        bump,
+        space = 8 + UserAccount::SPACE
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    #[account(mut)]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    /// Owner's stake index - the stake is dropped from it once closed (optional)
+    #[account(
+        mut,
+        seeds = [b"stake_index", stake_account.owner.as_ref()],
+        bump,
+    )]
+    pub stake_index: Option<Account<'info, UserStakeIndex>>,
+
+    #[account(mut)]
+    pub ico_ata_for_user: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
+
+    /// Dedicated staking reward vault - rewards are never paid from the ICO vault
+    /// Optional: without it the principal is still returned and all rewards stay pending
+    #[account(
+        mut,
+        seeds = [b"reward_vault", ico_mint.key().as_ref()],
+        bump,
+    )]
+    pub reward_vault: Option<Account<'info, TokenAccount>>,
+
+    pub ico_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for exiting a stake early for a penalty
+#[derive(Accounts)]
+pub struct EarlyUnstake<'info> {
+    #[account(
+        mut,
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"user", user.key().as_ref()],
+        bump,
+        space = 8 + UserAccount::SPACE
+    )]
+    pub user_account: Account<'info, UserAccount>,
+
+    #[account(mut)]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    /// Owner's stake index - the stake is dropped from it once closed (optional)
+    #[account(
+        mut,
+        seeds = [b"stake_index", stake_account.owner.as_ref()],
+        bump,
+    )]
+    pub stake_index: Option<Account<'info, UserStakeIndex>>,
+
+    #[account(mut)]
+    pub ico_ata_for_user: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [ico_mint.key().as_ref()],
+        bump,
+    )]
+    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"reward_vault", ico_mint.key().as_ref()],
+        bump,
+    )]
+    pub reward_vault: Account<'info, TokenAccount>,
+
+    /// Treasury's token account - receives the treasury share of the penalty
+    #[account(
+        mut,
+        constraint = penalty_treasury.owner == config.sol_treasury @ ErrorCode::InvalidAddress,
+        constraint = penalty_treasury.mint == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub penalty_treasury: Account<'info, TokenAccount>,
+
+    /// Mutable for burning the burn share of the penalty
+    #[account(mut)]
+    pub ico_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(mut)]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        constraint = admin.key() == config.admin @ ErrorCode::InvalidAdmin
+    )]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ResizeConfig<'info> {
+    /// CHECK: We're manually handling the reallocation
+    #[account(mut)]
+    pub config: AccountInfo<'info>,
+
+    /// Admin must match the config admin - Note: config is AccountInfo so we validate in function
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for resizing a legacy stake account (permissionless)
+#[derive(Accounts)]
+pub struct ResizeStakeAccount<'info> {
+    #[account(
+        mut,
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub config: Account<'info, Config>,
+
+    pub ico_mint: Account<'info, Mint>,
+
+    /// CHECK: Legacy stake accounts cannot deserialize until resized - discriminator checked in function
+    #[account(mut, owner = crate::ID)]
+    pub stake_account: AccountInfo<'info>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for growing a pre-upgrade presale allocation (admin only)
+#[derive(Accounts)]
+pub struct ResizePresaleAllocation<'info> {
+    pub config: Account<'info, Config>,
 
-    msg!(
-        "{} Vesting Stake complete: {} tokens staked to {:?} tier",
-        coin_name,
-        tokens_to_stake,
-        tier
-    );
+    /// CHECK: Pre-upgrade allocations cannot deserialize until resized - discriminator checked in function
+    #[account(mut, owner = crate::ID)]
+    pub user_allocation: AccountInfo<'info>,
 
-    Ok(())
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
-/// Calculate tokens to allocate based on USD value and current stage
-fn calculate_tokens_for_usd(usd_cents: u64, stage: u8) -> Result<u64> {
-    if stage >= 10 {
-        return Err(ErrorCode::PresaleEnded.into());
-    }
+#[derive(Accounts)]
+pub struct SetBlockStatus<'info> {
+    #[account(mut)]
+    pub config: Account<'info, Config>,
 
-    let price_with_4_decimals = STAGE_PRICES[stage as usize]; // e.g., 1501 = $0.1501
-    
-    // Convert price from 4-decimal format to cents
-    // $0.1501 is stored as 1501 (10000x multiplier for 4 decimals)
-    // To get price in cents: 1501 / 10000 * 100 = 15.01 cents
-    // Simplified: price_cents = price_with_4_decimals / 100
-    
-    // tokens = (usd_cents * TOKEN_DECIMALS * 100) / price_with_4_decimals
-    // Example: $100 at stage 1 ($0.1501)
-    // = (10000 cents * 1e9 * 100) / 1501
-    // = 666,222,518,320,453 raw units = 666,222.52 tokens
-    let tokens = (usd_cents as u128)
-        .checked_mul(TOKEN_DECIMALS as u128)
-        .ok_or(ErrorCode::Overflow)?
-        .checked_mul(100) // Adjust for 4-decimal price format
-        .ok_or(ErrorCode::Overflow)?
-        .checked_div(price_with_4_decimals as u128)
-        .ok_or(ErrorCode::Overflow)?;
+    #[account(
+        init_if_needed,
+        payer = admin,
+        seeds = [b"user", target_user.key().as_ref()],
+        bump,
+        space = 8 + UserAccount::SPACE
+    )]
+    pub user_account: Account<'info, UserAccount>,
 
-    Ok(tokens as u64)
+    /// CHECK: This is the user we're setting block status for
+    pub target_user: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = admin.key() == config.admin @ ErrorCode::InvalidAdmin
+    )]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
-fn calculate_rewards_internal(
-    _config: &Config,
-    stake: &StakeAccount,
-    current_time: i64,
-) -> Result<u64> {
-    require!(stake.active, ErrorCode::StakeNotActive);
+#[derive(Accounts)]
+pub struct WithdrawTokens<'info> {
+    #[account(mut)]
+    pub config: Account<'info, Config>,
 
-    if stake.last_reward_calculation == current_time {
-        return Ok(stake.pending_rewards);
-    }
+    #[account(mut)]
+    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
 
-    let time_elapsed = current_time
-        .checked_sub(stake.last_reward_calculation)
-        .ok_or(ErrorCode::Overflow)?;
+    #[account(mut)]
+    pub ico_ata_for_admin: Account<'info, TokenAccount>,
 
-    // Get APY based on tier
-    let apy = match stake.tier {
-        StakeTier::TierA => APY_TIER_A,
-        StakeTier::TierB => APY_TIER_B,
-        StakeTier::TierC => APY_TIER_C,
-    };
+    pub ico_mint: Account<'info, Mint>,
 
-    // Calculate rewards: (amount * APY * time) / (seconds_per_year * 100)
-    let new_rewards = (stake.amount as u128)
-        .checked_mul(apy as u128)
-        .ok_or(ErrorCode::Overflow)?
-        .checked_mul(time_elapsed as u128)
-        .ok_or(ErrorCode::Overflow)?
-        .checked_div(
-            (SECONDS_PER_YEAR as u128)
-                .checked_mul(100)
-                .ok_or(ErrorCode::Overflow)?,
-        )
-        .ok_or(ErrorCode::Overflow)? as u64;
+    #[account(
+        mut,
+        constraint = admin.key() == config.admin @ ErrorCode::InvalidAdmin
+    )]
+    pub admin: Signer<'info>,
 
-    Ok(stake
-        .pending_rewards
-        .checked_add(new_rewards)
-        .ok_or(ErrorCode::Overflow)?)
+    pub token_program: Program<'info, Token>,
 }
 
 // =====================================================
-// ACCOUNT STRUCTURES
+// TEAM VESTING ACCOUNT STRUCTURES
 // =====================================================
 
+/// Accounts for creating team vesting allocation (admin only)
 #[derive(Accounts)]
-pub struct Initialize<'info> {
-    #[account(
-        init,
-        payer = admin,
-        seeds = [b"config", admin.key().as_ref()],
-        bump,
-        space = 8 + Config::SPACE
-    )]
+pub struct CreateTeamVesting<'info> {
+    #[account(mut)]
     pub config: Account<'info, Config>,
 
+    /// Team vesting account - PDA per team member
     #[account(
         init,
         payer = admin,
-        seeds = [ico_mint.key().as_ref()],
+        seeds = [b"team_vesting", team_member.key().as_ref()],
         bump,
-        token::mint = ico_mint,
-        token::authority = ico_ata_for_ico_program,
+        space = 8 + TeamVesting::SPACE
     )]
-    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
-
-    pub ico_mint: Account<'info, Mint>,
+    pub team_vesting: Account<'info, TeamVesting>,
 
-    #[account(mut)]
-    pub ico_ata_for_admin: Account<'info, TokenAccount>,
+    /// CHECK: Team member wallet address
+    pub team_member: AccountInfo<'info>,
 
-    #[account(mut)]
+    #[account(
+        mut,
+        constraint = admin.key() == config.admin @ ErrorCode::InvalidAdmin
+    )]
     pub admin: Signer<'info>,
 
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
+/// Accounts for claiming team tokens after 18-month lockup
 #[derive(Accounts)]
-pub struct PresalePurchaseWithSol<'info> {
+pub struct ClaimTeamTokens<'info> {
     #[account(mut)]
     pub config: Account<'info, Config>,
 
     #[account(
-        init_if_needed,
-        payer = user,
-        seeds = [b"user", user.key().as_ref()],
+        mut,
+        seeds = [b"team_vesting", team_member.key().as_ref()],
         bump,
-        space = 8 + UserAccount::SPACE
     )]
-    pub user_account: Account<'info, UserAccount>,
+    pub team_vesting: Account<'info, TeamVesting>,
 
-    #[account(
-        init_if_needed,
-        payer = user,
-        seeds = [b"allocation", user.key().as_ref()],
-        bump,
-        space = 8 + PresaleAllocation::SPACE
-    )]
-    pub user_allocation: Account<'info, PresaleAllocation>,
+    /// Program's token treasury
+    #[account(mut)]
+    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
 
-    /// CHECK: Optional referrer allocation account - validated manually in function
+    /// Team member's token account to receive tokens
     #[account(mut)]
-    pub referrer_allocation: UncheckedAccount<'info>,
+    pub team_member_ata: Account<'info, TokenAccount>,
 
-    /// Pyth SOL/USD price account
-    pub pyth_sol_usd_price: Account<'info, PriceUpdateV2>,
+    pub ico_mint: Account<'info, Mint>,
 
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub team_member: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for viewing team vesting status
+#[derive(Accounts)]
+pub struct GetTeamVestingStatus<'info> {
+    pub config: Account<'info, Config>,
 
-    /// CHECK: SOL Treasury receives SOL payments - VALIDATED against config.sol_treasury
     #[account(
-        mut,
-        constraint = sol_treasury.key() == config.sol_treasury @ ErrorCode::InvalidAdmin
+        seeds = [b"team_vesting", team_vesting.member.as_ref()],
+        bump,
     )]
-    pub sol_treasury: AccountInfo<'info>,
-
-    pub system_program: Program<'info, System>,
+    pub team_vesting: Account<'info, TeamVesting>,
 }
 
-/// Account struct for presale purchase with automatic vesting stake
+// =====================================================
+// CROSS-CHAIN ACCOUNT STRUCTURES
+// =====================================================
+
+/// Accounts for coordinator-initiated transfer and vesting stake for EVM buyers
 #[derive(Accounts)]
-#[instruction(sol_amount: u64, tier: StakeTier)]
-pub struct PresalePurchaseAndVestStake<'info> {
-    #[account(mut)]
+#[instruction(purchase: CrossChainPurchase)]
+pub struct CoordinatorMintAndVestStake<'info> {
+    #[account(
+        mut,
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
     pub config: Account<'info, Config>,
 
+    pub ico_mint: Account<'info, Mint>,
+
+    /// Supported chain registry entry
     #[account(
-        init_if_needed,
-        payer = user,
-        seeds = [b"user", user.key().as_ref()],
+        mut,
+        seeds = [b"supported_chain".as_ref(), config.key().as_ref(), &purchase.chain_id.to_le_bytes()],
         bump,
-        space = 8 + UserAccount::SPACE
     )]
-    pub user_account: Account<'info, UserAccount>,
+    pub supported_chain: Account<'info, SupportedChain>,
 
+    /// Replay protection record for the EVM purchase event
     #[account(
         init_if_needed,
-        payer = user,
-        seeds = [b"allocation", user.key().as_ref()],
+        payer = coordinator,
+        seeds = [b"processed_tx".as_ref(), &purchase.chain_id.to_le_bytes(), purchase.tx_hash.as_ref(), &purchase.log_index.to_le_bytes()],
         bump,
-        space = 8 + PresaleAllocation::SPACE
+        space = 8 + ProcessedCrossChainTx::SPACE
     )]
-    pub user_allocation: Account<'info, PresaleAllocation>,
+    pub processed_tx: Account<'info, ProcessedCrossChainTx>,
 
-    /// Stake account - PDA per user per tier (max 3 accounts per user)
-    /// Seeds: ["vesting_stake", user_pubkey, tier_byte]
+    /// The cross-chain allocation to track this purchase
     #[account(
         init_if_needed,
-        payer = user,
-        seeds = [b"vesting_stake", user.key().as_ref(), &[tier as u8]],
+        payer = coordinator,
+        seeds = [b"cross_chain".as_ref(), purchase.buyer_eth_address.as_ref(), &purchase.chain_id.to_le_bytes()],
         bump,
-        space = 8 + StakeAccount::SPACE
+        space = 8 + CrossChainAllocation::SPACE
     )]
-    pub stake_account: Account<'info, StakeAccount>,
+    pub cross_chain_allocation: Account<'info, CrossChainAllocation>,
 
-    /// Pyth SOL/USD price account
-    pub pyth_sol_usd_price: Account<'info, PriceUpdateV2>,
+    /// Referrer bonus ledger - required when referrer_eth is set
+    #[account(
+        init_if_needed,
+        payer = coordinator,
+        seeds = [b"cross_chain_referral".as_ref(), purchase.referrer_eth.as_ref(), &purchase.chain_id.to_le_bytes()],
+        bump,
+        space = 8 + CrossChainReferral::SPACE
+    )]
+    pub referrer_cross_chain_referral: Option<Account<'info, CrossChainReferral>>,
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+    /// The Solana wallet that will own the staked tokens
+    /// CHECK: This is the beneficiary's Solana address provided by coordinator
+    pub beneficiary: AccountInfo<'info>,
 
-    /// CHECK: SOL Treasury receives SOL payments - VALIDATED against config.sol_treasury
+    /// Stake account - one PDA per vesting stake, same scheme as stake_tokens
     #[account(
-        mut,
-        constraint = sol_treasury.key() == config.sol_treasury @ ErrorCode::InvalidAdmin
+        init,
+        payer = coordinator,
+        seeds = [b"stake", beneficiary.key().as_ref(), config.next_stake_id.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + StakeAccount::SPACE
     )]
-    pub sol_treasury: AccountInfo<'info>,
-
-    pub system_program: Program<'info, System>,
-}
+    pub stake_account: Account<'info, StakeAccount>,
 
-#[derive(Accounts)]
-pub struct PresalePurchaseWithStablecoin<'info> {
-    #[account(mut)]
-    pub config: Account<'info, Config>,
+    /// Links the stake to the EVM buyer for EVM-signed stake management
+    #[account(
+        init,
+        payer = coordinator,
+        seeds = [b"evm_stake", stake_account.key().as_ref()],
+        bump,
+        space = 8 + EvmStakeLink::SPACE
+    )]
+    pub evm_stake_link: Account<'info, EvmStakeLink>,
 
+    /// Beneficiary's stake index - lists coordinator-created stakes
     #[account(
         init_if_needed,
-        payer = user,
-        seeds = [b"user", user.key().as_ref()],
+        payer = coordinator,
+        seeds = [b"stake_index", beneficiary.key().as_ref()],
         bump,
-        space = 8 + UserAccount::SPACE
+        space = 8 + UserStakeIndex::SPACE
     )]
-    pub user_account: Account<'info, UserAccount>,
+    pub beneficiary_stake_index: Account<'info, UserStakeIndex>,
 
+    /// Beneficiary's user account - blocked check and unique staker counting
     #[account(
         init_if_needed,
-        payer = user,
-        seeds = [b"allocation", user.key().as_ref()],
+        payer = coordinator,
+        seeds = [b"user", beneficiary.key().as_ref()],
         bump,
-        space = 8 + PresaleAllocation::SPACE
+        space = 8 + UserAccount::SPACE
     )]
-    pub user_allocation: Account<'info, PresaleAllocation>,
-
-    /// CHECK: Optional referrer allocation account - validated manually in function
-    #[account(mut)]
-    pub referrer_allocation: UncheckedAccount<'info>,
-
-    #[account(mut)]
-    pub stablecoin_ata_for_user: Account<'info, TokenAccount>,
-
-    #[account(mut)]
-    pub stablecoin_ata_for_admin: Account<'info, TokenAccount>,
+    pub beneficiary_user_account: Account<'info, UserAccount>,
 
-    pub stablecoin_mint: Account<'info, Mint>,
+    /// Audit stats of the signing coordinator
+    #[account(
+        init_if_needed,
+        payer = coordinator,
+        seeds = [b"coordinator_stats", coordinator.key().as_ref()],
+        bump,
+        space = 8 + CoordinatorStats::SPACE
+    )]
+    pub coordinator_stats: Account<'info, CoordinatorStats>,
 
+    /// Coordinator signer
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub coordinator: Signer<'info>,
 
-    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
-/// Account struct for presale purchase with stablecoin (USDT/USDC) and automatic vesting stake
-/// Uses PDA per user per tier (max 3 stake accounts per user)
+/// Accounts for recording a non-EVM purchase (coordinator only)
 #[derive(Accounts)]
-#[instruction(stablecoin_amount: u64, tier: StakeTier)]
-pub struct PresalePurchaseStablecoinAndVestStake<'info> {
+#[instruction(purchase: ForeignChainPurchase)]
+pub struct RecordForeignChainPurchase<'info> {
     #[account(mut)]
     pub config: Account<'info, Config>,
 
+    /// Supported chain registry entry
+    #[account(
+        mut,
+        seeds = [b"supported_chain".as_ref(), config.key().as_ref(), &purchase.chain_id.to_le_bytes()],
+        bump,
+    )]
+    pub supported_chain: Account<'info, SupportedChain>,
+
+    /// Replay protection record for the purchase event
     #[account(
         init_if_needed,
-        payer = user,
-        seeds = [b"user", user.key().as_ref()],
+        payer = coordinator,
+        seeds = [b"processed_tx".as_ref(), &purchase.chain_id.to_le_bytes(), purchase.tx_hash.as_ref(), &purchase.log_index.to_le_bytes()],
         bump,
-        space = 8 + UserAccount::SPACE
+        space = 8 + ProcessedCrossChainTx::SPACE
     )]
-    pub user_account: Account<'info, UserAccount>,
+    pub processed_tx: Account<'info, ProcessedCrossChainTx>,
 
     #[account(
         init_if_needed,
-        payer = user,
-        seeds = [b"allocation", user.key().as_ref()],
+        payer = coordinator,
+        seeds = [b"foreign_allocation".as_ref(), config.key().as_ref(), purchase.address_hash.as_ref(), &purchase.chain_id.to_le_bytes()],
         bump,
-        space = 8 + PresaleAllocation::SPACE
+        space = 8 + ForeignCrossChainAllocation::SPACE
     )]
-    pub user_allocation: Account<'info, PresaleAllocation>,
+    pub foreign_allocation: Account<'info, ForeignCrossChainAllocation>,
 
-    /// Stake account - PDA per user per tier (max 3 accounts per user)
-    /// Seeds: ["vesting_stake", user_pubkey, tier_byte]
+    /// Audit stats of the signing coordinator
     #[account(
         init_if_needed,
-        payer = user,
-        seeds = [b"vesting_stake", user.key().as_ref(), &[tier as u8]],
+        payer = coordinator,
+        seeds = [b"coordinator_stats", coordinator.key().as_ref()],
         bump,
-        space = 8 + StakeAccount::SPACE
+        space = 8 + CoordinatorStats::SPACE
     )]
-    pub stake_account: Account<'info, StakeAccount>,
+    pub coordinator_stats: Account<'info, CoordinatorStats>,
 
     #[account(mut)]
-    pub stablecoin_ata_for_user: Account<'info, TokenAccount>,
+    pub coordinator: Signer<'info>,
 
-    #[account(mut)]
-    pub stablecoin_ata_for_admin: Account<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+}
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+/// Accounts for linking a wallet to a foreign-address allocation (admin only)
+#[derive(Accounts)]
+#[instruction(address_hash: [u8; 32], chain_id: u64)]
+pub struct LinkForeignAllocationWallet<'info> {
+    pub config: Account<'info, Config>,
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [b"foreign_allocation".as_ref(), config.key().as_ref(), address_hash.as_ref(), &chain_id.to_le_bytes()],
+        bump,
+    )]
+    pub foreign_allocation: Account<'info, ForeignCrossChainAllocation>,
+
+    pub admin: Signer<'info>,
 }
 
+/// Accounts for claiming a foreign-address allocation at TGE
 #[derive(Accounts)]
-pub struct ClaimPresaleAllocation<'info> {
-    #[account(mut)]
+pub struct ClaimForeignAllocation<'info> {
+    #[account(
+        mut,
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
     pub config: Account<'info, Config>,
 
     #[account(
         mut,
-        seeds = [b"allocation", user.key().as_ref()],
+        seeds = [
+            b"foreign_allocation".as_ref(),
+            config.key().as_ref(),
+            foreign_allocation.address_hash.as_ref(),
+            &foreign_allocation.chain_id.to_le_bytes(),
+        ],
         bump,
     )]
-    pub user_allocation: Account<'info, PresaleAllocation>,
+    pub foreign_allocation: Account<'info, ForeignCrossChainAllocation>,
 
-    #[account(mut)]
+    #[account(
+        mut,
+        seeds = [ico_mint.key().as_ref()],
+        bump,
+    )]
     pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
 
     #[account(mut)]
@@ -3074,236 +7684,354 @@ pub struct ClaimPresaleAllocation<'info> {
     pub token_program: Program<'info, Token>,
 }
 
-/// Admin claim for user - allows admin to claim tokens on behalf of a user
+/// Accounts for resetting the cross-chain circuit breaker (admin only)
 #[derive(Accounts)]
-pub struct AdminClaimForUser<'info> {
+pub struct ResetCrossChainCircuitBreaker<'info> {
     #[account(mut)]
     pub config: Account<'info, Config>,
 
+    /// Optional chain whose usage window should also be cleared
     #[account(
         mut,
-        seeds = [b"allocation", user.key().as_ref()],
+        seeds = [b"supported_chain".as_ref(), config.key().as_ref(), &supported_chain.chain_id.to_le_bytes()],
         bump,
     )]
-    pub user_allocation: Account<'info, PresaleAllocation>,
+    pub supported_chain: Option<Account<'info, SupportedChain>>,
 
-    #[account(mut)]
-    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
+    pub admin: Signer<'info>,
+}
 
+/// Accounts for reversing a recorded cross-chain purchase (coordinator or admin)
+#[derive(Accounts)]
+#[instruction(chain_id: u64, tx_hash: [u8; 32], log_index: u32)]
+pub struct ReverseCrossChainPurchase<'info> {
     #[account(mut)]
-    pub ico_ata_for_user: Account<'info, TokenAccount>,
+    pub config: Account<'info, Config>,
 
-    pub ico_mint: Account<'info, Mint>,
+    #[account(
+        mut,
+        seeds = [b"processed_tx".as_ref(), &chain_id.to_le_bytes(), tx_hash.as_ref(), &log_index.to_le_bytes()],
+        bump,
+    )]
+    pub processed_tx: Account<'info, ProcessedCrossChainTx>,
 
-    /// CHECK: The user account to claim for (not a signer - admin is claiming for them)
-    pub user: AccountInfo<'info>,
+    #[account(
+        mut,
+        seeds = [b"cross_chain".as_ref(), processed_tx.buyer_eth_address.as_ref(), &chain_id.to_le_bytes()],
+        bump,
+    )]
+    pub cross_chain_allocation: Account<'info, CrossChainAllocation>,
 
-    /// Admin must be the config admin
+    /// Chain registry entry - the reversed USD is taken back out of its rolling usage
     #[account(
         mut,
-        constraint = admin.key() == config.admin @ ErrorCode::InvalidAdmin
+        seeds = [b"supported_chain".as_ref(), config.key().as_ref(), &chain_id.to_le_bytes()],
+        bump,
     )]
-    pub admin: Signer<'info>,
+    pub supported_chain: Account<'info, SupportedChain>,
 
-    pub token_program: Program<'info, Token>,
+    /// Referrer bonus ledger - required when the purchase was referred
+    #[account(
+        mut,
+        seeds = [b"cross_chain_referral".as_ref(), cross_chain_allocation.referrer_eth.as_ref(), &chain_id.to_le_bytes()],
+        bump,
+    )]
+    pub referrer_cross_chain_referral: Option<Account<'info, CrossChainReferral>>,
+
+    /// Coordinator or admin
+    pub authority: Signer<'info>,
 }
 
-/// Admin add allocation for giveaways/airdrops
-/// Creates or updates allocation without requiring payment
+/// Accounts for EVM-signed management of a coordinator-created stake (relayer-paid)
 #[derive(Accounts)]
-pub struct AdminAddAllocation<'info> {
-    /// Config account - contains admin pubkey
+pub struct EvmManageStake<'info> {
     #[account(
         mut,
-        seeds = [b"config", admin.key().as_ref()],
-        bump,
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
     )]
     pub config: Account<'info, Config>,
 
-    /// Admin signer - MUST match config.admin
+    pub ico_mint: Account<'info, Mint>,
+
     #[account(mut)]
-    pub admin: Signer<'info>,
+    pub stake_account: Account<'info, StakeAccount>,
 
-    /// Recipient wallet (giveaway recipient) - does NOT need to sign
-    /// CHECK: This is just the target wallet address for the giveaway
-    pub recipient: AccountInfo<'info>,
+    #[account(
+        seeds = [b"evm_stake", stake_account.key().as_ref()],
+        bump,
+    )]
+    pub evm_stake_link: Account<'info, EvmStakeLink>,
+
+    /// Allocation of the EVM buyer - its eth_address must have signed the action
+    #[account(
+        seeds = [b"cross_chain".as_ref(), evm_stake_link.eth_address.as_ref(), &evm_stake_link.chain_id.to_le_bytes()],
+        bump,
+    )]
+    pub cross_chain_allocation: Account<'info, CrossChainAllocation>,
 
-    /// User allocation PDA - created if doesn't exist
     #[account(
         init_if_needed,
-        payer = admin,
-        space = 8 + PresaleAllocation::SPACE,
-        seeds = [b"allocation", recipient.key().as_ref()],
+        payer = relayer,
+        seeds = [b"evm_link_nonce", evm_stake_link.eth_address.as_ref()],
         bump,
+        space = 8 + EvmLinkNonce::SPACE
     )]
-    pub user_allocation: Account<'info, PresaleAllocation>,
+    pub evm_link_nonce: Account<'info, EvmLinkNonce>,
+
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        seeds = [b"user", stake_account.owner.as_ref()],
+        bump,
+        space = 8 + UserAccount::SPACE
+    )]
+    pub beneficiary_user_account: Account<'info, UserAccount>,
+
+    #[account(mut)]
+    pub relayer: Signer<'info>,
 
     pub system_program: Program<'info, System>,
 }
 
+/// Accounts for EVM-signed unstake finalization (relayer-paid)
 #[derive(Accounts)]
-#[instruction(tier: StakeTier)]
-pub struct ClaimAndStake<'info> {
-    #[account(mut)]
+pub struct EvmFinalizeUnstake<'info> {
+    #[account(
+        mut,
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
     pub config: Account<'info, Config>,
 
+    #[account(mut)]
+    pub stake_account: Account<'info, StakeAccount>,
+
+    /// Owner's stake index - the stake is dropped from it once closed (optional)
     #[account(
-        init_if_needed,
-        payer = user,
-        seeds = [b"user", user.key().as_ref()],
+        mut,
+        seeds = [b"stake_index", stake_account.owner.as_ref()],
         bump,
-        space = 8 + UserAccount::SPACE
     )]
-    pub user_account: Account<'info, UserAccount>,
+    pub stake_index: Option<Account<'info, UserStakeIndex>>,
 
     #[account(
-        mut,
-        seeds = [b"allocation", user.key().as_ref()],
+        seeds = [b"evm_stake", stake_account.key().as_ref()],
         bump,
     )]
-    pub user_allocation: Account<'info, PresaleAllocation>,
+    pub evm_stake_link: Account<'info, EvmStakeLink>,
 
+    /// Allocation of the EVM buyer - its eth_address must have signed the action
     #[account(
-        init,
-        payer = user,
-        seeds = [b"stake", user.key().as_ref(), config.next_stake_id.to_le_bytes().as_ref()],
+        seeds = [b"cross_chain".as_ref(), evm_stake_link.eth_address.as_ref(), &evm_stake_link.chain_id.to_le_bytes()],
         bump,
-        space = 8 + StakeAccount::SPACE
     )]
-    pub stake_account: Account<'info, StakeAccount>,
+    pub cross_chain_allocation: Account<'info, CrossChainAllocation>,
+
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        seeds = [b"evm_link_nonce", evm_stake_link.eth_address.as_ref()],
+        bump,
+        space = 8 + EvmLinkNonce::SPACE
+    )]
+    pub evm_link_nonce: Account<'info, EvmLinkNonce>,
+
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        seeds = [b"user", stake_account.owner.as_ref()],
+        bump,
+        space = 8 + UserAccount::SPACE
+    )]
+    pub beneficiary_user_account: Account<'info, UserAccount>,
+
+    /// Destination must belong to the stake's beneficiary
+    #[account(
+        mut,
+        constraint = ico_ata_for_beneficiary.owner == stake_account.owner @ ErrorCode::InvalidAddress,
+        constraint = ico_ata_for_beneficiary.mint == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub ico_ata_for_beneficiary: Account<'info, TokenAccount>,
 
     #[account(mut)]
     pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
 
+    /// Dedicated staking reward vault - rewards are never paid from the ICO vault
+    /// Optional: without it the principal is still returned and all rewards stay pending
+    #[account(
+        mut,
+        seeds = [b"reward_vault", ico_mint.key().as_ref()],
+        bump,
+    )]
+    pub reward_vault: Option<Account<'info, TokenAccount>>,
+
     pub ico_mint: Account<'info, Mint>,
 
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub relayer: Signer<'info>,
 
     pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
+/// Accounts for an EVM-signed beneficiary change (relayer-paid)
 #[derive(Accounts)]
-pub struct RegisterReferrer<'info> {
+#[instruction(new_beneficiary: Pubkey)]
+pub struct EvmChangeStakeBeneficiary<'info> {
+    #[account(mut)]
+    pub stake_account: Account<'info, StakeAccount>,
+
     #[account(
-        init_if_needed,
-        payer = user,
-        seeds = [b"user", user.key().as_ref()],
+        seeds = [b"evm_stake", stake_account.key().as_ref()],
         bump,
-        space = 8 + UserAccount::SPACE
     )]
-    pub user_account: Account<'info, UserAccount>,
+    pub evm_stake_link: Account<'info, EvmStakeLink>,
 
+    /// Allocation of the EVM buyer - its eth_address must have signed the action
     #[account(
-        init_if_needed,
-        payer = user,
-        seeds = [b"allocation", user.key().as_ref()],
+        seeds = [b"cross_chain".as_ref(), evm_stake_link.eth_address.as_ref(), &evm_stake_link.chain_id.to_le_bytes()],
         bump,
-        space = 8 + PresaleAllocation::SPACE
     )]
-    pub user_allocation: Account<'info, PresaleAllocation>,
-
-    #[account(mut)]
-    pub user: Signer<'info>,
-
-    pub system_program: Program<'info, System>,
-}
+    pub cross_chain_allocation: Account<'info, CrossChainAllocation>,
 
-#[derive(Accounts)]
-pub struct StakeTokens<'info> {
-    #[account(mut)]
-    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = relayer,
+        seeds = [b"evm_link_nonce", evm_stake_link.eth_address.as_ref()],
+        bump,
+        space = 8 + EvmLinkNonce::SPACE
+    )]
+    pub evm_link_nonce: Account<'info, EvmLinkNonce>,
 
     #[account(
         init_if_needed,
-        payer = user,
-        seeds = [b"user", user.key().as_ref()],
+        payer = relayer,
+        seeds = [b"stake_index", stake_account.owner.as_ref()],
         bump,
-        space = 8 + UserAccount::SPACE
+        space = 8 + UserStakeIndex::SPACE
     )]
-    pub user_account: Account<'info, UserAccount>,
+    pub old_stake_index: Account<'info, UserStakeIndex>,
 
     #[account(
-        init,
-        payer = user,
-        seeds = [b"stake", user.key().as_ref(), config.next_stake_id.to_le_bytes().as_ref()],
+        init_if_needed,
+        payer = relayer,
+        seeds = [b"stake_index", new_beneficiary.as_ref()],
         bump,
-        space = 8 + StakeAccount::SPACE
+        space = 8 + UserStakeIndex::SPACE
     )]
-    pub stake_account: Account<'info, StakeAccount>,
+    pub new_stake_index: Account<'info, UserStakeIndex>,
 
     #[account(mut)]
-    pub ico_ata_for_user: Account<'info, TokenAccount>,
+    pub relayer: Signer<'info>,
 
-    #[account(mut)]
-    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for registering a legacy keypair stake in its owner's stake index
+#[derive(Accounts)]
+pub struct RegisterLegacyStake<'info> {
+    #[account(
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub config: Account<'info, Config>,
 
     pub ico_mint: Account<'info, Mint>,
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+    pub stake_account: Account<'info, StakeAccount>,
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-}
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [b"stake_index", stake_account.owner.as_ref()],
+        bump,
+        space = 8 + UserStakeIndex::SPACE
+    )]
+    pub stake_index: Account<'info, UserStakeIndex>,
 
-#[derive(Accounts)]
-pub struct ToggleAutoCompound<'info> {
+    /// Stake owner or admin
     #[account(mut)]
-    pub stake_account: Account<'info, StakeAccount>,
+    pub authority: Signer<'info>,
 
-    pub user: Signer<'info>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct HarvestRewards<'info> {
+#[instruction(purchase: CrossChainPurchase)]
+pub struct RecordCrossChainPurchase<'info> {
     #[account(mut)]
     pub config: Account<'info, Config>,
 
+    /// Supported chain registry entry
     #[account(
-        init_if_needed,
-        payer = user,
-        seeds = [b"user", user.key().as_ref()],
+        mut,
+        seeds = [b"supported_chain".as_ref(), config.key().as_ref(), &purchase.chain_id.to_le_bytes()],
         bump,
-        space = 8 + UserAccount::SPACE
     )]
-    pub user_account: Account<'info, UserAccount>,
+    pub supported_chain: Account<'info, SupportedChain>,
 
-    #[account(mut)]
-    pub stake_account: Account<'info, StakeAccount>,
+    /// Replay protection record for the EVM purchase event
+    #[account(
+        init_if_needed,
+        payer = coordinator,
+        seeds = [b"processed_tx".as_ref(), &purchase.chain_id.to_le_bytes(), purchase.tx_hash.as_ref(), &purchase.log_index.to_le_bytes()],
+        bump,
+        space = 8 + ProcessedCrossChainTx::SPACE
+    )]
+    pub processed_tx: Account<'info, ProcessedCrossChainTx>,
 
-    #[account(mut)]
-    pub ico_ata_for_user: Account<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = coordinator,
+        seeds = [b"cross_chain".as_ref(), purchase.buyer_eth_address.as_ref(), &purchase.chain_id.to_le_bytes()],
+        bump,
+        space = 8 + CrossChainAllocation::SPACE
+    )]
+    pub cross_chain_allocation: Account<'info, CrossChainAllocation>,
 
-    #[account(mut)]
-    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
+    /// Referrer bonus ledger - required when referrer_eth is set
+    #[account(
+        init_if_needed,
+        payer = coordinator,
+        seeds = [b"cross_chain_referral".as_ref(), purchase.referrer_eth.as_ref(), &purchase.chain_id.to_le_bytes()],
+        bump,
+        space = 8 + CrossChainReferral::SPACE
+    )]
+    pub referrer_cross_chain_referral: Option<Account<'info, CrossChainReferral>>,
 
-    pub ico_mint: Account<'info, Mint>,
+    /// Audit stats of the signing coordinator
+    #[account(
+        init_if_needed,
+        payer = coordinator,
+        seeds = [b"coordinator_stats", coordinator.key().as_ref()],
+        bump,
+        space = 8 + CoordinatorStats::SPACE
+    )]
+    pub coordinator_stats: Account<'info, CoordinatorStats>,
 
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub coordinator: Signer<'info>,
 
-    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct InitiateUnstake<'info> {
-    #[account(mut)]
-    pub config: Account<'info, Config>,
+#[instruction(eth_address: [u8; 20], chain_id: u64)]
+pub struct LinkSolanaWallet<'info> {
+    #[account(
+        mut,
+        seeds = [b"cross_chain".as_ref(), eth_address.as_ref(), &chain_id.to_le_bytes()],
+        bump,
+    )]
+    pub cross_chain_allocation: Account<'info, CrossChainAllocation>,
 
+    /// Link nonce for the ETH address - incremented on every successful link
     #[account(
         init_if_needed,
         payer = user,
-        seeds = [b"user", user.key().as_ref()],
+        seeds = [b"evm_link_nonce", eth_address.as_ref()],
         bump,
-        space = 8 + UserAccount::SPACE
+        space = 8 + EvmLinkNonce::SPACE
     )]
-    pub user_account: Account<'info, UserAccount>,
-
-    #[account(mut)]
-    pub stake_account: Account<'info, StakeAccount>,
+    pub evm_link_nonce: Account<'info, EvmLinkNonce>,
 
     #[account(mut)]
     pub user: Signer<'info>,
@@ -3311,292 +8039,306 @@ pub struct InitiateUnstake<'info> {
     pub system_program: Program<'info, System>,
 }
 
+/// Accounts for setting the coordinator committee (admin only)
 #[derive(Accounts)]
-pub struct FinalizeUnstake<'info> {
+pub struct SetCoordinatorCommittee<'info> {
     #[account(mut)]
     pub config: Account<'info, Config>,
 
     #[account(
         init_if_needed,
-        payer = user,
-        seeds = [b"user", user.key().as_ref()],
+        payer = admin,
+        seeds = [b"coordinator_committee", config.key().as_ref()],
         bump,
-        space = 8 + UserAccount::SPACE
+        space = 8 + CoordinatorCommittee::SPACE
     )]
-    pub user_account: Account<'info, UserAccount>,
-
-    #[account(mut)]
-    pub stake_account: Account<'info, StakeAccount>,
-
-    #[account(mut)]
-    pub ico_ata_for_user: Account<'info, TokenAccount>,
-
-    #[account(mut)]
-    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
-
-    pub ico_mint: Account<'info, Mint>,
+    pub coordinator_committee: Account<'info, CoordinatorCommittee>,
 
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub admin: Signer<'info>,
 
-    pub token_program: Program<'info, Token>,
     pub system_program: Program<'info, System>,
 }
 
+/// Accounts for registering a Wormhole emitter (admin only)
 #[derive(Accounts)]
-pub struct UpdateConfig<'info> {
-    #[account(mut)]
+#[instruction(chain_id: u64)]
+pub struct RegisterWormholeEmitter<'info> {
     pub config: Account<'info, Config>,
 
     #[account(
         mut,
-        constraint = admin.key() == config.admin @ ErrorCode::InvalidAdmin
+        seeds = [b"supported_chain".as_ref(), config.key().as_ref(), &chain_id.to_le_bytes()],
+        bump,
     )]
-    pub admin: Signer<'info>,
-}
-
-#[derive(Accounts)]
-pub struct ResizeConfig<'info> {
-    /// CHECK: We're manually handling the reallocation
-    #[account(mut)]
-    pub config: AccountInfo<'info>,
+    pub supported_chain: Account<'info, SupportedChain>,
 
-    /// Admin must match the config admin - Note: config is AccountInfo so we validate in function
-    #[account(mut)]
     pub admin: Signer<'info>,
-
-    pub system_program: Program<'info, System>,
 }
 
+/// Accounts for adding or updating a supported chain (admin only)
 #[derive(Accounts)]
-pub struct SetBlockStatus<'info> {
-    #[account(mut)]
+#[instruction(chain_id: u64)]
+pub struct SetSupportedChain<'info> {
     pub config: Account<'info, Config>,
 
     #[account(
         init_if_needed,
         payer = admin,
-        seeds = [b"user", target_user.key().as_ref()],
+        seeds = [b"supported_chain".as_ref(), config.key().as_ref(), &chain_id.to_le_bytes()],
         bump,
-        space = 8 + UserAccount::SPACE
+        space = 8 + SupportedChain::SPACE
     )]
-    pub user_account: Account<'info, UserAccount>,
-
-    /// CHECK: This is the user we're setting block status for
-    pub target_user: AccountInfo<'info>,
+    pub supported_chain: Account<'info, SupportedChain>,
 
-    #[account(
-        mut,
-        constraint = admin.key() == config.admin @ ErrorCode::InvalidAdmin
-    )]
+    #[account(mut)]
     pub admin: Signer<'info>,
 
     pub system_program: Program<'info, System>,
 }
 
+/// Accounts for migrating a legacy (u8 chain id) cross-chain allocation (admin only)
 #[derive(Accounts)]
-pub struct WithdrawTokens<'info> {
-    #[account(mut)]
+#[instruction(eth_address: [u8; 20], legacy_chain_id: u8)]
+pub struct MigrateCrossChainAllocation<'info> {
     pub config: Account<'info, Config>,
 
-    #[account(mut)]
-    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
-
-    #[account(mut)]
-    pub ico_ata_for_admin: Account<'info, TokenAccount>,
-
-    pub ico_mint: Account<'info, Mint>,
-
+    /// CHECK: Legacy allocation - owner, discriminator and contents validated in function
     #[account(
         mut,
-        constraint = admin.key() == config.admin @ ErrorCode::InvalidAdmin
+        seeds = [b"cross_chain".as_ref(), eth_address.as_ref(), &[legacy_chain_id]],
+        bump,
     )]
-    pub admin: Signer<'info>,
-
-    pub token_program: Program<'info, Token>,
-}
-
-// =====================================================
-// TEAM VESTING ACCOUNT STRUCTURES
-// =====================================================
-
-/// Accounts for creating team vesting allocation (admin only)
-#[derive(Accounts)]
-pub struct CreateTeamVesting<'info> {
-    #[account(mut)]
-    pub config: Account<'info, Config>,
+    pub legacy_allocation: UncheckedAccount<'info>,
 
-    /// Team vesting account - PDA per team member
     #[account(
         init,
         payer = admin,
-        seeds = [b"team_vesting", team_member.key().as_ref()],
+        seeds = [b"cross_chain".as_ref(), eth_address.as_ref(), &(legacy_chain_id as u64).to_le_bytes()],
         bump,
-        space = 8 + TeamVesting::SPACE
+        space = 8 + CrossChainAllocation::SPACE
     )]
-    pub team_vesting: Account<'info, TeamVesting>,
-
-    /// CHECK: Team member wallet address
-    pub team_member: AccountInfo<'info>,
+    pub cross_chain_allocation: Account<'info, CrossChainAllocation>,
 
-    #[account(
-        mut,
-        constraint = admin.key() == config.admin @ ErrorCode::InvalidAdmin
-    )]
+    #[account(mut)]
     pub admin: Signer<'info>,
 
     pub system_program: Program<'info, System>,
 }
 
-/// Accounts for claiming team tokens after 18-month lockup
+/// Accounts for recording a cross-chain purchase from a posted Wormhole VAA
 #[derive(Accounts)]
-pub struct ClaimTeamTokens<'info> {
+#[instruction(purchase: CrossChainPurchase)]
+pub struct RecordCrossChainPurchaseFromVaa<'info> {
     #[account(mut)]
     pub config: Account<'info, Config>,
 
+    /// Supported chain registry entry (holds the registered Wormhole emitter)
     #[account(
         mut,
-        seeds = [b"team_vesting", team_member.key().as_ref()],
+        seeds = [b"supported_chain".as_ref(), config.key().as_ref(), &purchase.chain_id.to_le_bytes()],
         bump,
     )]
-    pub team_vesting: Account<'info, TeamVesting>,
+    pub supported_chain: Account<'info, SupportedChain>,
 
-    /// Program's token treasury
-    #[account(mut)]
-    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
+    /// CHECK: Core Bridge PostedVAA - owner, discriminator and emitter validated in function
+    pub posted_vaa: UncheckedAccount<'info>,
 
-    /// Team member's token account to receive tokens
-    #[account(mut)]
-    pub team_member_ata: Account<'info, TokenAccount>,
+    /// Replay protection record for the EVM purchase event (shared with the coordinator paths)
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [b"processed_tx".as_ref(), &purchase.chain_id.to_le_bytes(), purchase.tx_hash.as_ref(), &purchase.log_index.to_le_bytes()],
+        bump,
+        space = 8 + ProcessedCrossChainTx::SPACE
+    )]
+    pub processed_tx: Account<'info, ProcessedCrossChainTx>,
 
-    pub ico_mint: Account<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [b"cross_chain".as_ref(), purchase.buyer_eth_address.as_ref(), &purchase.chain_id.to_le_bytes()],
+        bump,
+        space = 8 + CrossChainAllocation::SPACE
+    )]
+    pub cross_chain_allocation: Account<'info, CrossChainAllocation>,
+
+    /// Referrer bonus ledger - required when the payload has a referrer
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [b"cross_chain_referral".as_ref(), purchase.referrer_eth.as_ref(), &purchase.chain_id.to_le_bytes()],
+        bump,
+        space = 8 + CrossChainReferral::SPACE
+    )]
+    pub referrer_cross_chain_referral: Option<Account<'info, CrossChainReferral>>,
 
+    /// Relayer paying for account creation
     #[account(mut)]
-    pub team_member: Signer<'info>,
+    pub payer: Signer<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
-/// Accounts for viewing team vesting status
+/// Accounts for a committee member attesting a cross-chain purchase
 #[derive(Accounts)]
-pub struct GetTeamVestingStatus<'info> {
+#[instruction(purchase: CrossChainPurchase, attestation_hash: [u8; 32])]
+pub struct AttestCrossChainPurchase<'info> {
+    #[account(mut)]
     pub config: Account<'info, Config>,
 
+    /// Supported chain registry entry
     #[account(
-        seeds = [b"team_vesting", team_vesting.member.as_ref()],
+        mut,
+        seeds = [b"supported_chain".as_ref(), config.key().as_ref(), &purchase.chain_id.to_le_bytes()],
         bump,
     )]
-    pub team_vesting: Account<'info, TeamVesting>,
-}
+    pub supported_chain: Account<'info, SupportedChain>,
 
-// =====================================================
-// CROSS-CHAIN ACCOUNT STRUCTURES
-// =====================================================
+    #[account(
+        seeds = [b"coordinator_committee", config.key().as_ref()],
+        bump,
+    )]
+    pub coordinator_committee: Account<'info, CoordinatorCommittee>,
 
-/// Accounts for coordinator-initiated transfer and vesting stake for EVM buyers
-#[derive(Accounts)]
-#[instruction(buyer_eth_address: [u8; 20], chain_id: u8)]
-pub struct CoordinatorMintAndVestStake<'info> {
-    #[account(mut)]
-    pub config: Account<'info, Config>,
+    /// Pending attestation for this exact purchase data
+    #[account(
+        init_if_needed,
+        payer = attester,
+        seeds = [b"attestation", config.key().as_ref(), attestation_hash.as_ref()],
+        bump,
+        space = 8 + CrossChainAttestation::SPACE
+    )]
+    pub attestation: Account<'info, CrossChainAttestation>,
 
-    /// The cross-chain allocation to track this purchase
+    /// Replay protection record for the EVM purchase event
     #[account(
         init_if_needed,
-        payer = coordinator,
-        seeds = [b"cross_chain", buyer_eth_address.as_ref(), &[chain_id]],
+        payer = attester,
+        seeds = [b"processed_tx".as_ref(), &purchase.chain_id.to_le_bytes(), purchase.tx_hash.as_ref(), &purchase.log_index.to_le_bytes()],
+        bump,
+        space = 8 + ProcessedCrossChainTx::SPACE
+    )]
+    pub processed_tx: Account<'info, ProcessedCrossChainTx>,
+
+    #[account(
+        init_if_needed,
+        payer = attester,
+        seeds = [b"cross_chain".as_ref(), purchase.buyer_eth_address.as_ref(), &purchase.chain_id.to_le_bytes()],
         bump,
         space = 8 + CrossChainAllocation::SPACE
     )]
     pub cross_chain_allocation: Account<'info, CrossChainAllocation>,
 
-    /// Stake account - created for each vesting stake
+    /// Referrer bonus ledger - required when referrer_eth is set
     #[account(
-        init,
-        payer = coordinator,
-        space = 8 + StakeAccount::SPACE
+        init_if_needed,
+        payer = attester,
+        seeds = [b"cross_chain_referral".as_ref(), purchase.referrer_eth.as_ref(), &purchase.chain_id.to_le_bytes()],
+        bump,
+        space = 8 + CrossChainReferral::SPACE
     )]
-    pub stake_account: Account<'info, StakeAccount>,
+    pub referrer_cross_chain_referral: Option<Account<'info, CrossChainReferral>>,
 
-    /// The Solana wallet that will own the staked tokens
-    /// CHECK: This is the beneficiary's Solana address provided by coordinator
-    pub beneficiary: AccountInfo<'info>,
+    #[account(mut)]
+    pub attester: Signer<'info>,
 
-    /// Token mint for NOC
-    pub ico_mint: Account<'info, Mint>,
+    pub system_program: Program<'info, System>,
+}
 
-    /// Program's token treasury - tokens are transferred FROM here
-    /// This is where presale tokens are stored
+#[derive(Accounts)]
+#[instruction(referrer_eth: [u8; 20], chain_id: u64)]
+pub struct LinkCrossChainReferralWallet<'info> {
     #[account(
         mut,
-        seeds = [ico_mint.key().as_ref()],
+        seeds = [b"cross_chain_referral".as_ref(), referrer_eth.as_ref(), &chain_id.to_le_bytes()],
         bump,
     )]
-    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
+    pub cross_chain_referral: Account<'info, CrossChainReferral>,
 
-    /// Stake pool token account (program-owned) - initialized if needed
+    /// Link nonce for the referrer ETH address - incremented on every successful link
     #[account(
         init_if_needed,
-        payer = coordinator,
-        associated_token::mint = ico_mint,
-        associated_token::authority = stake_pool_authority,
+        payer = user,
+        seeds = [b"evm_link_nonce", referrer_eth.as_ref()],
+        bump,
+        space = 8 + EvmLinkNonce::SPACE
     )]
-    pub stake_pool_ata: Account<'info, TokenAccount>,
+    pub evm_link_nonce: Account<'info, EvmLinkNonce>,
 
-    /// Stake pool authority PDA
-    /// CHECK: PDA for stake pool
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimCrossChainReferralRewards<'info> {
     #[account(
-        seeds = [b"stake_pool"],
+        mut,
+        constraint = config.sale_token == ico_mint.key() @ ErrorCode::InvalidAddress
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"cross_chain_referral".as_ref(),
+            cross_chain_referral.referrer_eth.as_ref(),
+            &cross_chain_referral.chain_id.to_le_bytes(),
+        ],
         bump,
     )]
-    pub stake_pool_authority: AccountInfo<'info>,
+    pub cross_chain_referral: Account<'info, CrossChainReferral>,
+
+    #[account(mut)]
+    pub ico_ata_for_ico_program: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub ico_ata_for_user: Account<'info, TokenAccount>,
+
+    pub ico_mint: Account<'info, Mint>,
 
-    /// Coordinator signer
     #[account(mut)]
-    pub coordinator: Signer<'info>,
+    pub user: Signer<'info>,
 
     pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(buyer_eth_address: [u8; 20], chain_id: u8)]
-pub struct RecordCrossChainPurchase<'info> {
+#[instruction(eth_address: [u8; 20], chain_id: u64)]
+pub struct MergeCrossChainAllocation<'info> {
     #[account(mut)]
     pub config: Account<'info, Config>,
 
     #[account(
-        init_if_needed,
-        payer = coordinator,
-        seeds = [b"cross_chain", buyer_eth_address.as_ref(), &[chain_id]],
+        mut,
+        seeds = [b"cross_chain".as_ref(), eth_address.as_ref(), &chain_id.to_le_bytes()],
         bump,
-        space = 8 + CrossChainAllocation::SPACE
     )]
     pub cross_chain_allocation: Account<'info, CrossChainAllocation>,
 
-    /// CHECK: Optional referrer cross-chain allocation
-    #[account(mut)]
-    pub referrer_cross_chain_allocation: UncheckedAccount<'info>,
-
-    #[account(mut)]
-    pub coordinator: Signer<'info>,
-
-    pub system_program: Program<'info, System>,
-}
-
-#[derive(Accounts)]
-#[instruction(eth_address: [u8; 20], chain_id: u8)]
-pub struct LinkSolanaWallet<'info> {
+    /// Referral ledger of the same ETH address - optional, merged when present
     #[account(
         mut,
-        seeds = [b"cross_chain", eth_address.as_ref(), &[chain_id]],
+        seeds = [b"cross_chain_referral".as_ref(), eth_address.as_ref(), &chain_id.to_le_bytes()],
         bump,
     )]
-    pub cross_chain_allocation: Account<'info, CrossChainAllocation>,
+    pub cross_chain_referral: Option<Account<'info, CrossChainReferral>>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        seeds = [b"allocation", user.key().as_ref()],
+        bump,
+        space = 8 + PresaleAllocation::SPACE
+    )]
+    pub user_allocation: Account<'info, PresaleAllocation>,
 
     #[account(mut)]
     pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
@@ -3661,10 +8403,175 @@ pub struct Config {
     pub min_purchase_usd: u64,           // 8 - Min purchase per user in cents (0 = use constant)
     // Treasury for SOL payments (separate from admin for multisig support)
     pub sol_treasury: Pubkey,            // 32 - SOL payments go here (Squads vault)
+    // Two-sided referrals
+    pub referee_bonus_percentage: u64,   // 8 - bonus % for referred buyers on first purchase (0 = disabled)
+    // Volume-tiered referral rates
+    pub referral_tiers: [ReferralTier; MAX_REFERRAL_TIERS], // 16 * 5
+    pub referral_tier_count: u8,         // 1 - 0 = use flat referral_reward_percentage
+    pub coordinator_committee_enabled: bool, // 1 - true = cross-chain purchases require M-of-N attestations
+    pub cross_chain_price_tolerance_bps: u64, // 8 - allowed deviation of reported NOC amounts
+    pub stage_quote_window_seconds: i64, // 8 - previous-stage quotes accepted this long after advancing
+    pub stage_advanced_at: i64,          // 8 - last stage advance timestamp
+    // ICO vault sub-ledgers (staked principal is total_staked)
+    pub vault_presale_unclaimed: u64,    // 8 - sold/awarded allocations not yet claimed
+    pub vault_reward_reserve: u64,       // 8 - reward tokens still in the ICO vault (move to reward vault)
+    pub vault_team_reserved: u64,        // 8 - unclaimed team vesting
+    pub vault_community_reserved: u64,   // 8 - escrowed referral bonuses (pending + cross-chain)
+    // Cross-chain circuit breaker (0 limits = default constants)
+    pub cross_chain_paused: bool,        // 1 - tripped by a rolling limit, admin reset
+    pub cross_chain_paused_at: i64,      // 8
+    pub cross_chain_hourly_limit_usd_cents: u64, // 8
+    pub cross_chain_daily_limit_usd_cents: u64,  // 8
+    pub cross_chain_usage: RollingUsage, // RollingUsage::SPACE - all chains
+    // Coordinator rotation
+    pub next_coordinator: Pubkey,        // 32 - scheduled coordinator (default = none)
+    pub next_coordinator_activates_at: i64, // 8 - next coordinator accepted from here
+    pub coordinator_overlap_seconds: i64, // 8 - current coordinator still accepted this long after activation
+    // Staking reward vault
+    pub reward_budget: u64,              // 8 - funded reward tokens not yet paid out
+    pub total_reward_liability: u64,     // 8 - rewards booked to stakes' pending_rewards, unpaid
+    // Reward pool mode (emission-capped alternative to fixed tier APY)
+    pub reward_pool_enabled: bool,       // 1 - one-way switch
+    pub reward_pool_started_at: i64,     // 8 - earlier time still earns fixed APY
+    pub reward_emission_per_second: u64, // 8 - tokens emitted per second across all stakes
+    pub reward_emission_budget: u64,     // 8 - tokens left to emit (emissions stop at 0)
+    pub reward_tier_weights: [u16; STAKE_TIER_COUNT], // 2 * 3 - relative emission share per staked token
+    pub tier_staked: [u64; STAKE_TIER_COUNT], // 8 * 3 - active principal per tier (pool weighting)
+    pub acc_reward_per_share: u128,      // 16 - rewards per weighted token, scaled by REWARD_POOL_PRECISION
+    pub last_reward_pool_update: i64,    // 8
+    // Early unstake penalty (0 max penalty = early unstake disabled)
+    pub early_unstake_max_penalty_bps: u64, // 8 - penalty at stake start, declining to 0 at unlock
+    pub penalty_burn_bps: u64,           // 8 - share of each penalty burned
+    pub penalty_treasury_bps: u64,       // 8 - share sent to the treasury (rest goes to the tier's stakers)
+    pub tier_penalty_acc_per_share: [u128; STAKE_TIER_COUNT], // 16 * 3 - redistributed penalty per staked token, scaled
+    pub vault_ledger_reconciled: bool,   // 1 - vault sub-ledgers are exact (set at init or by reconcile_vault_ledger)
+}
+
+/// Vault sub-ledger bucket (see Config::vault_committed)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VaultBucket {
+    PresaleUnclaimed,
+    RewardReserve,
+    TeamReserved,
+    CommunityReserved,
 }
 
 impl Config {
-    pub const SPACE: usize = 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 1 + 8 + 8 + 8 + 8 + 8 + 1 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 32 + 8 + 8 + 8 + 32; // Added 32 bytes for sol_treasury
+    pub const SPACE: usize = 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 1 + 8 + 8 + 8 + 8 + 8 + 1 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 32 + 8 + 8 + 8 + 32
+        + 8 // referee_bonus_percentage
+        + ReferralTier::SPACE * MAX_REFERRAL_TIERS + 1 // referral_tiers + referral_tier_count
+        + 1 // coordinator_committee_enabled
+        + 8 + 8 + 8 // cross_chain_price_tolerance_bps + stage_quote_window_seconds + stage_advanced_at
+        + 8 + 8 + 8 + 8 // vault sub-ledgers
+        + 1 + 8 + 8 + 8 + RollingUsage::SPACE // cross-chain circuit breaker
+        + 32 + 8 + 8 // coordinator rotation
+        + 8 + 8 // reward_budget + total_reward_liability
+        + 1 + 8 + 8 + 8 + 2 * STAKE_TIER_COUNT + 8 * STAKE_TIER_COUNT + 16 + 8 // reward pool
+        + 8 + 8 + 8 + 16 * STAKE_TIER_COUNT // early unstake penalty
+        + 1; // vault_ledger_reconciled
+
+    pub fn cross_chain_hourly_limit(&self) -> u64 {
+        if self.cross_chain_hourly_limit_usd_cents == 0 { CROSS_CHAIN_HOURLY_LIMIT_USD_CENTS } else { self.cross_chain_hourly_limit_usd_cents }
+    }
+
+    pub fn cross_chain_daily_limit(&self) -> u64 {
+        if self.cross_chain_daily_limit_usd_cents == 0 { CROSS_CHAIN_DAILY_LIMIT_USD_CENTS } else { self.cross_chain_daily_limit_usd_cents }
+    }
+
+    /// Tokens in the ICO vault committed to a sub-ledger (not withdrawable)
+    pub fn vault_committed(&self) -> Result<u64> {
+        [
+            self.vault_presale_unclaimed,
+            self.vault_reward_reserve,
+            self.vault_team_reserved,
+            self.vault_community_reserved,
+        ]
+        .iter()
+        .try_fold(self.total_staked, |total, bucket| total.checked_add(*bucket))
+        .ok_or(ErrorCode::Overflow.into())
+    }
+
+    /// Vault sub-ledger to credit or debit
+    /// After an upgrade the buckets are only meaningful once reconcile_vault_ledger has run
+    fn vault_bucket_mut(&mut self, bucket: VaultBucket) -> Result<&mut u64> {
+        require!(self.vault_ledger_reconciled, ErrorCode::VaultLedgerNotReconciled);
+        Ok(match bucket {
+            VaultBucket::PresaleUnclaimed => &mut self.vault_presale_unclaimed,
+            VaultBucket::RewardReserve => &mut self.vault_reward_reserve,
+            VaultBucket::TeamReserved => &mut self.vault_team_reserved,
+            VaultBucket::CommunityReserved => &mut self.vault_community_reserved,
+        })
+    }
+
+    /// Booked reward liability not covered by the reward budget (0 = reward vault solvent)
+    pub fn reward_shortfall(&self) -> u64 {
+        self.total_reward_liability.saturating_sub(self.reward_budget)
+    }
+}
+
+/// Rolling USD usage counter: 5-minute buckets over the last hour, hourly buckets over the last day
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct RollingUsage {
+    pub hour_buckets: [u64; RATE_LIMIT_HOUR_BUCKETS], // 8 * 12
+    pub day_buckets: [u64; RATE_LIMIT_DAY_BUCKETS],   // 8 * 24
+    pub last_update: i64,             // 8
+}
+
+impl RollingUsage {
+    pub const SPACE: usize = 8 * RATE_LIMIT_HOUR_BUCKETS + 8 * RATE_LIMIT_DAY_BUCKETS + 8;
+    const HOUR_BUCKET_SECONDS: i64 = 300;
+    const DAY_BUCKET_SECONDS: i64 = 3600;
+
+    /// Expire buckets that fell out of the windows since the last update
+    pub fn roll(&mut self, now: i64) {
+        roll_usage_buckets(&mut self.hour_buckets, Self::HOUR_BUCKET_SECONDS, self.last_update, now);
+        roll_usage_buckets(&mut self.day_buckets, Self::DAY_BUCKET_SECONDS, self.last_update, now);
+        self.last_update = self.last_update.max(now);
+    }
+
+    pub fn hour_total(&self) -> u64 {
+        self.hour_buckets.iter().fold(0u64, |total, b| total.saturating_add(*b))
+    }
+
+    pub fn day_total(&self) -> u64 {
+        self.day_buckets.iter().fold(0u64, |total, b| total.saturating_add(*b))
+    }
+
+    /// Take back usage recorded at `recorded_at` from buckets still in their window (call roll first)
+    pub fn unrecord(&mut self, amount: u64, recorded_at: i64, now: i64) {
+        if now / Self::HOUR_BUCKET_SECONDS - recorded_at / Self::HOUR_BUCKET_SECONDS < RATE_LIMIT_HOUR_BUCKETS as i64 {
+            let hour = ((recorded_at / Self::HOUR_BUCKET_SECONDS) as usize) % RATE_LIMIT_HOUR_BUCKETS;
+            self.hour_buckets[hour] = self.hour_buckets[hour].saturating_sub(amount);
+        }
+        if now / Self::DAY_BUCKET_SECONDS - recorded_at / Self::DAY_BUCKET_SECONDS < RATE_LIMIT_DAY_BUCKETS as i64 {
+            let day = ((recorded_at / Self::DAY_BUCKET_SECONDS) as usize) % RATE_LIMIT_DAY_BUCKETS;
+            self.day_buckets[day] = self.day_buckets[day].saturating_sub(amount);
+        }
+    }
+
+    /// Add usage to the current buckets (call roll first)
+    pub fn record(&mut self, amount: u64, now: i64) -> Result<()> {
+        let hour = ((now / Self::HOUR_BUCKET_SECONDS) as usize) % RATE_LIMIT_HOUR_BUCKETS;
+        let day = ((now / Self::DAY_BUCKET_SECONDS) as usize) % RATE_LIMIT_DAY_BUCKETS;
+        self.hour_buckets[hour] = self.hour_buckets[hour]
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        self.day_buckets[day] = self.day_buckets[day]
+            .checked_add(amount)
+            .ok_or(ErrorCode::Overflow)?;
+        Ok(())
+    }
+}
+
+/// Referral tier: referrers with at least `min_referred_usd_cents` of referred volume earn `percentage`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct ReferralTier {
+    pub min_referred_usd_cents: u64,  // 8
+    pub percentage: u64,              // 8
+}
+
+impl ReferralTier {
+    pub const SPACE: usize = 8 + 8;
 }
 
 #[account]
@@ -3692,10 +8599,70 @@ pub struct PresaleAllocation {
     pub referral_bonus_tokens: u64,   // 8 - bonus from referrals
     pub referrer: Pubkey,             // 32 - who referred them
     pub claimed: bool,                // 1 - claimed at TGE?
+    pub vest_staked_tokens: u64,      // 8 - part of total_tokens delivered as vesting stake (not claimable)
 }
 
 impl PresaleAllocation {
-    pub const SPACE: usize = 32 + 8 + 8 + 4 + 8 + 8 + 8 + 32 + 1;
+    pub const SPACE: usize = 32 + 8 + 8 + 4 + 8 + 8 + 8 + 32 + 1 + 8;
+
+    /// Tokens paid out by a TGE claim (vest-staked purchases were delivered as stake)
+    pub fn claimable_tokens(&self) -> Result<u64> {
+        self.total_tokens
+            .checked_sub(self.vest_staked_tokens)
+            .ok_or(ErrorCode::Overflow.into())
+    }
+}
+
+/// Referral ledger per referrer (aggregated over all referees)
+#[account]
+pub struct ReferrerStats {
+    pub referrer: Pubkey,             // 32
+    pub total_referrals: u64,         // 8 - distinct referees that purchased
+    pub referred_usd_cents: u64,      // 8 - USD volume purchased by referees (cents)
+    pub referred_tokens: u64,         // 8 - tokens purchased by referees
+    pub bonus_tokens: u64,            // 8 - referral bonus tokens earned
+    pub first_referral_at: i64,       // 8
+    pub last_referral_at: i64,        // 8
+    pub bonus_as_stake: bool,         // 1 - add referral bonuses to a vesting stake when possible
+    pub bonus_stake_tier: StakeTier,  // 1 - tier of the vesting stake bonuses are added to
+}
+
+impl ReferrerStats {
+    pub const SPACE: usize = 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1;
+}
+
+/// Referral record per (referrer, referee) pair - a referee is only counted once
+#[account]
+pub struct ReferralRecord {
+    pub referrer: Pubkey,             // 32
+    pub referee: Pubkey,              // 32
+    pub purchase_count: u32,          // 4
+    pub usd_cents: u64,               // 8 - USD purchased by this referee (cents)
+    pub tokens: u64,                  // 8 - tokens purchased by this referee
+    pub bonus_tokens: u64,            // 8 - bonus paid to the referrer for this referee
+    pub first_purchase_at: i64,       // 8
+    pub last_purchase_at: i64,        // 8
+    pub referee_bonus_tokens: u64,    // 8 - bonus paid to the referee
+}
+
+impl ReferralRecord {
+    pub const SPACE: usize = 32 + 32 + 4 + 8 + 8 + 8 + 8 + 8 + 8;
+}
+
+/// Escrowed referral bonuses for a referrer without a PresaleAllocation
+/// Swept into their allocation, or claimed as tokens after TGE
+#[account]
+pub struct PendingReferralRewards {
+    pub referrer: Pubkey,             // 32
+    pub pending_tokens: u64,          // 8 - escrowed and not yet swept/claimed
+    pub total_escrowed: u64,          // 8
+    pub total_swept: u64,             // 8 - moved into the referrer's allocation
+    pub total_claimed: u64,           // 8 - claimed as tokens after TGE
+    pub last_escrowed_at: i64,        // 8
+}
+
+impl PendingReferralRewards {
+    pub const SPACE: usize = 32 + 8 + 8 + 8 + 8 + 8;
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
@@ -3716,14 +8683,58 @@ pub struct StakeAccount {
     pub pending_rewards: u64,         // 8
     pub active: bool,                 // 1
     pub tier: StakeTier,              // 1
-    pub auto_compound: bool,          // 1
+    pub auto_compound: bool,          // 1 - compound harvested rewards into principal
     pub cooldown_start: i64,          // 8 - timestamp when unstake initiated
     pub is_vesting: bool,             // 1 - true if this is a pre-TGE vesting stake
     pub total_added: u64,             // 8 - total tokens ever added to this stake (for tracking multiple purchases)
+    pub reward_debt: u128,            // 16 - pool + penalty-share entitlement at last checkpoint
+    pub unstake_amount: u64,          // 8 - principal in cooldown (0 = whole stake)
+    pub pool_counted: bool,           // 1 - principal counted in config.tier_staked
+    pub penalty_acc_checkpoint: u128, // 16 - tier penalty accumulator at last checkpoint
+    pub unstake_partial: bool,        // 1 - cooldown covers only unstake_amount (false = whole stake)
 }
 
 impl StakeAccount {
-    pub const SPACE: usize = 8 + 32 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 1 + 8 + 1 + 8;
+    pub const SPACE: usize = 8 + 32 + 8 + 8 + 8 + 8 + 8 + 1 + 1 + 1 + 8 + 1 + 8 + 16 + 8 + 1 + 16 + 1;
+
+    /// Principal still earning rewards: everything except a cooldown portion
+    /// (cooldowns started before portions were tracked keep earning on the whole stake)
+    pub fn earning_amount(&self) -> u64 {
+        if self.cooldown_start > 0 {
+            self.amount.saturating_sub(self.unstake_amount)
+        } else {
+            self.amount
+        }
+    }
+}
+
+/// Per-coordinator audit stats for credited cross-chain purchases
+#[account]
+pub struct CoordinatorStats {
+    pub coordinator: Pubkey,          // 32
+    pub purchases_recorded: u64,      // 8 - purchases credited (incl. vesting stakes)
+    pub vest_stakes_created: u64,     // 8 - of which delivered as vesting stakes
+    pub usd_volume_cents: u64,        // 8
+    pub noc_volume: u64,              // 8
+    pub first_recorded_at: i64,       // 8
+    pub last_recorded_at: i64,        // 8
+}
+
+impl CoordinatorStats {
+    pub const SPACE: usize = 32 + 8 + 8 + 8 + 8 + 8 + 8;
+}
+
+/// Per-user index of coordinator-created and migrated legacy stake accounts
+/// Closed stakes are dropped once unstaked with no rewards left pending, freeing their slot
+#[account]
+pub struct UserStakeIndex {
+    pub owner: Pubkey,                // 32
+    pub stakes: [Pubkey; MAX_INDEXED_STAKES], // 32 * 20
+    pub stake_count: u8,              // 1
+}
+
+impl UserStakeIndex {
+    pub const SPACE: usize = 32 + 32 * MAX_INDEXED_STAKES + 1;
 }
 
 // =====================================================
@@ -3734,35 +8745,289 @@ impl StakeAccount {
 #[account]
 pub struct CrossChainAllocation {
     pub eth_address: [u8; 20],        // 20 - Ethereum/BNB address
-    pub chain_id: u8,                 // 1 - 1=Ethereum, 56=BNB, 137=Polygon
+    pub chain_id: u64,                // 8 - EVM chain ID (1=Ethereum, 56=BNB, 42161=Arbitrum, ...)
     pub total_tokens: u64,            // 8 - tokens allocated
     pub total_usd_cents: u64,         // 8 - USD spent in cents
     pub purchase_count: u32,          // 4
     pub first_purchase_at: i64,       // 8
     pub last_purchase_at: i64,        // 8
     pub referrer_eth: [u8; 20],       // 20 - referrer ETH address
-    pub referral_bonus: u64,          // 8 - referral bonus received
+    pub referral_bonus: u64,          // 8 - referral bonus paid to referrer_eth by this buyer
     pub linked_solana_wallet: Pubkey, // 32 - linked Solana wallet for claim
     pub claimed: bool,                // 1
+    pub vest_staked_tokens: u64,      // 8 - tokens delivered via coordinator vesting stakes
+    pub merged_tokens: u64,           // 8 - tokens moved into the linked wallet's PresaleAllocation
+    pub merged_usd_cents: u64,        // 8 - mergeable total_usd_cents already merged
+    pub merged_purchase_count: u32,   // 4 - purchase_count already merged
+    pub merged_at: i64,               // 8 - last merge timestamp
+    pub vest_staked_usd_cents: u64,   // 8 - total_usd_cents spent on vest_staked_tokens (never merged)
 }
 
 impl CrossChainAllocation {
-    pub const SPACE: usize = 20 + 1 + 8 + 8 + 4 + 8 + 8 + 20 + 8 + 32 + 1;
+    pub const SPACE: usize = 20 + 8 + 8 + 8 + 4 + 8 + 8 + 20 + 8 + 32 + 1 + 8 + 8 + 8 + 4 + 8 + 8;
 }
 
-/// Cross-chain referral bonus tracking
+/// Cross-chain referral bonus tracking per (referrer ETH address, chain)
 #[account]
 pub struct CrossChainReferral {
     pub referrer_eth: [u8; 20],       // 20 - referrer ETH address
-    pub chain_id: u8,                 // 1
+    pub chain_id: u64,                // 8
     pub total_bonus: u64,             // 8 - total bonus earned
     pub referral_count: u32,          // 4
     pub linked_solana_wallet: Pubkey, // 32 - linked Solana wallet
-    pub claimed: bool,                // 1
+    pub claimed_amount: u64,          // 8 - bonus already claimed
+    pub first_referral_at: i64,       // 8
+    pub last_referral_at: i64,        // 8
+    pub referred_usd_cents: u64,      // 8 - USD volume purchased by referees (selects the referral tier)
 }
 
 impl CrossChainReferral {
-    pub const SPACE: usize = 20 + 1 + 8 + 4 + 32 + 1;
+    pub const SPACE: usize = 20 + 8 + 8 + 4 + 32 + 8 + 8 + 8 + 8;
+}
+
+/// Supported EVM chain registry entry (admin managed, seeded per config)
+#[account]
+pub struct SupportedChain {
+    pub chain_id: u64,                // 8 - EVM chain ID
+    pub name: String,                 // 4 + 32 - display name
+    pub enabled: bool,                // 1 - purchases accepted
+    pub contract_address: [u8; 20],   // 20 - presale contract on the EVM chain
+    pub min_usd_cents: u64,           // 8 - 0 = CROSS_CHAIN_MIN_USD_CENTS
+    pub max_usd_cents: u64,           // 8 - 0 = CROSS_CHAIN_MAX_USD_CENTS
+    pub max_total_usd_cents: u64,     // 8 - 0 = CROSS_CHAIN_MAX_TOTAL_USD_CENTS
+    pub cooldown_seconds: i64,        // 8 - 0 = CROSS_CHAIN_COOLDOWN_SECONDS
+    pub wormhole_chain: u16,          // 2 - Wormhole chain ID (0 = VAA ingestion disabled)
+    pub emitter_address: [u8; 32],    // 32 - Wormhole emitter address, left-padded
+    pub hourly_limit_usd_cents: u64,  // 8 - 0 = CROSS_CHAIN_CHAIN_HOURLY_LIMIT_USD_CENTS
+    pub daily_limit_usd_cents: u64,   // 8 - 0 = CROSS_CHAIN_CHAIN_DAILY_LIMIT_USD_CENTS
+    pub usage: RollingUsage,          // RollingUsage::SPACE
+}
+
+/// Admin-set parameters of a supported chain (limits set to 0 use the CROSS_CHAIN_* defaults)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct SupportedChainSettings {
+    pub name: String,
+    pub enabled: bool,
+    pub contract_address: [u8; 20],
+    pub min_usd_cents: u64,
+    pub max_usd_cents: u64,
+    pub max_total_usd_cents: u64,
+    pub cooldown_seconds: i64,
+    pub hourly_limit_usd_cents: u64,
+    pub daily_limit_usd_cents: u64,
+}
+
+impl SupportedChain {
+    pub const SPACE: usize = 8 + 4 + MAX_CHAIN_NAME_LEN + 1 + 20 + 8 + 8 + 8 + 8 + 2 + 32
+        + 8 + 8 + RollingUsage::SPACE;
+
+    pub fn hourly_limit(&self) -> u64 {
+        if self.hourly_limit_usd_cents == 0 { CROSS_CHAIN_CHAIN_HOURLY_LIMIT_USD_CENTS } else { self.hourly_limit_usd_cents }
+    }
+
+    pub fn daily_limit(&self) -> u64 {
+        if self.daily_limit_usd_cents == 0 { CROSS_CHAIN_CHAIN_DAILY_LIMIT_USD_CENTS } else { self.daily_limit_usd_cents }
+    }
+
+    pub fn min_usd(&self) -> u64 {
+        if self.min_usd_cents == 0 { CROSS_CHAIN_MIN_USD_CENTS } else { self.min_usd_cents }
+    }
+
+    pub fn max_usd(&self) -> u64 {
+        if self.max_usd_cents == 0 { CROSS_CHAIN_MAX_USD_CENTS } else { self.max_usd_cents }
+    }
+
+    pub fn max_total_usd(&self) -> u64 {
+        if self.max_total_usd_cents == 0 { CROSS_CHAIN_MAX_TOTAL_USD_CENTS } else { self.max_total_usd_cents }
+    }
+
+    pub fn cooldown(&self) -> i64 {
+        if self.cooldown_seconds == 0 { CROSS_CHAIN_COOLDOWN_SECONDS } else { self.cooldown_seconds }
+    }
+}
+
+/// Address family of a non-Solana source chain
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChainFamily {
+    Evm,     // 20-byte address
+    Bitcoin, // witness program / script hash bytes
+    Tron,    // 21-byte address (0x41 prefix)
+    Ton,     // workchain byte + 32-byte account id
+}
+
+/// Chain-agnostic buyer/referrer address (raw bytes, up to MAX_FOREIGN_ADDRESS_LEN)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct ForeignAddress {
+    pub family: ChainFamily,          // 1
+    pub bytes: Vec<u8>,               // 4 + 64
+}
+
+impl ForeignAddress {
+    pub const SPACE: usize = 1 + 4 + MAX_FOREIGN_ADDRESS_LEN;
+
+    pub fn validate(&self) -> Result<()> {
+        let len_ok = match self.family {
+            ChainFamily::Evm => self.bytes.len() == 20,
+            ChainFamily::Tron => self.bytes.len() == 21,
+            ChainFamily::Ton => self.bytes.len() == 33,
+            ChainFamily::Bitcoin => !self.bytes.is_empty() && self.bytes.len() <= MAX_FOREIGN_ADDRESS_LEN,
+        };
+        require!(len_ok, ErrorCode::InvalidForeignAddress);
+        Ok(())
+    }
+
+    /// PDA seed for the address: keccak256(family || bytes)
+    pub fn hash(&self) -> [u8; 32] {
+        keccak::hashv(&[&[self.family as u8], &self.bytes]).to_bytes()
+    }
+}
+
+/// EVM purchase event as reported by the coordinator, committee or Wormhole relayer
+/// (tx_hash, log_index) identifies the event on the source chain and keys replay protection
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CrossChainPurchase {
+    pub buyer_eth_address: [u8; 20],  // 20
+    pub chain_id: u64,                // 8
+    pub noc_amount: u64,              // 8
+    pub usd_cents: u64,               // 8
+    pub tx_hash: [u8; 32],            // 32
+    pub log_index: u32,               // 4
+    pub stage: u8,                    // 1 - stage quoted by the source chain
+    pub referrer_eth: [u8; 20],       // 20 - zero = no referrer
+}
+
+/// Non-EVM purchase event as reported by the coordinator
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Eq, Debug)]
+pub struct ForeignChainPurchase {
+    pub address_hash: [u8; 32],       // 32 - ForeignAddress::hash of the buyer
+    pub chain_id: u64,                // 8
+    pub tx_hash: [u8; 32],            // 32
+    pub log_index: u32,               // 4
+    pub buyer: ForeignAddress,        // ForeignAddress::SPACE
+    pub noc_amount: u64,              // 8
+    pub usd_cents: u64,               // 8
+    pub stage: u8,                    // 1
+    pub referrer: ForeignAddress,     // ForeignAddress::SPACE - empty bytes = no referrer
+}
+
+/// Cross-chain allocation for buyers on non-EVM chains (v2, seeded by config and address hash)
+/// Claim-only: not reversible, not mergeable into a PresaleAllocation and not part of the
+/// referral ledger (the referrer is kept for attribution). EVM buyers keep using CrossChainAllocation
+#[account]
+pub struct ForeignCrossChainAllocation {
+    pub version: u8,                  // 1 - FOREIGN_ALLOCATION_VERSION (0 = uninitialized)
+    pub address: ForeignAddress,      // ForeignAddress::SPACE
+    pub address_hash: [u8; 32],       // 32 - PDA seed
+    pub chain_id: u64,                // 8
+    pub total_tokens: u64,            // 8
+    pub total_usd_cents: u64,         // 8
+    pub purchase_count: u32,          // 4
+    pub first_purchase_at: i64,       // 8
+    pub last_purchase_at: i64,        // 8
+    pub referrer: ForeignAddress,     // ForeignAddress::SPACE - empty bytes = none
+    pub linked_solana_wallet: Pubkey, // 32 - set once by the admin
+    pub claimed: bool,                // 1
+    pub linked_at: i64,               // 8 - claim opens FOREIGN_LINK_TIMELOCK_SECONDS later
+}
+
+impl ForeignCrossChainAllocation {
+    pub const SPACE: usize = 1 + ForeignAddress::SPACE + 32 + 8 + 8 + 8 + 4 + 8 + 8
+        + ForeignAddress::SPACE + 32 + 1 + 8;
+}
+
+/// Pre-migration cross-chain allocation layout (u8 chain id) - read-only, for migration
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct LegacyCrossChainAllocation {
+    pub eth_address: [u8; 20],        // 20
+    pub chain_id: u8,                 // 1
+    pub total_tokens: u64,            // 8
+    pub total_usd_cents: u64,         // 8
+    pub purchase_count: u32,          // 4
+    pub first_purchase_at: i64,       // 8
+    pub last_purchase_at: i64,        // 8
+    pub referrer_eth: [u8; 20],       // 20
+    pub referral_bonus: u64,          // 8
+    pub linked_solana_wallet: Pubkey, // 32
+    pub claimed: bool,                // 1
+}
+
+impl LegacyCrossChainAllocation {
+    pub const SPACE: usize = 20 + 1 + 8 + 8 + 4 + 8 + 8 + 20 + 8 + 32 + 1;
+}
+
+/// Coordinator committee for M-of-N cross-chain purchase attestations
+#[account]
+pub struct CoordinatorCommittee {
+    pub members: [Pubkey; MAX_COMMITTEE_MEMBERS], // 32 * 7
+    pub member_count: u8,             // 1
+    pub threshold: u8,                // 1 - attestations required to credit a purchase
+    pub epoch: u32,                   // 4 - bumped on every committee change
+}
+
+impl CoordinatorCommittee {
+    pub const SPACE: usize = 32 * MAX_COMMITTEE_MEMBERS + 1 + 1 + 4;
+}
+
+/// Pending committee attestation per purchase data hash
+#[account]
+pub struct CrossChainAttestation {
+    pub attestation_hash: [u8; 32],   // 32 - hash of the attested purchase data
+    pub epoch: u32,                   // 4 - committee epoch the attestations belong to
+    pub attesters: [Pubkey; MAX_COMMITTEE_MEMBERS], // 32 * 7
+    pub attestation_count: u8,        // 1
+    pub executed: bool,               // 1 - allocation credited
+    pub created_at: i64,              // 8
+    pub executed_at: i64,             // 8
+}
+
+impl CrossChainAttestation {
+    pub const SPACE: usize = 32 + 4 + 32 * MAX_COMMITTEE_MEMBERS + 1 + 1 + 8 + 8;
+}
+
+/// Processed EVM purchase event per (chain, tx hash, log index) - prevents double crediting
+#[account]
+pub struct ProcessedCrossChainTx {
+    pub chain_id: u64,                // 8
+    pub tx_hash: [u8; 32],            // 32 - EVM transaction hash
+    pub log_index: u32,               // 4 - purchase event log index within the tx
+    pub buyer_eth_address: [u8; 20],  // 20 - non-EVM: first 20 bytes of the foreign address hash
+    pub noc_amount: u64,              // 8
+    pub usd_cents: u64,               // 8
+    pub quoted_stage: u8,             // 1 - stage quoted by the EVM contract
+    pub recorded_stage: u8,           // 1 - Solana stage when recorded (for reconciliation)
+    pub vest_staked: bool,            // 1 - credited via coordinator_mint_and_vest_stake
+    pub processed_at: i64,            // 8
+    pub referral_bonus: u64,          // 8 - referrer bonus paid by this purchase
+    pub reversed_at: i64,             // 8 - set by reverse_cross_chain_purchase
+    pub referred: bool,               // 1 - counted toward the referrer's CrossChainReferral volume
+    pub foreign: bool,                // 1 - non-EVM purchase (ForeignCrossChainAllocation, not reversible)
+}
+
+impl ProcessedCrossChainTx {
+    pub const SPACE: usize = 8 + 32 + 4 + 20 + 8 + 8 + 1 + 1 + 1 + 8 + 8 + 8 + 1 + 1;
+}
+
+/// EVM buyer of a coordinator-created stake (authorizes EVM-signed stake management)
+#[account]
+pub struct EvmStakeLink {
+    pub stake: Pubkey,                // 32
+    pub eth_address: [u8; 20],        // 20
+    pub chain_id: u64,                // 8 - with eth_address, locates the CrossChainAllocation
+}
+
+impl EvmStakeLink {
+    pub const SPACE: usize = 32 + 20 + 8;
+}
+
+/// Per-ETH-address nonce for wallet link and stake action signatures (replay protection)
+#[account]
+pub struct EvmLinkNonce {
+    pub eth_address: [u8; 20],        // 20
+    pub nonce: u64,                   // 8 - nonce expected in the next signed link message
+}
+
+impl EvmLinkNonce {
+    pub const SPACE: usize = 20 + 8;
 }
 
 // =====================================================
@@ -3783,3 +9048,373 @@ pub struct TeamVesting {
 impl TeamVesting {
     pub const SPACE: usize = 32 + 8 + 8 + 8 + 8 + 1;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Hand-built Core Bridge PostedVAA account data
+    fn posted_vaa(emitter_chain: u16, emitter: [u8; 32], payload: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(POSTED_VAA_DISCRIMINATOR);
+        data.push(1); // version
+        data.push(32); // consistency level
+        data.extend_from_slice(&1_700_000_000u32.to_le_bytes()); // vaa_time
+        data.extend_from_slice(&[7u8; 32]); // signature_set
+        data.extend_from_slice(&1_700_000_100u32.to_le_bytes()); // submission_time
+        data.extend_from_slice(&42u32.to_le_bytes()); // nonce
+        data.extend_from_slice(&9u64.to_le_bytes()); // sequence
+        data.extend_from_slice(&emitter_chain.to_le_bytes());
+        data.extend_from_slice(&emitter);
+        data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        data.extend_from_slice(payload);
+        data
+    }
+
+    fn sample_purchase() -> CrossChainPurchase {
+        CrossChainPurchase {
+            buyer_eth_address: [1u8; 20],
+            chain_id: 56,
+            noc_amount: 5_000,
+            usd_cents: 1_234,
+            tx_hash: [9u8; 32],
+            log_index: 3,
+            stage: 3,
+            referrer_eth: [2u8; 20],
+        }
+    }
+
+    /// abi.encodePacked purchase payload as emitted by the EVM presale contract
+    fn purchase_payload(purchase: &CrossChainPurchase) -> Vec<u8> {
+        let mut payload = vec![VAA_PURCHASE_PAYLOAD_ID];
+        payload.extend_from_slice(&purchase.buyer_eth_address);
+        payload.extend_from_slice(&purchase.noc_amount.to_be_bytes());
+        payload.extend_from_slice(&purchase.usd_cents.to_be_bytes());
+        payload.push(purchase.stage);
+        payload.extend_from_slice(&purchase.referrer_eth);
+        payload.extend_from_slice(&purchase.tx_hash);
+        payload.extend_from_slice(&purchase.log_index.to_be_bytes());
+        payload
+    }
+
+    /// Replay PDA as derived by the processed_tx seeds of every cross-chain purchase context
+    fn processed_tx_address(purchase: &CrossChainPurchase) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                b"processed_tx".as_ref(),
+                &purchase.chain_id.to_le_bytes(),
+                purchase.tx_hash.as_ref(),
+                &purchase.log_index.to_le_bytes(),
+            ],
+            &crate::ID,
+        )
+        .0
+    }
+
+    #[test]
+    fn parses_posted_vaa_emitter_and_payload() {
+        let emitter = [0xabu8; 32];
+        let payload = purchase_payload(&sample_purchase());
+        let data = posted_vaa(2, emitter, &payload);
+        assert_eq!(data.len(), POSTED_VAA_HEADER_LEN + payload.len());
+
+        let (chain, address, parsed) = parse_posted_vaa(&data).unwrap();
+        assert_eq!(chain, 2);
+        assert_eq!(address, emitter);
+        assert_eq!(parsed, payload.as_slice());
+    }
+
+    #[test]
+    fn ignores_trailing_bytes_after_payload() {
+        let payload = purchase_payload(&sample_purchase());
+        let mut data = posted_vaa(4, [0u8; 32], &payload);
+        data.extend_from_slice(&[0xffu8; 16]);
+
+        let (_, _, parsed) = parse_posted_vaa(&data).unwrap();
+        assert_eq!(parsed, payload.as_slice());
+    }
+
+    #[test]
+    fn rejects_wrong_discriminator() {
+        let mut data = posted_vaa(2, [0u8; 32], &[VAA_PURCHASE_PAYLOAD_ID]);
+        data[..3].copy_from_slice(b"msg");
+        assert!(parse_posted_vaa(&data).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_header_and_payload() {
+        let payload = purchase_payload(&sample_purchase());
+        let data = posted_vaa(2, [0u8; 32], &payload);
+        assert!(parse_posted_vaa(&data[..POSTED_VAA_HEADER_LEN - 1]).is_err());
+        assert!(parse_posted_vaa(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn decodes_purchase_payload_big_endian() {
+        let expected = CrossChainPurchase {
+            noc_amount: 123_456_789_000,
+            usd_cents: 25_000,
+            log_index: 0x0102_0304,
+            stage: 7,
+            ..sample_purchase()
+        };
+        let payload = purchase_payload(&expected);
+        assert_eq!(payload.len(), VAA_PURCHASE_PAYLOAD_LEN);
+        assert_eq!(&payload[90..94], &[1, 2, 3, 4]);
+
+        let purchase = decode_vaa_purchase_payload(&payload, expected.chain_id).unwrap();
+        assert_eq!(purchase, expected);
+    }
+
+    #[test]
+    fn rejects_unknown_payload_id_and_length() {
+        let mut payload = purchase_payload(&sample_purchase());
+        payload[0] = VAA_PURCHASE_PAYLOAD_ID + 1;
+        assert!(decode_vaa_purchase_payload(&payload, 56).is_err());
+
+        let payload = purchase_payload(&sample_purchase());
+        assert!(decode_vaa_purchase_payload(&payload[..payload.len() - 1], 56).is_err());
+    }
+
+    #[test]
+    fn vaa_and_coordinator_share_replay_key() {
+        // The coordinator reports the same EVM event the VAA carries
+        let reported = sample_purchase();
+        let relayed = decode_vaa_purchase_payload(&purchase_payload(&reported), reported.chain_id).unwrap();
+        assert_eq!(processed_tx_address(&relayed), processed_tx_address(&reported));
+
+        // Another log in the same transaction is a different purchase
+        let next_log = CrossChainPurchase { log_index: reported.log_index + 1, ..reported };
+        assert_ne!(processed_tx_address(&next_log), processed_tx_address(&reported));
+    }
+
+    /// All-zero account state (as freshly allocated), for exercising the pure helpers
+    fn zeroed<T: AnchorDeserialize>(space: usize) -> T {
+        T::deserialize(&mut &vec![0u8; space][..]).unwrap()
+    }
+
+    #[test]
+    fn verifies_cross_chain_price_and_quote_window() {
+        let mut config: Config = zeroed(Config::SPACE);
+        config.current_stage = 2;
+        config.cross_chain_price_tolerance_bps = 100;
+        config.stage_quote_window_seconds = 600;
+        config.stage_advanced_at = 1_000;
+
+        let expected = calculate_tokens_for_usd(10_000, 2).unwrap();
+        assert!(verify_cross_chain_price(&config, expected, 10_000, 2, 1_100).is_ok());
+        assert!(verify_cross_chain_price(&config, expected + expected / 100, 10_000, 2, 1_100).is_ok());
+        assert!(verify_cross_chain_price(&config, expected + expected / 50, 10_000, 2, 1_100).is_err());
+
+        // Previous-stage quotes only within the window after the advance
+        let previous = calculate_tokens_for_usd(10_000, 1).unwrap();
+        assert!(verify_cross_chain_price(&config, previous, 10_000, 1, 1_600).is_ok());
+        assert!(verify_cross_chain_price(&config, previous, 10_000, 1, 1_601).is_err());
+        assert!(verify_cross_chain_price(&config, calculate_tokens_for_usd(10_000, 0).unwrap(), 10_000, 0, 1_100).is_err());
+        assert!(verify_cross_chain_price(&config, calculate_tokens_for_usd(10_000, 3).unwrap(), 10_000, 3, 1_100).is_err());
+    }
+
+    #[test]
+    fn rolls_skipped_usage_buckets() {
+        let mut buckets = [1u64; 4];
+        roll_usage_buckets(&mut buckets, 10, 5, 25);
+        assert_eq!(buckets, [1, 0, 0, 1]);
+
+        roll_usage_buckets(&mut buckets, 10, 25, 20);
+        assert_eq!(buckets, [1, 0, 0, 1]);
+
+        roll_usage_buckets(&mut buckets, 10, 25, 65);
+        assert_eq!(buckets, [0; 4]);
+    }
+
+    #[test]
+    fn rolling_usage_records_expires_and_unrecords() {
+        let mut usage = RollingUsage::default();
+        usage.record(100, 0).unwrap();
+        usage.roll(300);
+        usage.record(50, 300).unwrap();
+        assert_eq!((usage.hour_total(), usage.day_total()), (150, 150));
+
+        // An hour later the first 5-minute bucket has expired, the day window still holds both
+        usage.roll(3_600);
+        assert_eq!((usage.hour_total(), usage.day_total()), (50, 150));
+
+        // Usage outside the hour window is only taken back from the day window
+        usage.unrecord(100, 0, 3_600);
+        assert_eq!((usage.hour_total(), usage.day_total()), (50, 50));
+        usage.unrecord(50, 300, 3_600);
+        assert_eq!((usage.hour_total(), usage.day_total()), (0, 0));
+
+        usage.record(10, 3_600).unwrap();
+        usage.roll(3_600 + 86_400);
+        assert_eq!((usage.hour_total(), usage.day_total()), (0, 0));
+    }
+
+    #[test]
+    fn referral_percentage_uses_highest_reached_tier() {
+        let mut config: Config = zeroed(Config::SPACE);
+        config.referral_reward_percentage = 7;
+        assert_eq!(referral_percentage_for_volume(&config, 50_000_000), 7);
+
+        config.referral_tiers[0] = ReferralTier { min_referred_usd_cents: 0, percentage: 5 };
+        config.referral_tiers[1] = ReferralTier { min_referred_usd_cents: 1_000_000, percentage: 8 };
+        config.referral_tiers[2] = ReferralTier { min_referred_usd_cents: 10_000_000, percentage: 12 };
+        config.referral_tier_count = 3;
+        assert_eq!(referral_percentage_for_volume(&config, 0), 5);
+        assert_eq!(referral_percentage_for_volume(&config, 999_999), 5);
+        assert_eq!(referral_percentage_for_volume(&config, 1_000_000), 8);
+        assert_eq!(referral_percentage_for_volume(&config, 50_000_000), 12);
+
+        config.referral_tiers[2].percentage = MAX_REFERRAL_PERCENTAGE + 5;
+        assert_eq!(referral_percentage_for_volume(&config, 50_000_000), MAX_REFERRAL_PERCENTAGE);
+    }
+
+    #[test]
+    fn early_unstake_penalty_declines_to_unlock() {
+        let mut config: Config = zeroed(Config::SPACE);
+        config.early_unstake_max_penalty_bps = 2_000;
+        let mut stake: StakeAccount = zeroed(StakeAccount::SPACE);
+        stake.start_time = 1_000;
+        stake.lock_period_days = 10;
+        let unlock = 1_000 + 10 * SECONDS_PER_DAY;
+
+        assert_eq!(early_unstake_penalty_bps(&config, &stake, 1_000).unwrap(), 2_000);
+        assert_eq!(early_unstake_penalty_bps(&config, &stake, 0).unwrap(), 2_000);
+        assert_eq!(early_unstake_penalty_bps(&config, &stake, 1_000 + 5 * SECONDS_PER_DAY).unwrap(), 1_000);
+        assert_eq!(early_unstake_penalty_bps(&config, &stake, unlock - 1).unwrap(), 0);
+        assert!(early_unstake_penalty_bps(&config, &stake, unlock).is_err());
+    }
+
+    #[test]
+    fn reward_pool_accumulates_within_budget() {
+        let mut config: Config = zeroed(Config::SPACE);
+        config.reward_emission_per_second = 10;
+        config.reward_emission_budget = 1_000;
+        config.last_reward_pool_update = 100;
+        config.reward_tier_weights = [1, 2, 4];
+        config.tier_staked = [1_000, 0, 0];
+
+        // Disabled pool emits nothing
+        assert_eq!(reward_pool_state_at(&config, 150).unwrap(), (0, 0));
+
+        config.reward_pool_enabled = true;
+        let (acc, emitted) = reward_pool_state_at(&config, 150).unwrap();
+        assert_eq!(emitted, 500);
+        assert_eq!(acc, REWARD_POOL_PRECISION / 2);
+        assert_eq!(reward_pool_state_at(&config, 1_000).unwrap().1, 1_000);
+
+        // No active stake, nothing emitted
+        config.tier_staked = [0; STAKE_TIER_COUNT];
+        assert_eq!(reward_pool_state_at(&config, 150).unwrap(), (0, 0));
+
+        // Entitlement scales with principal and tier weight, plus the penalty accumulator
+        let mut stake: StakeAccount = zeroed(StakeAccount::SPACE);
+        stake.amount = 400;
+        assert_eq!(stake_reward_debt(&config, &stake, acc).unwrap(), 200);
+        stake.tier = StakeTier::TierC;
+        assert_eq!(stake_reward_debt(&config, &stake, acc).unwrap(), 800);
+        config.tier_penalty_acc_per_share[StakeTier::TierC as usize] = REWARD_POOL_PRECISION / 4;
+        assert_eq!(stake_reward_debt(&config, &stake, acc).unwrap(), 900);
+    }
+
+    #[test]
+    fn penalty_share_accrues_since_checkpoint() {
+        let mut config: Config = zeroed(Config::SPACE);
+        config.tier_penalty_acc_per_share[0] = REWARD_POOL_PRECISION / 4;
+        let mut stake: StakeAccount = zeroed(StakeAccount::SPACE);
+        stake.amount = 400;
+
+        // Inactive stakes no longer share penalties
+        assert_eq!(unbooked_penalty_share(&config, &stake).unwrap(), 0);
+
+        stake.active = true;
+        assert_eq!(unbooked_penalty_share(&config, &stake).unwrap(), 100);
+
+        reset_reward_debt(&config, &mut stake).unwrap();
+        assert_eq!(unbooked_penalty_share(&config, &stake).unwrap(), 0);
+        config.tier_penalty_acc_per_share[0] += REWARD_POOL_PRECISION / 2;
+        assert_eq!(unbooked_penalty_share(&config, &stake).unwrap(), 200);
+    }
+
+    #[test]
+    fn cooldown_portion_stops_earning() {
+        let mut config: Config = zeroed(Config::SPACE);
+        config.reward_pool_enabled = true;
+        config.reward_emission_per_second = 10;
+        config.reward_emission_budget = 10_000;
+        config.last_reward_pool_update = 100;
+        config.reward_tier_weights = [1, 1, 1];
+        config.tier_staked = [1_000, 0, 0];
+        config.total_staked = 1_000;
+        config.total_staked_tier_a = 1_000;
+        config.min_stake_amount = 100;
+        let mut stake: StakeAccount = zeroed(StakeAccount::SPACE);
+        stake.amount = 1_000;
+        stake.active = true;
+        stake.pool_counted = true;
+        stake.last_reward_calculation = 100;
+
+        // Rewards up to the cooldown are booked on the whole stake, then the portion leaves the pool
+        begin_unstake_cooldown(&mut config, &mut stake, 600, 150).unwrap();
+        assert_eq!(stake.pending_rewards, 500);
+        assert_eq!(stake.earning_amount(), 400);
+        assert_eq!(config.tier_staked[0], 400);
+        assert!(begin_unstake_cooldown(&mut config, &mut stake, 100, 160).is_err());
+
+        // Emissions during the cooldown go to the remaining principal only
+        assert_eq!(accrued_stake_rewards(&config, &stake, 200).unwrap(), 500);
+        assert_eq!(withdraw_stake_portion(&mut config, &mut stake, 200).unwrap(), 600);
+        assert_eq!(stake.pending_rewards, 1_000);
+        assert_eq!(stake.amount, 400);
+        assert_eq!(config.total_staked, 400);
+        assert_eq!(config.tier_staked[0], 400);
+    }
+
+    /// personal_sign by secret key [0x11; 32] over the "allocation" link message below
+    const LINK_SIGNER: [u8; 20] = [
+        0x19, 0xe7, 0xe3, 0x76, 0xe7, 0xc2, 0x13, 0xb7, 0xe7, 0xe7,
+        0xe4, 0x6c, 0xc7, 0x0a, 0x5d, 0xd0, 0x86, 0xda, 0xff, 0x2a,
+    ];
+    const LINK_SIGNATURE: [u8; 65] = [
+        0x2c, 0x23, 0x59, 0xcd, 0xbb, 0xb1, 0x7d, 0xd3, 0xac, 0x9b, 0x64, 0x06, 0x17, 0x45, 0xc4, 0x4d,
+        0x14, 0xbc, 0x07, 0x12, 0x9a, 0xf6, 0xb7, 0x74, 0xe9, 0xd0, 0x7d, 0x50, 0x46, 0x1c, 0x93, 0x44,
+        0x00, 0x1d, 0xc4, 0xb4, 0xb0, 0x44, 0x9c, 0x6c, 0xae, 0x52, 0x28, 0xf2, 0x9a, 0xb5, 0xf6, 0x33,
+        0xb2, 0xf1, 0x55, 0xe9, 0x46, 0xe1, 0x12, 0x91, 0x9f, 0xd2, 0xeb, 0xc9, 0xfd, 0xb4, 0x46, 0xb8,
+        27,
+    ];
+
+    fn link_message(nonce: u64) -> String {
+        evm_link_message("allocation", LINK_SIGNER, 56, Pubkey::new_from_array([7u8; 32]), nonce, &crate::ID)
+    }
+
+    #[test]
+    fn formats_evm_link_message() {
+        assert_eq!(evm_address_hex(&LINK_SIGNER), "0x19e7e376e7c213b7e7e7e46cc70a5dd086daff2a");
+        assert_eq!(
+            link_message(0),
+            format!(
+                "Noctura presale: link allocation of 0x19e7e376e7c213b7e7e7e46cc70a5dd086daff2a on chain 56 \
+                 to Solana wallet {}\nNonce: 0\nProgram: {}",
+                Pubkey::new_from_array([7u8; 32]),
+                crate::ID
+            )
+        );
+    }
+
+    #[test]
+    fn recovers_eip191_signer() {
+        assert_eq!(recover_evm_signer(&link_message(0), &LINK_SIGNATURE).unwrap(), LINK_SIGNER);
+
+        // Raw recovery id (v = 0/1) is accepted as well
+        let mut raw = LINK_SIGNATURE;
+        raw[64] -= 27;
+        assert_eq!(recover_evm_signer(&link_message(0), &raw).unwrap(), LINK_SIGNER);
+
+        // A replayed signature (stale nonce) does not recover the signer
+        assert_ne!(recover_evm_signer(&link_message(1), &LINK_SIGNATURE).ok(), Some(LINK_SIGNER));
+
+        let mut invalid_v = LINK_SIGNATURE;
+        invalid_v[64] = 29;
+        assert!(recover_evm_signer(&link_message(0), &invalid_v).is_err());
+    }
+}